@@ -121,7 +121,12 @@
 //!                 //  - if not: drop the sample
 //!             }
 //!             ChangeKind::Patch => {
-//!                 println!("Received PATCH for {}: not yet supported", sample.res_name);
+//!                 let _key = sample.res_name;
+//!                 // @TODO:
+//!                 //  - check if timestamp is newer than the stored one for the same key
+//!                 //  - if yes: call utils::patch_sample() with the stored sample (if any)
+//!                 //            and store (key, patched sample)
+//!                 //  - if not: drop the sample
 //!             }
 //!         }
 //!         Ok(())
@@ -135,7 +140,9 @@
 //!         //  - if not: just get the sample with key==path_expr and call: query.reply(sample.clone()).await;
 //!         //  - if yes: get all the samples with key matching path_expr and call for each: query.reply(sample.clone()).await;
 //!         //
-//!         // NOTE: in case query.predicate() is not empty something smarter should be done with returned samples...
+//!         // NOTE: in case query.predicate() contains a filter (e.g. "?x>1&y<2"), only the samples matching
+//!         // this filter should be returned. Use Selector::try_from(&query)?.value_filter()? to get
+//!         // a zenoh::selector::Filter and check each sample with Filter::matches_sample().
 //!         Ok(())
 //!     }
 //! }
@@ -143,9 +150,12 @@
 
 use async_std::sync::{Arc, RwLock};
 use async_trait::async_trait;
+use log::warn;
 use std::convert::TryFrom;
-use zenoh::net::Sample;
-use zenoh::{Properties, Selector, Value, ZError, ZResult};
+use zenoh::net::{ReplyError, Sample};
+use zenoh::selector::Fragment;
+use zenoh::{Properties, Selector, Value, ZError, ZErrorKind, ZResult};
+use zenoh_util::zerror;
 
 pub mod utils;
 
@@ -163,6 +173,13 @@ pub const PROP_STORAGE_PATH_EXPR: &str = "path_expr";
 /// queries' path expression to the stored keys calling [`crate::utils::get_sub_path_exprs()`].
 pub const PROP_STORAGE_PATH_PREFIX: &str = "path_prefix";
 
+/// The `"replication_period"` property key that could be used to enable the replication of a storage
+/// with the other storages having the same `"path_expr"`. Its value is the period (in seconds)
+/// at which the storages exchange a digest of their content in order to repair the missing or stale entries.
+///
+/// The replication is performed by the storages plugin, whatever the backend is.
+pub const PROP_STORAGE_REPLICATION_PERIOD: &str = "replication_period";
+
 /// Trait to be implemented by a Backend.
 ///
 #[async_trait]
@@ -196,6 +213,34 @@ pub trait Storage: Send + Sync {
     /// Function called for each incoming query matching this storage's PathExpression.
     /// This storage should reply with data matching the query calling [`Query::reply()`].
     async fn on_query(&mut self, query: Query) -> ZResult<()>;
+
+    /// Returns some statistics on the data currently stored, that will be added to the status
+    /// sent as a reply to a query on the administration space for this storage.
+    /// By default, no statistics are returned.
+    async fn get_stats(&self) -> Option<StorageStats> {
+        None
+    }
+
+    /// Function called when the properties of this storage are changed at runtime
+    /// (via a PUT on the administration space for this storage). `props` are the new properties.
+    /// Note that the changes of `"path_expr"` and `"replication_period"` properties are handled
+    /// by the storages plugin, and that this function is called only if some other properties changed.
+    /// If the changes can't be applied an error must be returned, and the storage keeps its previous properties.
+    /// By default, the properties can't be changed.
+    async fn reconfigure(&mut self, _props: Properties) -> ZResult<()> {
+        zerror!(ZErrorKind::Other {
+            descr: "this storage doesn't support the change of its properties at runtime".into()
+        })
+    }
+}
+
+/// Statistics on the data stored by a [`Storage`] (see [`Storage::get_stats()`]).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StorageStats {
+    /// The number of keys having a value
+    pub keys: usize,
+    /// The total size of the stored payloads (in bytes)
+    pub bytes: usize,
 }
 
 /// An interceptor allowing to modify the data pushed into a storage before it's actually stored.
@@ -211,10 +256,12 @@ pub trait OutgoingDataInterceptor: Send + Sync {
 }
 
 /// A wrapper around the [`zenoh::net::Query`] allowing to call the
-/// OutgoingDataInterceptor (if any) before to send the reply
+/// OutgoingDataInterceptor (if any) before to send the reply,
+/// and to apply the fragment of the query's selector (if any) to the replied values.
 pub struct Query {
     q: zenoh::net::Query,
     interceptor: Option<Arc<RwLock<Box<dyn OutgoingDataInterceptor>>>>,
+    fragment: Option<Fragment>,
 }
 
 impl Query {
@@ -222,7 +269,21 @@ impl Query {
         q: zenoh::net::Query,
         interceptor: Option<Arc<RwLock<Box<dyn OutgoingDataInterceptor>>>>,
     ) -> Query {
-        Query { q, interceptor }
+        let fragment = match Selector::try_from(&q).and_then(|s| s.value_fragment()) {
+            Ok(fragment) => fragment,
+            Err(e) => {
+                warn!(
+                    "Invalid selector for query on {}{}: {} - fragment ignored",
+                    q.res_name, q.predicate, e
+                );
+                None
+            }
+        };
+        Query {
+            q,
+            interceptor,
+            fragment,
+        }
     }
 
     /// Returns the resource name of this Query
@@ -245,9 +306,27 @@ impl Query {
         } else {
             sample
         };
+        // Keep only the fields selected by the fragment
+        let sample = if let Some(ref fragment) = self.fragment {
+            fragment.project_sample(sample)
+        } else {
+            sample
+        };
         // Send reply
         self.q.reply_async(sample).await
     }
+
+    /// Sends an error as a reply to this Query, e.g. to let the querier know why it can't be answered
+    #[inline(always)]
+    pub fn reply_error(&self, error: ReplyError) {
+        self.q.reply_error(error)
+    }
+
+    /// Returns `true` if the querier cancelled this Query: the storage can stop replying to it
+    #[inline(always)]
+    pub fn is_cancelled(&self) -> bool {
+        self.q.is_cancelled()
+    }
 }
 
 impl TryFrom<&Query> for Selector {
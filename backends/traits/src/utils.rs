@@ -15,6 +15,9 @@
 //! Some useful functions for Backend/Storage implementations.
 
 use zenoh::net::utils::resource_name::*;
+use zenoh::net::{Sample, ZInt};
+use zenoh::{ChangeKind, Value, ZError, ZErrorKind, ZResult};
+use zenoh_util::{zerror, zerror2};
 
 /// Returns the longest prefix in a Path expressions that doesn't contain any '*' character.  
 /// This would be the common prefix of all keys stored in a storage using this Path expression.
@@ -105,6 +108,162 @@ pub fn get_sub_path_exprs<'a>(path_expr: &'a str, prefix: &str) -> Vec<&'a str>
     }
 }
 
+/// Applies a patch to a [`Value`], as a storage must do when receiving a PATCH:
+///  - for a [`Value::Json`], the patch is applied as a JSON Merge Patch (see [RFC 7386](https://tools.ietf.org/html/rfc7386)).
+///  - for a [`Value::Properties`], the patch properties are added to the value, replacing the ones with the same keys.
+///
+/// If there is no value to patch (`None`), the patch is applied to an empty value.
+/// Returns `Err(`[`ZError`]`)` if the patch is not a JSON or a Properties value, or is not of the same type than the value.
+///
+/// # Examples:
+/// ```
+/// # use zenoh::{Properties, Value};
+/// # use zenoh_backend_traits::utils::apply_patch;
+/// let value = Value::Json(r#"{"a":1,"b":{"c":2,"d":3}}"#.into());
+/// let patch = Value::Json(r#"{"a":null,"b":{"c":4},"e":5}"#.into());
+/// match apply_patch(Some(value), patch).unwrap() {
+///     Value::Json(s) => assert_eq!(s, r#"{"b":{"c":4,"d":3},"e":5}"#),
+///     _ => panic!(),
+/// }
+///
+/// let value = Value::Properties(Properties::from("a=1;b=2"));
+/// let patch = Value::Properties(Properties::from("b=3;c=4"));
+/// match apply_patch(Some(value), patch).unwrap() {
+///     Value::Properties(p) => assert_eq!(p, Properties::from("a=1;b=3;c=4")),
+///     _ => panic!(),
+/// }
+/// ```
+pub fn apply_patch(value: Option<Value>, patch: Value) -> ZResult<Value> {
+    match (value, patch) {
+        (None, Value::Json(patch)) => Ok(Value::Json(
+            merge_patch(serde_json::Value::Null, parse_json(&patch)?).to_string(),
+        )),
+        (Some(Value::Json(value)), Value::Json(patch)) => Ok(Value::Json(
+            merge_patch(parse_json(&value)?, parse_json(&patch)?).to_string(),
+        )),
+        (None, Value::Properties(patch)) => Ok(Value::Properties(patch)),
+        (Some(Value::Properties(mut value)), Value::Properties(patch)) => {
+            value.extend(patch.0);
+            Ok(Value::Properties(value))
+        }
+        (Some(value), patch) => zerror!(ZErrorKind::Other {
+            descr: format!(
+                "Can't apply a patch with encoding {} to a value with encoding {}",
+                patch.encoding_descr(),
+                value.encoding_descr()
+            )
+        }),
+        (None, patch) => zerror!(ZErrorKind::Other {
+            descr: format!(
+                "Can't apply a patch with encoding {}: only JSON and Properties are supported",
+                patch.encoding_descr()
+            )
+        }),
+    }
+}
+
+/// Applies a PATCH [`Sample`] to the Sample currently stored for the same path (if any),
+/// returning the Sample (as a PUT) to be stored in place (see [`apply_patch()`]).
+/// The returned Sample has the resource name and the timestamp of the patch.
+pub fn patch_sample(sample: Option<&Sample>, patch: &Sample) -> ZResult<Sample> {
+    let value = match sample {
+        Some(sample) => Value::from_sample(sample, true)?,
+        None => None,
+    };
+    let patch_value = Value::from_sample(patch, true)?.ok_or_else(|| {
+        zerror2!(ZErrorKind::Other {
+            descr: format!("Invalid PATCH on {}: no value", patch.res_name)
+        })
+    })?;
+    let (encoding, payload) = apply_patch(value, patch_value)?.encode();
+    let mut info = patch.data_info.clone().unwrap_or_default();
+    info.kind = Some(ChangeKind::Put as ZInt);
+    info.encoding = Some(encoding);
+    Ok(Sample {
+        res_name: patch.res_name.clone(),
+        payload,
+        data_info: Some(info),
+    })
+}
+
+fn parse_json(s: &str) -> ZResult<serde_json::Value> {
+    serde_json::from_str(s).map_err(|e| {
+        zerror2!(ZErrorKind::ValueDecodingFailed {
+            descr: format!("Invalid JSON: {}", e)
+        })
+    })
+}
+
+// The MergePatch function from RFC 7386
+fn merge_patch(target: serde_json::Value, patch: serde_json::Value) -> serde_json::Value {
+    match patch {
+        serde_json::Value::Object(patch) => {
+            let mut target = match target {
+                serde_json::Value::Object(map) => map,
+                _ => serde_json::Map::new(),
+            };
+            for (name, value) in patch {
+                if value.is_null() {
+                    target.remove(&name);
+                } else {
+                    let old = target.remove(&name).unwrap_or(serde_json::Value::Null);
+                    target.insert(name, merge_patch(old, value));
+                }
+            }
+            serde_json::Value::Object(target)
+        }
+        patch => patch,
+    }
+}
+
+#[test]
+fn test_merge_patch() {
+    // Test cases from RFC 7386, Appendix A
+    let cases = [
+        (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+        (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+        (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+        (
+            r#"{"a":{"b":"c"}}"#,
+            r#"{"a":{"b":"d","c":null}}"#,
+            r#"{"a":{"b":"d"}}"#,
+        ),
+        (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+        (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+        (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+        (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+        (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+        (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"a":1,"e":null}"#),
+        (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+        (
+            r#"{}"#,
+            r#"{"a":{"bb":{"ccc":null}}}"#,
+            r#"{"a":{"bb":{}}}"#,
+        ),
+    ];
+    for (target, patch, result) in cases.iter() {
+        assert_eq!(
+            merge_patch(
+                serde_json::from_str(target).unwrap(),
+                serde_json::from_str(patch).unwrap()
+            ),
+            serde_json::from_str::<serde_json::Value>(result).unwrap(),
+            "merge_patch({}, {})",
+            target,
+            patch
+        );
+    }
+    assert!(apply_patch(
+        Some(Value::Json("{}".into())),
+        Value::Properties(zenoh::Properties::from("a=1"))
+    )
+    .is_err());
+    assert!(apply_patch(None, Value::Integer(1)).is_err());
+}
+
 #[test]
 fn test_get_sub_path_exprs() {
     assert_eq!(
@@ -0,0 +1,315 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use clap::ArgMatches;
+use http_types::Method;
+use std::collections::HashMap;
+use std::str::FromStr;
+use zenoh::net::utils::resource_name;
+
+const ANY: &str = "*";
+const REALM: &str = "zenoh";
+
+/// The reason of a request refusal.
+#[derive(Debug, PartialEq)]
+pub(crate) enum AccessError {
+    /// The request has no valid credentials (HTTP 401)
+    Unauthenticated,
+    /// The authenticated user is not allowed to apply the request's method on its path (HTTP 403)
+    Forbidden,
+}
+
+// A rule of the ACL, allowing a user (or any user if None) to apply some methods
+// (or any method if None) on the paths included in a path expression
+#[derive(Debug)]
+struct AclRule {
+    user: Option<String>,
+    methods: Option<Vec<Method>>,
+    path_expr: String,
+}
+
+impl AclRule {
+    fn allows(&self, user: Option<&str>, method: Method, path: &str) -> bool {
+        self.user.as_deref().iter().all(|u| Some(*u) == user)
+            && self.methods.iter().all(|methods| methods.contains(&method))
+            && resource_name::include(&self.path_expr, path)
+    }
+}
+
+/// The authentication and access control configuration of the REST plugin.
+///
+/// If some users or tokens are configured, each request must be authenticated either via
+/// HTTP Basic authentication with a user's password, or via HTTP Bearer authentication with a user's token.
+/// If some ACL rules are configured, a request is allowed only if a rule allows its user
+/// to apply its method on its path. Otherwise, any (authenticated) request is allowed.
+#[derive(Debug, Default)]
+pub(crate) struct Auth {
+    // password by user, for Basic authentication
+    passwords: HashMap<String, String>,
+    // (token, user) for Bearer authentication
+    tokens: Vec<(String, String)>,
+    acl: Vec<AclRule>,
+}
+
+impl Auth {
+    /// Creates the configuration from the plugin's `--rest-user`, `--rest-token` and `--rest-acl` arguments.
+    /// In ACL rules, the `/@/router/local` prefix is replaced with `/@/router/<pid>`.
+    pub(crate) fn new(args: &ArgMatches, pid: &str) -> Result<Auth, String> {
+        let mut auth = Auth::default();
+        for arg in args.values_of("rest-user").into_iter().flatten() {
+            let (user, password) = split_credentials(arg, "--rest-user", "USER:PASSWORD")?;
+            auth.passwords.insert(user, password);
+        }
+        for arg in args.values_of("rest-token").into_iter().flatten() {
+            let (user, token) = split_credentials(arg, "--rest-token", "USER:TOKEN")?;
+            auth.tokens.push((token, user));
+        }
+        for arg in args.values_of("rest-acl").into_iter().flatten() {
+            auth.acl.push(parse_rule(arg, pid)?);
+        }
+        Ok(auth)
+    }
+
+    fn requires_authentication(&self) -> bool {
+        !self.passwords.is_empty() || !self.tokens.is_empty()
+    }
+
+    /// Returns the value of the `WWW-Authenticate` header to be sent with a 401 response.
+    pub(crate) fn challenge(&self) -> String {
+        let mut challenges = vec![];
+        if !self.passwords.is_empty() {
+            challenges.push(format!(r#"Basic realm="{}""#, REALM));
+        }
+        if !self.tokens.is_empty() {
+            challenges.push(format!(r#"Bearer realm="{}""#, REALM));
+        }
+        challenges.join(", ")
+    }
+
+    /// Checks the credentials in the `authorization` header value (if required),
+    /// and that the authenticated user is allowed to apply `method` on `path`.
+    pub(crate) fn check(
+        &self,
+        authorization: Option<&str>,
+        method: Method,
+        path: &str,
+    ) -> Result<(), AccessError> {
+        let user = self.authenticate(authorization)?;
+        self.authorize(user, method, path)
+    }
+
+    /// Checks the credentials in the `authorization` header value (if required),
+    /// returning the authenticated user (or `None` if no authentication is required).
+    pub(crate) fn authenticate(
+        &self,
+        authorization: Option<&str>,
+    ) -> Result<Option<&str>, AccessError> {
+        if self.requires_authentication() {
+            authorization
+                .and_then(|auth| self.find_user(auth))
+                .map(Some)
+                .ok_or(AccessError::Unauthenticated)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Checks that `user` (as returned by [`Auth::authenticate()`]) is allowed to apply `method` on `path`.
+    pub(crate) fn authorize(
+        &self,
+        user: Option<&str>,
+        method: Method,
+        path: &str,
+    ) -> Result<(), AccessError> {
+        if self.acl.is_empty() || self.acl.iter().any(|rule| rule.allows(user, method, path)) {
+            Ok(())
+        } else {
+            Err(AccessError::Forbidden)
+        }
+    }
+
+    // Returns the user authenticated by the `authorization` header value, if valid
+    fn find_user(&self, authorization: &str) -> Option<&str> {
+        let (scheme, credentials) = authorization.trim().split_once(' ')?;
+        let credentials = credentials.trim();
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = String::from_utf8(base64::decode(credentials).ok()?).ok()?;
+            let (user, password) = decoded.split_once(':')?;
+            let (user, expected) = self.passwords.get_key_value(user)?;
+            if constant_time_eq(password.as_bytes(), expected.as_bytes()) {
+                return Some(user);
+            }
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            return self
+                .tokens
+                .iter()
+                .find(|(token, _)| constant_time_eq(credentials.as_bytes(), token.as_bytes()))
+                .map(|(_, user)| user.as_str());
+        }
+        None
+    }
+}
+
+fn split_credentials(arg: &str, name: &str, expected: &str) -> Result<(String, String), String> {
+    match arg.split_once(':') {
+        Some((user, secret)) if !user.is_empty() && !secret.is_empty() && user != ANY => {
+            Ok((user.to_string(), secret.to_string()))
+        }
+        _ => Err(format!(
+            "Invalid {} argument: '{}' (expected: '{}')",
+            name, arg, expected
+        )),
+    }
+}
+
+fn parse_rule(arg: &str, pid: &str) -> Result<AclRule, String> {
+    let err = || {
+        format!(
+            "Invalid --rest-acl argument: '{}' (expected: 'USER:METHODS:PATH_EXPR')",
+            arg
+        )
+    };
+    let mut split = arg.splitn(3, ':');
+    let (user, methods, path_expr) = match (split.next(), split.next(), split.next()) {
+        (Some(user), Some(methods), Some(path_expr))
+            if !user.is_empty() && path_expr.starts_with('/') =>
+        {
+            (user, methods, path_expr)
+        }
+        _ => return Err(err()),
+    };
+    let methods = if methods == ANY {
+        None
+    } else {
+        Some(
+            methods
+                .split(',')
+                .map(|m| Method::from_str(&m.trim().to_uppercase()).map_err(|_| err()))
+                .collect::<Result<Vec<Method>, String>>()?,
+        )
+    };
+    let path_expr = match path_expr.strip_prefix("/@/router/local") {
+        Some(suffix) => format!("/@/router/{}{}", pid, suffix),
+        None => path_expr.to_string(),
+    };
+    Ok(AclRule {
+        user: if user == ANY {
+            None
+        } else {
+            Some(user.to_string())
+        },
+        methods,
+        path_expr,
+    })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::App;
+    use zenoh_plugin_trait::prelude::*;
+
+    fn new_auth(args: &[&str]) -> Result<Auth, String> {
+        let matches = App::new("test")
+            .args(&crate::RestPlugin::get_requirements())
+            .get_matches_from(std::iter::once("test").chain(args.iter().copied()));
+        Auth::new(&matches, "PID")
+    }
+
+    fn basic(user: &str, password: &str) -> String {
+        format!("Basic {}", base64::encode(format!("{}:{}", user, password)))
+    }
+
+    #[test]
+    fn authentication_and_acl() {
+        let auth = new_auth(&[
+            "--rest-user=alice:secret",
+            "--rest-user=bob:pwd",
+            "--rest-token=robot:t0k3n",
+            "--rest-acl=alice:*:/**",
+            "--rest-acl=*:GET:/demo/**",
+            "--rest-acl=robot:put,delete:/demo/robot/**",
+            "--rest-acl=robot:GET:/@/router/local/**",
+        ])
+        .unwrap();
+        let alice = basic("alice", "secret");
+        let bob = Some(basic("bob", "pwd"));
+        let bob = bob.as_deref();
+
+        // authentication
+        assert_eq!(
+            auth.check(None, Method::Get, "/demo/a"),
+            Err(AccessError::Unauthenticated)
+        );
+        assert_eq!(
+            auth.check(Some(&basic("alice", "wrong")), Method::Get, "/demo/a"),
+            Err(AccessError::Unauthenticated)
+        );
+        assert_eq!(
+            auth.check(Some("Bearer wrong"), Method::Get, "/demo/a"),
+            Err(AccessError::Unauthenticated)
+        );
+        assert_eq!(
+            auth.check(Some(&alice), Method::Delete, "/@/router/x"),
+            Ok(())
+        );
+
+        // access control
+        assert_eq!(auth.check(bob, Method::Get, "/demo/**"), Ok(()));
+        assert_eq!(
+            auth.check(bob, Method::Get, "/**"),
+            Err(AccessError::Forbidden)
+        );
+        assert_eq!(
+            auth.check(bob, Method::Put, "/demo/a"),
+            Err(AccessError::Forbidden)
+        );
+        assert_eq!(
+            auth.check(Some("bearer t0k3n"), Method::Delete, "/demo/robot/1"),
+            Ok(())
+        );
+        assert_eq!(
+            auth.check(Some("Bearer t0k3n"), Method::Patch, "/demo/robot/1"),
+            Err(AccessError::Forbidden)
+        );
+        assert_eq!(
+            auth.check(Some("Bearer t0k3n"), Method::Get, "/@/router/PID/status"),
+            Ok(())
+        );
+        assert_eq!(
+            auth.challenge(),
+            r#"Basic realm="zenoh", Bearer realm="zenoh""#
+        );
+    }
+
+    #[test]
+    fn no_authentication() {
+        let auth = new_auth(&["--rest-acl=*:GET:/**"]).unwrap();
+        assert_eq!(auth.check(None, Method::Get, "/demo/a"), Ok(()));
+        assert_eq!(
+            auth.check(None, Method::Put, "/demo/a"),
+            Err(AccessError::Forbidden)
+        );
+        assert_eq!(
+            Auth::default().check(None, Method::Delete, "/@/router/x"),
+            Ok(())
+        );
+        assert!(new_auth(&["--rest-user=alice"]).is_err());
+        assert!(new_auth(&["--rest-acl=alice:GET"]).is_err());
+        assert!(new_auth(&["--rest-acl=alice:FOO:/**"]).is_err());
+    }
+}
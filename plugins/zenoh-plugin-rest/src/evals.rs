@@ -0,0 +1,545 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+
+//! HTTP evals: queryables answering the zenoh queries by forwarding them to HTTP services.
+//!
+//! An HTTP eval is declared on a path expression with the URL of an HTTP service.
+//! Each query on this path expression is forwarded as an HTTP GET request on the URL, with the
+//! queried resource name appended to the URL path and the query predicate as URL query
+//! (e.g. a query on `/demo/eval/x?(n=1)` is forwarded as `GET http://host:8080/api/demo/eval/x?(n=1)`
+//! for the URL `http://host:8080/api`). The HTTP response is converted into replies:
+//!  - a `204 No Content` or `404 Not Found` response into no reply
+//!  - an `application/x-ndjson` response into one reply for each `{"key": "/...", "value": ...}` line
+//!  - any other successful response into one reply, with the body as value, the `Content-Type` as encoding,
+//!    and as key the `Content-Location` header if present or the queried resource name otherwise
+//!    (the `Content-Location` header is required for a query on a path expression with wildcards).
+//!
+//! The keys of the replies must match the queried path expression. The whole request, including the
+//! reading of the response body, is bounded by the eval's timeout, and the body size by 16 MiB.
+//!
+//! The HTTP evals are managed via the admin space of the REST plugin: a PUT on
+//! `/@/router/<pid>/plugin/rest/eval/<name>` with the `path_expr` and `url` properties declares (or replaces)
+//! an HTTP eval, and a DELETE undeclares it.
+use crate::{enc_from_mime, json_to_payload, NDJSON_MIME};
+use async_std::channel::{bounded, Receiver, Sender};
+use async_std::net::TcpStream;
+use async_std::sync::Arc;
+use async_std::task;
+use clap::ArgMatches;
+use futures::prelude::*;
+use futures::select;
+use http_types::{Method, Request, StatusCode, Url};
+use std::collections::HashMap;
+use std::time::Duration;
+use zenoh::net::queryable::EVAL;
+use zenoh::net::utils::resource_name;
+use zenoh::net::*;
+use zenoh::{ChangeKind, Properties};
+
+const PROP_EVAL_PATH_EXPR: &str = "path_expr";
+const PROP_EVAL_URL: &str = "url";
+const PROP_EVAL_TIMEOUT: &str = "timeout";
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+/// The maximum size (in bytes) of the body of an HTTP eval response.
+pub(crate) const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+const ARG_EVAL_NAME: &str = "rest-eval";
+
+const ADMIN_SUB_INFO: SubInfo = SubInfo {
+    reliability: Reliability::Reliable,
+    mode: SubMode::Push,
+    period: None,
+};
+
+/// The configuration of an HTTP eval.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct EvalConfig {
+    path_expr: String,
+    url: Url,
+    // the timeout of the HTTP requests
+    timeout: Duration,
+}
+
+impl EvalConfig {
+    /// Creates the configuration from the `path_expr`, `url` and optional `timeout` (in seconds) properties.
+    pub(crate) fn new(props: &Properties) -> Result<EvalConfig, String> {
+        let path_expr = match props.get(PROP_EVAL_PATH_EXPR) {
+            Some(path_expr) if path_expr.starts_with('/') => path_expr.clone(),
+            Some(path_expr) => {
+                return Err(format!(
+                    "Invalid {} property: {} (must start with '/')",
+                    PROP_EVAL_PATH_EXPR, path_expr
+                ))
+            }
+            None => return Err(format!("Missing {} property", PROP_EVAL_PATH_EXPR)),
+        };
+        let url = match props.get(PROP_EVAL_URL) {
+            Some(url) => Url::parse(url)
+                .ok()
+                .filter(|url| url.scheme() == "http" && url.host_str().is_some())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid {} property: {} (expected an http:// URL)",
+                        PROP_EVAL_URL, url
+                    )
+                })?,
+            None => return Err(format!("Missing {} property", PROP_EVAL_URL)),
+        };
+        let timeout = match props.get(PROP_EVAL_TIMEOUT) {
+            Some(secs) => secs
+                .parse::<f64>()
+                .ok()
+                .filter(|secs| *secs > 0.0)
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid {} property: {} (expected a positive number of seconds)",
+                        PROP_EVAL_TIMEOUT, secs
+                    )
+                })?,
+            None => Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        };
+        Ok(EvalConfig {
+            path_expr,
+            url,
+            timeout,
+        })
+    }
+
+    fn to_properties(&self) -> Properties {
+        let mut props = Properties::default();
+        props.insert(PROP_EVAL_PATH_EXPR.into(), self.path_expr.clone());
+        props.insert(PROP_EVAL_URL.into(), self.url.to_string());
+        props.insert(
+            PROP_EVAL_TIMEOUT.into(),
+            self.timeout.as_secs_f64().to_string(),
+        );
+        props
+    }
+}
+
+/// Returns the configurations of the HTTP evals specified via the `--rest-eval` arguments,
+/// with their names.
+pub(crate) fn configs_from_args(args: &ArgMatches) -> Result<Vec<(String, EvalConfig)>, String> {
+    args.values_of(ARG_EVAL_NAME)
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, arg)| {
+            let (path_expr, url) = arg.split_once('=').ok_or_else(|| {
+                format!(
+                    "Invalid --{} argument: '{}' (expected: 'PATH_EXPR=URL')",
+                    ARG_EVAL_NAME, arg
+                )
+            })?;
+            let props =
+                Properties::from([(PROP_EVAL_PATH_EXPR, path_expr), (PROP_EVAL_URL, url)].as_ref());
+            let config = EvalConfig::new(&props)
+                .map_err(|e| format!("Invalid --{} argument: {}", ARG_EVAL_NAME, e))?;
+            Ok((format!("{}-{}", ARG_EVAL_NAME, i + 1), config))
+        })
+        .collect()
+}
+
+/// Runs the HTTP evals configured via the arguments, and the ones declared via the admin space.
+pub(crate) async fn run(session: Arc<Session>, pid: String, configs: Vec<(String, EvalConfig)>) {
+    let evals_prefix = format!("/@/router/{}/plugin/rest/eval", pid);
+    // Map owning the handles on the running evals. Once dropped, a handle will stop the eval.
+    let mut eval_handles: HashMap<String, Sender<()>> = HashMap::new();
+    for (name, config) in configs {
+        let admin_path = format!("{}/{}", evals_prefix, name);
+        let handle = start_eval(session.clone(), admin_path.clone(), config);
+        eval_handles.insert(admin_path, handle);
+    }
+
+    // subscribe to PUT/DELETE on 'evals_prefix'/*
+    let admin_selector = ResKey::from(format!("{}/*", evals_prefix));
+    let mut admin = match session
+        .declare_subscriber(&admin_selector, &ADMIN_SUB_INFO)
+        .await
+    {
+        Ok(admin) => admin,
+        Err(e) => {
+            log::error!("Failed to subscribe on {}: {}", admin_selector, e);
+            return;
+        }
+    };
+    while let Some(sample) = admin.receiver().next().await {
+        let admin_path = sample.res_name.clone();
+        let kind = sample
+            .data_info
+            .as_ref()
+            .and_then(|info| info.kind)
+            .map(ChangeKind::from)
+            .unwrap_or(ChangeKind::Put);
+        match kind {
+            ChangeKind::Put => {
+                let props =
+                    Properties::from(String::from_utf8_lossy(&sample.payload.to_vec()).as_ref());
+                match EvalConfig::new(&props) {
+                    Ok(config) => {
+                        // an existing eval with the same name is replaced
+                        let handle = start_eval(session.clone(), admin_path.clone(), config);
+                        eval_handles.insert(admin_path, handle);
+                    }
+                    Err(e) => log::warn!("Can't declare HTTP eval {}: {}", admin_path, e),
+                }
+            }
+            ChangeKind::Delete => {
+                log::debug!("Undeclare HTTP eval {}", admin_path);
+                let _ = eval_handles.remove(&admin_path);
+            }
+            ChangeKind::Patch => log::warn!("PATCH not supported on {}", admin_path),
+        }
+    }
+}
+
+// Starts an HTTP eval, returning the Sender to be dropped to stop it
+fn start_eval(session: Arc<Session>, admin_path: String, config: EvalConfig) -> Sender<()> {
+    let (stop_tx, stop_rx) = bounded::<()>(1);
+    task::spawn(async move {
+        if let Err(e) = serve_eval(&session, &admin_path, config, stop_rx).await {
+            log::error!("Error running HTTP eval {}: {}", admin_path, e);
+        }
+    });
+    stop_tx
+}
+
+async fn serve_eval(
+    session: &Session,
+    admin_path: &str,
+    config: EvalConfig,
+    stop: Receiver<()>,
+) -> ZResult<()> {
+    log::debug!(
+        "Declare HTTP eval {} on {} forwarding to {}",
+        admin_path,
+        config.path_expr,
+        config.url
+    );
+    let config = Arc::new(config);
+    let mut queryable = session
+        .declare_queryable(&config.path_expr.clone().into(), EVAL)
+        .await?;
+    // answer to GET on 'admin_path'
+    let mut admin = session.declare_queryable(&admin_path.into(), EVAL).await?;
+    loop {
+        select!(
+            query = queryable.receiver().next().fuse() => match query {
+                Some(query) => {
+                    task::spawn(forward(query, config.clone()));
+                }
+                None => break,
+            },
+            query = admin.receiver().next().fuse() => {
+                if let Some(query) = query {
+                    let mut info = DataInfo::new();
+                    info.encoding = Some(encoding::APP_PROPERTIES);
+                    query.reply_async(Sample {
+                        res_name: admin_path.to_string(),
+                        payload: config.to_properties().to_string().as_bytes().into(),
+                        data_info: Some(info),
+                    }).await;
+                }
+            },
+            _ = stop.recv().fuse() => break,
+        );
+    }
+    log::debug!("Stop HTTP eval {}", admin_path);
+    admin.undeclare().await?;
+    queryable.undeclare().await
+}
+
+// Forwards a query to the HTTP service, and replies the samples converted from the HTTP response.
+// If the HTTP request fails, the querier is replied an error with the reason.
+async fn forward(query: Query, config: Arc<EvalConfig>) {
+    let url = request_url(&config.url, &query.res_name, &query.predicate);
+    log::trace!("Forward query {:?} to {}", query, url);
+    // the timeout covers both the request and the reading of the response body
+    let result = async_std::future::timeout(config.timeout, async {
+        let response = request(url.clone())
+            .await
+            .map_err(|e| format!("HTTP eval request {} failed: {}", url, e))?;
+        to_samples(&query.res_name, response)
+            .await
+            .map_err(|e| format!("Invalid response to HTTP eval request {}: {}", url, e))
+    })
+    .await;
+    let descr = match result {
+        Ok(Ok(samples)) => {
+            for sample in samples {
+                query.reply_async(sample).await;
+            }
+            return;
+        }
+        Ok(Err(descr)) => descr,
+        Err(_) => format!(
+            "HTTP eval request {} timed out after {:?}",
+            url, config.timeout
+        ),
+    };
+    log::warn!("{}", descr);
+    query.reply_error(ReplyError {
+        res_name: query.res_name.clone(),
+        payload: descr.into_bytes().into(),
+        data_info: None,
+    });
+}
+
+// Returns the URL of the HTTP request forwarding a query
+fn request_url(base: &Url, res_name: &str, predicate: &str) -> Url {
+    let mut url = base.clone();
+    url.set_path(&format!(
+        "{}{}",
+        base.path().trim_end_matches('/'),
+        res_name
+    ));
+    let predicate = predicate.trim_start_matches('?');
+    url.set_query(if predicate.is_empty() {
+        None
+    } else {
+        Some(predicate)
+    });
+    url
+}
+
+async fn request(url: Url) -> http_types::Result<http_types::Response> {
+    let addr = format!(
+        "{}:{}",
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or(80)
+    );
+    let stream = TcpStream::connect(addr).await?;
+    async_h1::connect(stream, Request::new(Method::Get, url)).await
+}
+
+// Converts the response to a forwarded query on `res_name` into reply samples
+async fn to_samples(
+    res_name: &str,
+    mut response: http_types::Response,
+) -> Result<Vec<Sample>, String> {
+    match response.status() {
+        StatusCode::NoContent | StatusCode::NotFound => return Ok(vec![]),
+        status if !status.is_success() => {
+            let body = read_body(&mut response).await.unwrap_or_default();
+            return Err(format!("{} {}", status, String::from_utf8_lossy(&body)));
+        }
+        _ => (),
+    }
+    let content_type = response.content_type();
+    let location = response
+        .header("content-location")
+        .map(|h| h.last().as_str().to_string());
+    let body = read_body(&mut response).await?;
+    if content_type
+        .as_ref()
+        .map(|mime| mime.essence() == NDJSON_MIME)
+        .unwrap_or(false)
+    {
+        String::from_utf8_lossy(&body)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| ndjson_line_to_sample(res_name, line))
+            .collect()
+    } else {
+        // a reply must have a resource name, not a path expression
+        let res_name = match location {
+            Some(location) if is_res_name(&location) && key_matches(res_name, &location) => {
+                location
+            }
+            Some(location) => return Err(format!("invalid Content-Location: {}", location)),
+            None if is_res_name(res_name) => res_name.to_string(),
+            None => return Err("no Content-Location for a query on a path expression".to_string()),
+        };
+        Ok(vec![new_sample(
+            res_name,
+            body,
+            enc_from_mime(content_type),
+        )])
+    }
+}
+
+// Reads the body of a response, failing if it's larger than MAX_BODY_SIZE
+async fn read_body(response: &mut http_types::Response) -> Result<Vec<u8>, String> {
+    if matches!(response.len(), Some(len) if len > MAX_BODY_SIZE) {
+        return Err(format!("body larger than {} bytes", MAX_BODY_SIZE));
+    }
+    let mut body = vec![];
+    response
+        .take_body()
+        .take(MAX_BODY_SIZE as u64 + 1)
+        .read_to_end(&mut body)
+        .await
+        .map_err(|e| e.to_string())?;
+    if body.len() > MAX_BODY_SIZE {
+        return Err(format!("body larger than {} bytes", MAX_BODY_SIZE));
+    }
+    Ok(body)
+}
+
+fn ndjson_line_to_sample(res_name: &str, line: &str) -> Result<Sample, String> {
+    let json: serde_json::Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    match (json["key"].as_str(), &json["value"]) {
+        (Some(key), _) if is_res_name(key) && !key_matches(res_name, key) => Err(format!(
+            "key {} doesn't match the queried path expression {}",
+            key, res_name
+        )),
+        (Some(key), value) if is_res_name(key) && !value.is_null() => {
+            let (payload, encoding) = json_to_payload(value);
+            Ok(new_sample(key.to_string(), payload, encoding))
+        }
+        _ => Err(format!(
+            r#"invalid line (expected: {{"key": "/...", "value": ...}}): {}"#,
+            line
+        )),
+    }
+}
+
+fn is_res_name(s: &str) -> bool {
+    s.starts_with('/') && !s.contains('*')
+}
+
+// Returns true if the resource name `key` matches the queried path expression `res_name`
+fn key_matches(res_name: &str, key: &str) -> bool {
+    resource_name::intersect(res_name, key)
+}
+
+fn new_sample(res_name: String, payload: Vec<u8>, encoding: ZInt) -> Sample {
+    let mut info = DataInfo::new();
+    info.encoding = Some(encoding);
+    Sample {
+        res_name,
+        payload: payload.into(),
+        data_info: Some(info),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_config() {
+        let config = EvalConfig::new(&Properties::from(
+            "path_expr=/demo/eval/**;url=http://host:8080/api",
+        ))
+        .unwrap();
+        assert_eq!(config.timeout, Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+        assert_eq!(EvalConfig::new(&config.to_properties()), Ok(config.clone()));
+        assert_eq!(
+            request_url(&config.url, "/demo/eval/x", "?(n=1)").as_str(),
+            "http://host:8080/api/demo/eval/x?(n=1)"
+        );
+        assert_eq!(
+            request_url(&config.url, "/demo/eval/x", "").as_str(),
+            "http://host:8080/api/demo/eval/x"
+        );
+
+        assert!(EvalConfig::new(&Properties::from("url=http://host:8080")).is_err());
+        assert!(EvalConfig::new(&Properties::from("path_expr=/a;url=ftp://host")).is_err());
+        assert!(EvalConfig::new(&Properties::from("path_expr=/a;url=http://h;timeout=0")).is_err());
+    }
+
+    #[test]
+    fn response_to_samples() {
+        async_std::task::block_on(async {
+            let mut response = http_types::Response::new(StatusCode::Ok);
+            response.set_body("21.5");
+            response.set_content_type(http_types::mime::PLAIN);
+            response.insert_header("content-location", "/demo/eval/temp");
+            let samples = to_samples("/demo/eval/*", response).await.unwrap();
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].res_name, "/demo/eval/temp");
+            assert_eq!(samples[0].payload.to_vec(), b"21.5");
+
+            let mut response = http_types::Response::new(StatusCode::Ok);
+            response.set_body(
+                "{\"key\": \"/a\", \"value\": \"x\"}\n{\"key\": \"/b\", \"value\": {\"y\": 1}}\n",
+            );
+            response.set_content_type(NDJSON_MIME.parse::<http_types::Mime>().unwrap());
+            let samples = to_samples("/**", response).await.unwrap();
+            assert_eq!(samples.len(), 2);
+            assert_eq!(samples[1].res_name, "/b");
+            assert_eq!(samples[1].payload.to_vec(), br#"{"y":1}"#);
+
+            // the keys must match the queried path expression
+            let mut response = http_types::Response::new(StatusCode::Ok);
+            response
+                .set_body("{\"key\": \"/a/x\", \"value\": 1}\n{\"key\": \"/b/y\", \"value\": 2}\n");
+            response.set_content_type(NDJSON_MIME.parse::<http_types::Mime>().unwrap());
+            assert!(to_samples("/a/*", response).await.is_err());
+            let mut response = http_types::Response::new(StatusCode::Ok);
+            response.set_body("21.5");
+            response.insert_header("content-location", "/other/temp");
+            assert!(to_samples("/demo/eval/*", response).await.is_err());
+
+            let mut response = http_types::Response::new(StatusCode::Ok);
+            response.set_body(vec![0u8; MAX_BODY_SIZE + 1]);
+            response.insert_header("content-location", "/demo/eval/big");
+            assert!(to_samples("/demo/eval/*", response).await.is_err());
+
+            let mut response = http_types::Response::new(StatusCode::Ok);
+            response.set_body("21.5");
+            assert!(to_samples("/demo/eval/*", response).await.is_err());
+
+            let response = http_types::Response::new(StatusCode::NotFound);
+            assert!(to_samples("/a", response).await.unwrap().is_empty());
+            let response = http_types::Response::new(StatusCode::InternalServerError);
+            assert!(to_samples("/a", response).await.is_err());
+        })
+    }
+
+    #[test]
+    fn forward_failure() {
+        async_std::task::block_on(async {
+            let mut config = zenoh::net::config::peer();
+            config.insert(
+                zenoh::net::config::ZN_MULTICAST_SCOUTING_KEY,
+                zenoh::net::config::ZN_FALSE.to_string(),
+            );
+            let session = Arc::new(open(config).await.unwrap());
+            // nothing listens on port 1: the HTTP request fails
+            let eval = start_eval(
+                session.clone(),
+                "/@/router/test/plugin/rest/eval/failure".to_string(),
+                EvalConfig::new(&Properties::from(
+                    "path_expr=/demo/failure/**;url=http://127.0.0.1:1;timeout=5",
+                ))
+                .unwrap(),
+            );
+            // wait for the eval to be declared
+            task::sleep(Duration::from_millis(200)).await;
+
+            let mut replies = session
+                .query(
+                    &"/demo/failure/x".into(),
+                    "",
+                    QueryTarget::default(),
+                    QueryConsolidation::none(),
+                )
+                .await
+                .unwrap();
+            let reply = replies.next().await.unwrap();
+            match reply.data {
+                Err(e) => {
+                    assert_eq!(e.res_name, "/demo/failure/x");
+                    let descr = String::from_utf8(e.payload.to_vec()).unwrap();
+                    assert!(descr.contains("failed"), "{}", descr);
+                }
+                Ok(sample) => panic!("unexpected reply: {:?}", sample),
+            }
+            assert!(replies.next().await.is_none());
+            drop(eval);
+        })
+    }
+}
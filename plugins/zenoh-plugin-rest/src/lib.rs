@@ -12,24 +12,51 @@
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
 
+use async_std::io::BufReader;
 use async_std::sync::Arc;
 use clap::{Arg, ArgMatches};
 use futures::prelude::*;
 use http_types::Method;
 use runtime::Runtime;
 use std::convert::TryFrom;
+use std::pin::Pin;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use tide::http::Mime;
 use tide::sse::Sender;
-use tide::{Request, Response, Server, StatusCode};
+use tide::{Body, Request, Response, Server, StatusCode};
 use zenoh::net::*;
 use zenoh::{Change, Selector, Value};
 use zenoh_plugin_trait::prelude::*;
 
+mod auth;
+mod evals;
+mod params;
+use auth::{AccessError, Auth};
+use params::QueryParams;
+mod tls;
+mod ws;
+
 const PORT_SEPARATOR: char = ':';
 const DEFAULT_HTTP_HOST: &str = "0.0.0.0";
 const DEFAULT_HTTP_PORT: &str = "8000";
 
+// The selector properties to paginate the replies to a GET
+const PROP_LIMIT: &str = "limit";
+const PROP_OFFSET: &str = "offset";
+// The selector properties handled by the REST plugin (i.e. not forwarded in the queries' predicate)
+const PLUGIN_PROPS: [&str; 6] = [
+    PROP_LIMIT,
+    PROP_OFFSET,
+    params::PROP_TARGET,
+    params::PROP_TARGET_KIND,
+    params::PROP_CONSOLIDATION,
+    params::PROP_TIMEOUT,
+];
+
+// The MIME type of newline delimited JSON
+const NDJSON_MIME: &str = "application/x-ndjson";
+
 const SSE_SUB_INFO: SubInfo = SubInfo {
     reliability: Reliability::Reliable,
     mode: SubMode::Push,
@@ -105,13 +132,56 @@ fn sample_to_json(sample: Sample) -> String {
     }
 }
 
-async fn to_json(results: ReplyReceiver) -> String {
-    let values = results
-        .filter_map(move |reply| async move { Some(sample_to_json(reply.data)) })
-        .collect::<Vec<String>>()
+// Converts a reply to JSON: its sample, or the error replied by the queryable
+fn reply_to_json(data: Result<Sample, ReplyError>) -> String {
+    match data {
+        Ok(sample) => sample_to_json(sample),
+        Err(error) => serde_json::json!({
+            "key": error.res_name,
+            "error": String::from_utf8_lossy(&error.payload.contiguous()),
+        })
+        .to_string(),
+    }
+}
+
+// The replies of a query, as a stream of text chunks to be sent in a chunked HTTP response body
+type Chunks = Pin<Box<dyn Stream<Item = String> + Send + Sync>>;
+type Replies = Pin<Box<dyn Stream<Item = Reply> + Send + Sync>>;
+
+// Returns the replies received before the timeout, or None if the timeout elapsed before the first reply
+// (the response status can't be changed once the first chunk has been sent)
+async fn with_timeout(mut replies: Replies, timeout: Duration) -> Option<Replies> {
+    let deadline = Instant::now() + timeout;
+    let first = async_std::future::timeout(timeout, replies.next())
         .await
-        .join(",\n");
-    format!("[\n{}\n]\n", values)
+        .ok()?;
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    Some(Box::pin(
+        stream::iter(first)
+            .chain(replies)
+            .take_until(async_std::task::sleep(remaining)),
+    ))
+}
+
+// Streams the replies as a JSON array, each element being sent as soon as received
+fn to_json(replies: impl Stream<Item = Reply> + Send + Sync + 'static) -> Chunks {
+    let values = replies.enumerate().map(|(i, reply)| {
+        if i == 0 {
+            reply_to_json(reply.data)
+        } else {
+            format!(",\n{}", reply_to_json(reply.data))
+        }
+    });
+    Box::pin(
+        stream::once(future::ready("[\n".to_string()))
+            .chain(values)
+            .chain(stream::once(future::ready("\n]\n".to_string()))),
+    )
+}
+
+// Streams the replies as newline delimited JSON (one JSON object per line)
+fn to_ndjson(replies: impl Stream<Item = Reply> + Send + Sync + 'static) -> Chunks {
+    Box::pin(replies.map(|reply| format!("{}\n", reply_to_json(reply.data))))
 }
 
 fn sample_to_html(sample: Sample) -> String {
@@ -122,13 +192,30 @@ fn sample_to_html(sample: Sample) -> String {
     )
 }
 
-async fn to_html(results: ReplyReceiver) -> String {
-    let values = results
-        .filter_map(move |reply| async move { Some(sample_to_html(reply.data)) })
-        .collect::<Vec<String>>()
-        .await
-        .join("\n");
-    format!("<dl>\n{}\n</dl>\n", values)
+fn reply_to_html(data: Result<Sample, ReplyError>) -> String {
+    match data {
+        Ok(sample) => sample_to_html(sample),
+        Err(error) => format!(
+            "<dt>{}</dt>\n<dd class=\"error\">{}</dd>\n",
+            error.res_name,
+            String::from_utf8_lossy(&error.payload.contiguous())
+        ),
+    }
+}
+
+fn to_html(replies: impl Stream<Item = Reply> + Send + Sync + 'static) -> Chunks {
+    let values = replies.enumerate().map(|(i, reply)| {
+        if i == 0 {
+            reply_to_html(reply.data)
+        } else {
+            format!("\n{}", reply_to_html(reply.data))
+        }
+    });
+    Box::pin(
+        stream::once(future::ready("<dl>\n".to_string()))
+            .chain(values)
+            .chain(stream::once(future::ready("\n</dl>\n".to_string()))),
+    )
 }
 
 fn enc_from_mime(mime: Option<Mime>) -> ZInt {
@@ -145,6 +232,14 @@ fn enc_from_mime(mime: Option<Mime>) -> ZInt {
     }
 }
 
+// Converts a JSON value to a payload: a string as text/plain, any other value as application/json
+fn json_to_payload(value: &serde_json::Value) -> (Vec<u8>, ZInt) {
+    match value {
+        serde_json::Value::String(s) => (s.as_bytes().to_vec(), encoding::TEXT_PLAIN),
+        value => (value.to_string().into_bytes(), encoding::APP_JSON),
+    }
+}
+
 fn method_to_kind(method: Method) -> ZInt {
     match method {
         Method::Put => data_kind::PUT,
@@ -163,6 +258,100 @@ fn response(status: StatusCode, content_type: Mime, body: &str) -> Response {
         .build()
 }
 
+// Returns a response with a body sent using chunked transfer encoding, as the chunks are produced
+fn streamed_response(content_type: Mime, chunks: Chunks) -> Response {
+    let reader = chunks
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.into_bytes()))
+        .into_async_read();
+    Response::builder(StatusCode::Ok)
+        .header("Access-Control-Allow-Origin", "*")
+        .content_type(content_type)
+        .body(Body::from_reader(BufReader::new(reader), None))
+        .build()
+}
+
+// Returns the value of an optional non-negative integer property of the selector
+fn get_usize_prop(selector: &Selector, key: &str) -> Result<Option<usize>, String> {
+    match selector.properties.get(key) {
+        Some(s) => s.parse::<usize>().map(Some).map_err(|_| {
+            format!(
+                "Invalid '{}' property: {} (expected a non-negative integer)",
+                key, s
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+// Returns the predicate of the selector without the properties handled by the REST plugin
+fn forwarded_predicate(selector: &Selector) -> String {
+    if !PLUGIN_PROPS
+        .iter()
+        .any(|prop| selector.properties.contains_key(*prop))
+    {
+        return selector.predicate.clone();
+    }
+    let mut properties = selector.properties.clone();
+    for prop in PLUGIN_PROPS.iter() {
+        properties.remove(*prop);
+    }
+    let mut predicate = String::new();
+    if selector.filter.is_some() || !properties.is_empty() {
+        predicate.push('?');
+        if let Some(filter) = &selector.filter {
+            predicate.push_str(filter);
+        }
+        if !properties.is_empty() {
+            predicate.push_str(&format!("({})", properties));
+        }
+    }
+    if let Some(fragment) = &selector.fragment {
+        predicate.push_str(&format!("[{}]", fragment));
+    }
+    predicate
+}
+
+// The state shared by the HTTP requests handlers: the zenoh-net session, the router's pid
+// and the authentication and access control configuration
+type State = (Arc<Session>, String, Arc<Auth>);
+
+// Returns an error response if the request isn't allowed on the resource
+fn check_access(req: &Request<State>, resource: &ResKey) -> Option<Response> {
+    let authorization = req.header("authorization").map(|h| h.last().as_str());
+    match req
+        .state()
+        .2
+        .check(authorization, req.method(), &resource.to_string())
+    {
+        Ok(()) => None,
+        Err(e) => Some(access_denied(req, e, &resource.to_string())),
+    }
+}
+
+// Returns the response to a request refused on a path
+fn access_denied(req: &Request<State>, error: AccessError, path: &str) -> Response {
+    match error {
+        AccessError::Unauthenticated => {
+            log::debug!("Unauthenticated {} request on {}", req.method(), path);
+            let mut response = response(
+                StatusCode::Unauthorized,
+                Mime::from_str("text/plain").unwrap(),
+                "Authentication required",
+            );
+            response.insert_header("WWW-Authenticate", req.state().2.challenge());
+            response
+        }
+        AccessError::Forbidden => {
+            log::debug!("Forbidden {} request on {}", req.method(), path);
+            response(
+                StatusCode::Forbidden,
+                Mime::from_str("text/plain").unwrap(),
+                "Access denied",
+            )
+        }
+    }
+}
+
 zenoh_plugin_trait::declare_plugin!(RestPlugin);
 pub struct RestPlugin {}
 #[derive(Clone, Copy, Debug)]
@@ -190,6 +379,56 @@ impl Plugin for RestPlugin {
         vec![
             Arg::from_usage("--rest-http-port 'The REST plugin's http port'")
                 .default_value(DEFAULT_HTTP_PORT),
+            Arg::from_usage(
+                "--rest-user=[USER:PASSWORD]... \
+                'A user allowed to authenticate to the REST plugin with HTTP Basic authentication. \
+                Repeat this option to specify several users. \
+                If some users or tokens are specified, all the requests must be authenticated.'",
+            )
+            .use_delimiter(false),
+            Arg::from_usage(
+                "--rest-token=[USER:TOKEN]... \
+                'A token allowed to authenticate a user to the REST plugin with HTTP Bearer authentication. \
+                Repeat this option to specify several tokens.'",
+            )
+            .use_delimiter(false),
+            Arg::from_usage(
+                "--rest-acl=[USER:METHODS:PATH_EXPR]... \
+                'A rule allowing USER (or any user if *) to use the comma-separated METHODS \
+                (or any method if *) on the paths included in PATH_EXPR via the REST plugin. \
+                Repeat this option to specify several rules. \
+                If some rules are specified, the requests not allowed by any rule are denied.'",
+            )
+            .use_delimiter(false),
+            Arg::from_usage(
+                "--rest-https \
+                'Serve the REST API over HTTPS (on the --rest-http-port). \
+                By default, the server certificate and private key are the ones configured for zenoh TLS links \
+                (tls_server_certificate and tls_server_private_key).'",
+            ),
+            Arg::from_usage(
+                "--rest-tls-certificate=[FILE] \
+                'The PEM file of the REST plugin HTTPS server certificate.'",
+            )
+            .requires("rest-https"),
+            Arg::from_usage(
+                "--rest-tls-private-key=[FILE] \
+                'The PEM file of the REST plugin HTTPS server private key.'",
+            )
+            .requires("rest-https"),
+            Arg::from_usage(
+                "--rest-tls-client-ca=[FILE] \
+                'The PEM file of a CA certificate. If specified, the HTTPS clients must present \
+                a certificate signed by this CA.'",
+            )
+            .requires("rest-https"),
+            Arg::from_usage(
+                "--rest-eval=[PATH_EXPR=URL]... \
+                'Declare an eval on PATH_EXPR answering the queries by forwarding them \
+                as HTTP GET requests to URL (with the queried resource name appended to the URL path). \
+                Repeat this option to specify several evals.'",
+            )
+            .use_delimiter(false),
         ]
     }
 
@@ -201,15 +440,40 @@ impl Plugin for RestPlugin {
                 err: "No --rest-http-port argument found",
             })),
             Some(port) => {
-                async_std::task::spawn(run(runtime.clone(), port.to_owned()));
-                Ok(Box::new(()))
+                let config = Auth::new(args, &runtime.get_pid_str()).and_then(|auth| {
+                    let tls_config = tls::server_config(args, &runtime.config)?;
+                    let evals = evals::configs_from_args(args)?;
+                    Ok((auth, tls_config, evals))
+                });
+                match config {
+                    Ok((auth, tls_config, evals)) => {
+                        async_std::task::spawn(run(
+                            runtime.clone(),
+                            port.to_owned(),
+                            auth,
+                            tls_config,
+                            evals,
+                        ));
+                        Ok(Box::new(()))
+                    }
+                    Err(e) => {
+                        // see run() about logs initialization
+                        let _ = env_logger::try_init();
+                        log::error!("Can't start the REST plugin: {}", e);
+                        Err(e.into())
+                    }
+                }
             }
         }
     }
 }
 
-async fn query(req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
+async fn query(req: Request<State>) -> tide::Result<Response> {
     log::trace!("Incoming GET request: {:?}", req);
+    if ws::is_upgrade(&req) {
+        // the access to each path is checked for each request sent over the WebSocket
+        return ws::upgrade(req).await;
+    }
     // Reconstruct Selector from req.url() (no easier way...)
     let url = req.url();
     let mut s = String::with_capacity(url.as_str().len());
@@ -241,10 +505,15 @@ async fn query(req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
             .to_string(),
         None => "application/json".to_string(),
     };
+    let resource = path_to_resource(selector.path_expr.as_str(), &req.state().1);
+    if let Some(response) = check_access(&req, &resource) {
+        return Ok(response);
+    }
+
     if first_accept == "text/event-stream" {
         Ok(tide::sse::upgrade(
             req,
-            move |req: Request<(Arc<Session>, String)>, sender: Sender| async move {
+            move |req: Request<State>, sender: Sender| async move {
                 let resource = path_to_resource(req.url().path(), &req.state().1);
                 async_std::task::spawn(async move {
                     log::debug!(
@@ -290,36 +559,71 @@ async fn query(req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
             },
         ))
     } else {
-        let resource = path_to_resource(selector.path_expr.as_str(), &req.state().1);
-        let consolidation = if selector.has_time_range() {
-            QueryConsolidation::none()
-        } else {
-            QueryConsolidation::default()
+        let (offset, limit) = match (
+            get_usize_prop(&selector, PROP_OFFSET),
+            get_usize_prop(&selector, PROP_LIMIT),
+        ) {
+            (Ok(offset), Ok(limit)) => (offset.unwrap_or(0), limit.unwrap_or(usize::MAX)),
+            (Err(e), _) | (_, Err(e)) => {
+                return Ok(response(
+                    StatusCode::BadRequest,
+                    Mime::from_str("text/plain").unwrap(),
+                    &e,
+                ))
+            }
+        };
+        let params = match QueryParams::new(&selector, |name| {
+            req.header(name).map(|h| h.last().as_str())
+        }) {
+            Ok(params) => params,
+            Err(e) => {
+                return Ok(response(
+                    StatusCode::BadRequest,
+                    Mime::from_str("text/plain").unwrap(),
+                    &e,
+                ))
+            }
         };
         match req
             .state()
             .0
             .query(
                 &resource,
-                &selector.predicate,
-                QueryTarget::default(),
-                consolidation,
+                &forwarded_predicate(&selector),
+                params.target,
+                params.consolidation,
             )
             .await
         {
             Ok(receiver) => {
-                if first_accept == "text/html" {
-                    Ok(response(
-                        StatusCode::Ok,
+                // the replies are sent as they arrive, without buffering them
+                let replies: Replies = Box::pin(receiver.skip(offset).take(limit));
+                let replies = match params.timeout {
+                    Some(timeout) => match with_timeout(replies, timeout).await {
+                        Some(replies) => replies,
+                        None => {
+                            return Ok(response(
+                                StatusCode::GatewayTimeout,
+                                Mime::from_str("text/plain").unwrap(),
+                                &format!("No reply received before the timeout ({:?})", timeout),
+                            ))
+                        }
+                    },
+                    None => replies,
+                };
+                match first_accept.as_str() {
+                    "text/html" => Ok(streamed_response(
                         Mime::from_str("text/html").unwrap(),
-                        &to_html(receiver).await,
-                    ))
-                } else {
-                    Ok(response(
-                        StatusCode::Ok,
+                        to_html(replies),
+                    )),
+                    NDJSON_MIME => Ok(streamed_response(
+                        Mime::from_str(NDJSON_MIME).unwrap(),
+                        to_ndjson(replies),
+                    )),
+                    _ => Ok(streamed_response(
                         Mime::from_str("application/json").unwrap(),
-                        &to_json(receiver).await,
-                    ))
+                        to_json(replies),
+                    )),
                 }
             }
             Err(e) => Ok(response(
@@ -331,11 +635,14 @@ async fn query(req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
     }
 }
 
-async fn write(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
+async fn write(mut req: Request<State>) -> tide::Result<Response> {
     log::trace!("Incoming PUT request: {:?}", req);
+    let resource = path_to_resource(req.url().path(), &req.state().1);
+    if let Some(response) = check_access(&req, &resource) {
+        return Ok(response);
+    }
     match req.body_bytes().await {
         Ok(bytes) => {
-            let resource = path_to_resource(req.url().path(), &req.state().1);
             match req
                 .state()
                 .0
@@ -364,7 +671,13 @@ async fn write(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Respons
     }
 }
 
-pub async fn run(runtime: Runtime, port: String) {
+async fn run(
+    runtime: Runtime,
+    port: String,
+    auth: Auth,
+    tls_config: Option<tls::ServerConfig>,
+    evals: Vec<(String, evals::EvalConfig)>,
+) {
     // Try to initiate login.
     // Required in case of dynamic lib, otherwise no logs.
     // But cannot be done twice in case of static link.
@@ -375,7 +688,10 @@ pub async fn run(runtime: Runtime, port: String) {
     let pid = runtime.get_pid_str();
     let session = Session::init(runtime, true, vec![], vec![]).await;
 
-    let mut app = Server::with_state((Arc::new(session), pid));
+    let session = Arc::new(session);
+    async_std::task::spawn(evals::run(session.clone(), pid.clone(), evals));
+
+    let mut app = Server::with_state((session, pid, Arc::new(auth)));
     app.with(
         tide::security::CorsMiddleware::new()
             .allow_methods(
@@ -399,7 +715,11 @@ pub async fn run(runtime: Runtime, port: String) {
     app.at("/").delete(write);
     app.at("*").delete(write);
 
-    if let Err(e) = app.listen(http_port).await {
+    let result = match tls_config {
+        Some(tls_config) => tls::listen(app, http_port, tls_config).await,
+        None => app.listen(http_port).await,
+    };
+    if let Err(e) = result {
         log::error!("Unable to start http server for REST : {:?}", e);
     }
 }
@@ -0,0 +1,220 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use std::time::Duration;
+use zenoh::net::queryable::{ALL_KINDS, EVAL, STORAGE};
+use zenoh::net::{ConsolidationMode, QueryConsolidation, QueryTarget, Target, ZInt};
+use zenoh::Selector;
+
+pub(crate) const PROP_TARGET: &str = "target";
+pub(crate) const PROP_TARGET_KIND: &str = "target_kind";
+pub(crate) const PROP_CONSOLIDATION: &str = "consolidation";
+pub(crate) const PROP_TIMEOUT: &str = "timeout";
+
+// The HTTP header that can be used instead of each selector property
+const HEADERS: [(&str, &str); 4] = [
+    (PROP_TARGET, "x-zenoh-query-target"),
+    (PROP_TARGET_KIND, "x-zenoh-query-target-kind"),
+    (PROP_CONSOLIDATION, "x-zenoh-query-consolidation"),
+    (PROP_TIMEOUT, "x-zenoh-query-timeout"),
+];
+
+/// The parameters of a query sent by the REST plugin.
+#[derive(Debug, PartialEq)]
+pub(crate) struct QueryParams {
+    pub(crate) target: QueryTarget,
+    pub(crate) consolidation: QueryConsolidation,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl QueryParams {
+    /// Returns the parameters specified via the selector properties, or otherwise via the
+    /// HTTP headers (returned by `header` for a name).
+    ///
+    /// By default, the query targets the best matching queryables of any kind, and isn't consolidated
+    /// if the selector has a time range (to get all the values in this range).
+    pub(crate) fn new<'a>(
+        selector: &'a Selector,
+        header: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<QueryParams, String> {
+        let get = |prop: &str| {
+            selector
+                .properties
+                .get(prop)
+                .map(String::as_str)
+                .or_else(|| {
+                    HEADERS
+                        .iter()
+                        .find(|(p, _)| *p == prop)
+                        .and_then(|(_, name)| header(name))
+                })
+        };
+        let target = QueryTarget {
+            kind: match get(PROP_TARGET_KIND) {
+                Some(s) => parse_kind(s).ok_or_else(|| {
+                    invalid(
+                        PROP_TARGET_KIND,
+                        s,
+                        "'all', 'storage', 'eval' or 'storage,eval'",
+                    )
+                })?,
+                None => ALL_KINDS,
+            },
+            target: match get(PROP_TARGET) {
+                Some(s) => parse_target(s).ok_or_else(|| {
+                    invalid(
+                        PROP_TARGET,
+                        s,
+                        "'best_matching', 'all', 'all_complete' or 'none'",
+                    )
+                })?,
+                None => Target::default(),
+            },
+        };
+        let consolidation = match get(PROP_CONSOLIDATION) {
+            Some(s) => parse_consolidation(s).ok_or_else(|| {
+                invalid(
+                    PROP_CONSOLIDATION,
+                    s,
+                    "'none', 'lazy' or 'full' for all the hops, or 3 comma-separated modes \
+                    for the first routers, the last router and the reception",
+                )
+            })?,
+            None if selector.has_time_range() => QueryConsolidation::none(),
+            None => QueryConsolidation::default(),
+        };
+        let timeout = match get(PROP_TIMEOUT) {
+            Some(s) => Some(
+                s.parse::<f64>()
+                    .ok()
+                    .filter(|secs| *secs > 0.0)
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .ok_or_else(|| invalid(PROP_TIMEOUT, s, "a positive number of seconds"))?,
+            ),
+            None => None,
+        };
+        Ok(QueryParams {
+            target,
+            consolidation,
+            timeout,
+        })
+    }
+}
+
+fn invalid(prop: &str, value: &str, expected: &str) -> String {
+    format!(
+        "Invalid '{}' parameter: {} (expected {})",
+        prop, value, expected
+    )
+}
+
+fn parse_kind(s: &str) -> Option<ZInt> {
+    s.split(',').try_fold(0, |kind, s| match s.trim() {
+        "all" => Some(ALL_KINDS),
+        "storage" if kind != ALL_KINDS => Some(kind | STORAGE),
+        "eval" if kind != ALL_KINDS => Some(kind | EVAL),
+        _ => None,
+    })
+}
+
+fn parse_target(s: &str) -> Option<Target> {
+    match s.trim() {
+        "best_matching" => Some(Target::BestMatching),
+        "all" => Some(Target::All),
+        "all_complete" => Some(Target::Complete { n: ZInt::MAX }),
+        "none" => Some(Target::None),
+        _ => None,
+    }
+}
+
+fn parse_consolidation(s: &str) -> Option<QueryConsolidation> {
+    let modes = s
+        .split(',')
+        .map(|s| match s.trim() {
+            "none" => Some(ConsolidationMode::None),
+            "lazy" => Some(ConsolidationMode::Lazy),
+            "full" => Some(ConsolidationMode::Full),
+            _ => None,
+        })
+        .collect::<Option<Vec<ConsolidationMode>>>()?;
+    match modes.as_slice() {
+        [mode] => Some(QueryConsolidation {
+            first_routers: *mode,
+            last_router: *mode,
+            reception: *mode,
+        }),
+        [first_routers, last_router, reception] => Some(QueryConsolidation {
+            first_routers: *first_routers,
+            last_router: *last_router,
+            reception: *reception,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn params(selector: &str, headers: &[(&str, &str)]) -> Result<QueryParams, String> {
+        let selector = Selector::try_from(selector).unwrap();
+        QueryParams::new(&selector, |name| {
+            headers.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+        })
+    }
+
+    #[test]
+    fn query_params() {
+        let default = params("/demo/**", &[]).unwrap();
+        assert_eq!(default.target, QueryTarget::default());
+        assert_eq!(default.consolidation, QueryConsolidation::default());
+        assert_eq!(default.timeout, None);
+        assert_eq!(
+            params("/demo/**?(starttime=now()-1h)", &[])
+                .unwrap()
+                .consolidation,
+            QueryConsolidation::none()
+        );
+
+        let p = params(
+            "/demo/**?(target=all;target_kind=storage,eval;consolidation=none,lazy,full)",
+            &[
+                ("x-zenoh-query-timeout", "0.5"),
+                ("x-zenoh-query-target", "none"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            p.target,
+            QueryTarget {
+                kind: STORAGE | EVAL,
+                target: Target::All
+            }
+        );
+        assert_eq!(
+            p.consolidation,
+            QueryConsolidation {
+                first_routers: ConsolidationMode::None,
+                last_router: ConsolidationMode::Lazy,
+                reception: ConsolidationMode::Full,
+            }
+        );
+        assert_eq!(p.timeout, Some(Duration::from_millis(500)));
+
+        assert!(params("/demo/**?(target=some)", &[]).is_err());
+        assert!(params("/demo/**?(consolidation=lazy,full)", &[]).is_err());
+        assert!(params("/demo/**", &[("x-zenoh-query-timeout", "-1")]).is_err());
+        assert!(params("/demo/**?(target_kind=all,eval)", &[]).is_err());
+    }
+}
@@ -0,0 +1,506 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+
+//! A WebSocket endpoint multiplexing subscriptions, publications and queries over one connection.
+//!
+//! A WebSocket connection can be opened on any path of the REST API. The client sends JSON requests
+//! in text messages, each with an `"op"` and an `"id"` (any JSON value, echoed in the related messages):
+//!  - `{"op": "subscribe", "id": 1, "path": "/demo/**"}`: replied with `{"op": "ok", "id": 1}`,
+//!    then each received sample is sent as `{"op": "sample", "id": 1, "kind": "PUT", "data": {...}}`
+//!  - `{"op": "unsubscribe", "id": 1}`: replied with `{"op": "ok", "id": 1}`
+//!  - `{"op": "put", "id": 2, "path": "/demo/a", "value": ...}` (or `"patch"`): replied with `{"op": "ok", "id": 2}`.
+//!    A string value is published as `text/plain`, any other JSON value as `application/json`.
+//!  - `{"op": "delete", "id": 3, "path": "/demo/a"}`: replied with `{"op": "ok", "id": 3}`
+//!  - `{"op": "get", "id": 4, "selector": "/demo/**?(starttime=now()-1h)"}`: each reply is sent as
+//!    `{"op": "reply", "id": 4, "data": {...}}`, and the end of the query as `{"op": "done", "id": 4, "partial": false}`.
+//!    As for a GET on the REST API, the selector properties can specify the `limit`, `offset`, `target`,
+//!    `target_kind`, `consolidation` and `timeout` of the query. If the timeout elapses before the first reply,
+//!    the query fails with `{"op": "error", "id": 4, "error": "timeout"}` (where a GET fails with a 504 status).
+//!    If it elapses later, the replies already sent are kept (where a GET truncates its response)
+//!    and the query ends with `{"op": "done", "id": 4, "partial": true}`, as when the query itself times out.
+//!
+//! A failing request is replied with `{"op": "error", "id": ..., "error": "..."}`.
+//! The samples and replies `"data"` have the same format as the elements returned by a GET on the REST API.
+//! The access control rules apply to each request, a subscription or a query requiring the GET method.
+use crate::{
+    access_denied, forwarded_predicate, get_usize_prop, json_to_payload, path_to_resource,
+    reply_to_json, sample_to_json, QueryParams, State, PROP_LIMIT, PROP_OFFSET,
+};
+use async_std::channel::{bounded, Receiver, Sender};
+use async_std::task;
+use async_tungstenite::tungstenite::handshake::derive_accept_key;
+use async_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use async_tungstenite::tungstenite::protocol::{CloseFrame, Role, WebSocketConfig};
+use async_tungstenite::tungstenite::{Error, Message};
+use async_tungstenite::WebSocketStream;
+use futures::prelude::*;
+use futures::select;
+use futures::stream::SplitSink;
+use http_types::upgrade::Connection;
+use http_types::Method;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::Instant;
+use tide::{Request, Response, StatusCode};
+use zenoh::net::*;
+use zenoh::Selector;
+
+// The maximum size of a message received from a client
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+// The number of messages that can be queued before being sent to a client
+const OUTGOING_QUEUE_SIZE: usize = 256;
+
+const SUB_INFO: SubInfo = SubInfo {
+    reliability: Reliability::Reliable,
+    mode: SubMode::Push,
+    period: None,
+};
+
+/// Returns true if the request asks for an upgrade to the WebSocket protocol.
+pub(crate) fn is_upgrade(req: &Request<State>) -> bool {
+    req.header("upgrade")
+        .map(|h| h.last().as_str().eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false)
+}
+
+/// Replies to a WebSocket handshake request, and serves the connection once upgraded.
+pub(crate) async fn upgrade(req: Request<State>) -> tide::Result<Response> {
+    let authorization = req.header("authorization").map(|h| h.last().as_str());
+    let user = match req.state().2.authenticate(authorization) {
+        Ok(user) => user.map(String::from),
+        Err(e) => return Ok(access_denied(&req, e, req.url().path())),
+    };
+    let key = match (
+        req.header("sec-websocket-key"),
+        req.header("sec-websocket-version"),
+    ) {
+        (Some(key), Some(version)) if version.last().as_str() == "13" => {
+            key.last().as_str().trim().to_string()
+        }
+        _ => {
+            return Ok(Response::builder(StatusCode::BadRequest)
+                .header("sec-websocket-version", "13")
+                .body("Invalid WebSocket handshake")
+                .build())
+        }
+    };
+    let mut response = Response::new(StatusCode::SwitchingProtocols);
+    response.insert_header("upgrade", "websocket");
+    response.insert_header("connection", "Upgrade");
+    response.insert_header("sec-websocket-accept", derive_accept_key(key.as_bytes()));
+    let http_response: &mut http_types::Response = response.as_mut();
+    let upgrade_receiver = http_response.recv_upgrade().await;
+    let state = req.state().clone();
+    task::spawn(async move {
+        if let Some(connection) = upgrade_receiver.await {
+            log::debug!("WebSocket connection opened (user: {:?})", user);
+            serve(connection, state, user).await;
+            log::debug!("WebSocket connection closed");
+        }
+    });
+    Ok(response)
+}
+
+enum Outgoing {
+    Text(String),
+    Close(CloseCode),
+}
+
+async fn write_messages(
+    mut sink: SplitSink<WebSocketStream<Connection>, Message>,
+    outgoing: Receiver<Outgoing>,
+) {
+    while let Ok(msg) = outgoing.recv().await {
+        let (msg, last) = match msg {
+            Outgoing::Text(text) => (Message::Text(text), false),
+            Outgoing::Close(code) => (
+                Message::Close(Some(CloseFrame {
+                    code,
+                    reason: "".into(),
+                })),
+                true,
+            ),
+        };
+        if let Err(e) = sink.send(msg).await {
+            log::debug!("Error writing on WebSocket connection: {}", e);
+            break;
+        }
+        if last {
+            break;
+        }
+    }
+    let _ = sink.close().await;
+}
+
+// Reads the messages sent by the client until the connection is closed, and handles them
+async fn serve(connection: Connection, state: State, user: Option<String>) {
+    let config = WebSocketConfig {
+        max_message_size: Some(MAX_MESSAGE_SIZE),
+        max_frame_size: Some(MAX_MESSAGE_SIZE),
+        ..Default::default()
+    };
+    let stream = WebSocketStream::from_raw_socket(connection, Role::Server, Some(config)).await;
+    let (sink, mut stream) = stream.split();
+    let (out_tx, out_rx) = bounded::<Outgoing>(OUTGOING_QUEUE_SIZE);
+    task::spawn(write_messages(sink, out_rx));
+    let mut client = Client {
+        state,
+        user,
+        out: out_tx,
+        subscriptions: HashMap::new(),
+    };
+    let close_code = loop {
+        // the pings are answered by the WebSocket stream itself
+        match stream.next().await {
+            Some(Ok(Message::Text(text))) => client.handle(&text).await,
+            Some(Ok(Message::Binary(_))) => break CloseCode::Unsupported,
+            Some(Ok(Message::Close(_))) | None => break CloseCode::Normal,
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => {
+                log::debug!("Error reading WebSocket connection: {}", e);
+                match e {
+                    Error::Capacity(_) => break CloseCode::Size,
+                    Error::Utf8 => break CloseCode::Invalid,
+                    _ => break CloseCode::Protocol,
+                }
+            }
+        }
+    };
+    // stop the subscriptions
+    client.subscriptions.clear();
+    let _ = client.out.send(Outgoing::Close(close_code)).await;
+}
+
+// A WebSocket client, with its subscriptions (dropping the Sender stops the subscription)
+struct Client {
+    state: State,
+    user: Option<String>,
+    out: Sender<Outgoing>,
+    subscriptions: HashMap<String, Sender<()>>,
+}
+
+impl Client {
+    async fn handle(&mut self, text: &str) {
+        let request = match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(request @ serde_json::Value::Object(_)) => request,
+            _ => {
+                return self
+                    .send_error(
+                        &serde_json::Value::Null,
+                        "invalid request: not a JSON object",
+                    )
+                    .await
+            }
+        };
+        let id = request["id"].clone();
+        let result = match request["op"].as_str() {
+            Some("subscribe") => self.subscribe(&id, &request).await,
+            Some("unsubscribe") => match self.subscriptions.remove(&id.to_string()) {
+                Some(_) => Ok(()),
+                None => Err(format!("no subscription with id {}", id)),
+            },
+            Some("put") => self.write(&request, Method::Put).await,
+            Some("patch") => self.write(&request, Method::Patch).await,
+            Some("delete") => self.write(&request, Method::Delete).await,
+            Some("get") => self.get(&id, &request).await,
+            _ => Err("invalid request: unknown op".to_string()),
+        };
+        match result {
+            // the subscriptions and queries reply asynchronously
+            Ok(())
+                if request["op"].as_str() == Some("subscribe")
+                    || request["op"].as_str() == Some("get") => {}
+            Ok(()) => self.send(serde_json::json!({"op": "ok", "id": id})).await,
+            Err(e) => self.send_error(&id, &e).await,
+        }
+    }
+
+    async fn send(&self, msg: serde_json::Value) {
+        let _ = self.out.send(Outgoing::Text(msg.to_string())).await;
+    }
+
+    async fn send_error(&self, id: &serde_json::Value, error: &str) {
+        self.send(serde_json::json!({"op": "error", "id": id, "error": error}))
+            .await
+    }
+
+    // Returns the resource for a path of a request, if the user is allowed to apply method on it
+    fn resource(&self, path: &str, method: Method) -> Result<ResKey, String> {
+        let resource = path_to_resource(path, &self.state.1);
+        self.state
+            .2
+            .authorize(self.user.as_deref(), method, &resource.to_string())
+            .map_err(|_| format!("access denied to {} on {}", method, path))?;
+        Ok(resource)
+    }
+
+    async fn subscribe(
+        &mut self,
+        id: &serde_json::Value,
+        request: &serde_json::Value,
+    ) -> Result<(), String> {
+        let path = request["path"].as_str().ok_or("invalid request: no path")?;
+        let resource = self.resource(path, Method::Get)?;
+        if self.subscriptions.contains_key(&id.to_string()) {
+            return Err(format!("a subscription with id {} already exists", id));
+        }
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        self.subscriptions.insert(id.to_string(), stop_tx);
+        task::spawn(subscription(
+            self.state.0.clone(),
+            resource,
+            id.clone(),
+            self.out.clone(),
+            stop_rx,
+        ));
+        Ok(())
+    }
+
+    async fn write(&self, request: &serde_json::Value, method: Method) -> Result<(), String> {
+        let path = request["path"].as_str().ok_or("invalid request: no path")?;
+        let resource = self.resource(path, method)?;
+        let (payload, encoding) = match &request["value"] {
+            serde_json::Value::Null if method == Method::Delete => (vec![], encoding::NONE),
+            serde_json::Value::Null => return Err("invalid request: no value".to_string()),
+            value => json_to_payload(value),
+        };
+        self.state
+            .0
+            .write_ext(
+                &resource,
+                payload.into(),
+                encoding,
+                crate::method_to_kind(method),
+                CongestionControl::Drop, // @TODO: Define the right congestion control value
+            )
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn get(&self, id: &serde_json::Value, request: &serde_json::Value) -> Result<(), String> {
+        let selector = request["selector"]
+            .as_str()
+            .ok_or("invalid request: no selector")?;
+        let selector = Selector::try_from(selector).map_err(|e| e.to_string())?;
+        let resource = self.resource(selector.path_expr.as_str(), Method::Get)?;
+        let offset = get_usize_prop(&selector, PROP_OFFSET)?.unwrap_or(0);
+        let limit = get_usize_prop(&selector, PROP_LIMIT)?.unwrap_or(usize::MAX);
+        let params = QueryParams::new(&selector, |_| None)?;
+        let deadline = params.timeout.map(|timeout| Instant::now() + timeout);
+        let replies = self
+            .state
+            .0
+            .query(
+                &resource,
+                &forwarded_predicate(&selector),
+                params.target,
+                params.consolidation,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        let id = id.clone();
+        let out = self.out.clone();
+        task::spawn(async move {
+            let receiver = replies.clone();
+            let mut replies = replies.skip(offset).take(limit);
+            let mut sent = false;
+            let partial = loop {
+                let reply = match deadline {
+                    Some(deadline) => {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        match async_std::future::timeout(remaining, replies.next()).await {
+                            Ok(reply) => reply,
+                            // as for a GET, the timeout is an error only if no reply was received
+                            Err(_) if !sent => {
+                                let error = serde_json::json!({"op": "error", "id": id, "error": "timeout"});
+                                let _ = out.send(Outgoing::Text(error.to_string())).await;
+                                return;
+                            }
+                            Err(_) => break true,
+                        }
+                    }
+                    None => replies.next().await,
+                };
+                let reply = match reply {
+                    Some(reply) => reply,
+                    None => break receiver.is_partial(),
+                };
+                let msg = format!(
+                    r#"{{"op": "reply", "id": {}, "data": {}}}"#,
+                    id,
+                    reply_to_json(reply.data)
+                );
+                if out.send(Outgoing::Text(msg)).await.is_err() {
+                    return;
+                }
+                sent = true;
+            };
+            let done = serde_json::json!({"op": "done", "id": id, "partial": partial});
+            let _ = out.send(Outgoing::Text(done.to_string())).await;
+        });
+        Ok(())
+    }
+}
+
+// Forwards the samples received for a subscription until it's stopped
+async fn subscription(
+    session: std::sync::Arc<Session>,
+    resource: ResKey,
+    id: serde_json::Value,
+    out: Sender<Outgoing>,
+    stop: Receiver<()>,
+) {
+    let mut sub = match session.declare_subscriber(&resource, &SUB_INFO).await {
+        Ok(sub) => sub,
+        Err(e) => {
+            let error = serde_json::json!({"op": "error", "id": id, "error": e.to_string()});
+            let _ = out.send(Outgoing::Text(error.to_string())).await;
+            return;
+        }
+    };
+    let ok = serde_json::json!({"op": "ok", "id": id});
+    let _ = out.send(Outgoing::Text(ok.to_string())).await;
+    loop {
+        select!(
+            sample = sub.receiver().next().fuse() => match sample {
+                Some(sample) => {
+                    let msg = format!(
+                        r#"{{"op": "sample", "id": {}, "kind": "{}", "data": {}}}"#,
+                        id,
+                        crate::get_kind_str(&sample),
+                        sample_to_json(sample)
+                    );
+                    if out.send(Outgoing::Text(msg)).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            _ = stop.recv().fuse() => break,
+        );
+    }
+    if let Err(e) = sub.undeclare().await {
+        log::warn!("Error undeclaring WebSocket subscriber: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Auth;
+    use async_std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+
+    async fn next_json(client: &mut WebSocketStream<TcpStream>) -> serde_json::Value {
+        match client.next().await {
+            Some(Ok(Message::Text(text))) => serde_json::from_str(&text).unwrap(),
+            msg => panic!("unexpected message: {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn websocket_session() {
+        task::block_on(async {
+            let mut config = config::peer();
+            config.insert(
+                config::ZN_MULTICAST_SCOUTING_KEY,
+                config::ZN_FALSE.to_string(),
+            );
+            let session = Arc::new(open(config).await.unwrap());
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let stream = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (connection, _) = listener.accept().await.unwrap();
+            let state_session = session.clone();
+            let state = (session, "PID".to_string(), Arc::new(Auth::default()));
+            task::spawn(serve(Connection::new(connection), state, None));
+            let mut client = WebSocketStream::from_raw_socket(stream, Role::Client, None).await;
+
+            let request = r#"{"op": "subscribe", "id": 1, "path": "/demo/ws/**"}"#;
+            client.send(Message::Text(request.into())).await.unwrap();
+            assert_eq!(
+                next_json(&mut client).await,
+                serde_json::json!({"op": "ok", "id": 1})
+            );
+
+            let request = r#"{"op": "put", "id": 2, "path": "/demo/ws/a", "value": "hello"}"#;
+            client.send(Message::Text(request.into())).await.unwrap();
+            let mut ops = vec![];
+            for _ in 0..2 {
+                let msg = next_json(&mut client).await;
+                if msg["op"] == "sample" {
+                    assert_eq!(msg["data"]["key"], "/demo/ws/a");
+                    assert_eq!(msg["data"]["value"], "hello");
+                }
+                ops.push((msg["op"].clone(), msg["id"].clone()));
+            }
+            ops.sort_by_key(|(_, id)| id.as_i64());
+            assert_eq!(
+                ops,
+                vec![("sample".into(), 1.into()), ("ok".into(), 2.into())]
+            );
+
+            // a query timing out after a reply ends with a partial result
+            let mut queryable = state_session
+                .declare_queryable(&"/demo/hang".into(), queryable::EVAL)
+                .await
+                .unwrap();
+            let receiver = queryable.receiver().clone();
+            let handle = task::spawn(async move {
+                let mut receiver = receiver;
+                let mut pending = vec![];
+                while let Some(query) = receiver.next().await {
+                    query
+                        .reply_async(Sample {
+                            res_name: query.res_name.clone(),
+                            payload: b"v".to_vec().into(),
+                            data_info: None,
+                        })
+                        .await;
+                    pending.push(query);
+                }
+            });
+            let request = r#"{"op": "get", "id": 3, "selector": "/demo/hang?(timeout=0.5;target_kind=all;consolidation=none)"}"#;
+            client.send(Message::Text(request.into())).await.unwrap();
+            assert_eq!(next_json(&mut client).await["op"], "reply");
+            assert_eq!(
+                next_json(&mut client).await,
+                serde_json::json!({"op": "done", "id": 3, "partial": true})
+            );
+            let request = r#"{"op": "get", "id": 4, "selector": "/demo/nothing/**"}"#;
+            client.send(Message::Text(request.into())).await.unwrap();
+            assert_eq!(
+                next_json(&mut client).await,
+                serde_json::json!({"op": "done", "id": 4, "partial": false})
+            );
+            queryable.undeclare().await.unwrap();
+            handle.cancel().await;
+
+            client.send(Message::Ping(vec![1, 2])).await.unwrap();
+            assert_eq!(
+                client.next().await.unwrap().unwrap(),
+                Message::Pong(vec![1, 2])
+            );
+
+            // a too big message closes the connection
+            let request = "x".repeat(MAX_MESSAGE_SIZE + 1);
+            client.send(Message::Text(request)).await.unwrap();
+            match client.next().await {
+                Some(Ok(Message::Close(Some(frame)))) => assert_eq!(frame.code, CloseCode::Size),
+                msg => panic!("unexpected message: {:?}", msg),
+            }
+        })
+    }
+}
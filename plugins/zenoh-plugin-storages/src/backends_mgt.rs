@@ -20,11 +20,8 @@ use futures::select;
 use log::{debug, error, trace, warn};
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use zenoh::{ChangeKind, Path, PathExpr, Selector, Value, ZError, ZErrorKind, ZResult, Zenoh};
-use zenoh_backend_traits::{
-    IncomingDataInterceptor, OutgoingDataInterceptor, PROP_STORAGE_PATH_EXPR,
-};
-use zenoh_util::{zerror, zerror2};
+use zenoh::{ChangeKind, Path, PathExpr, Properties, Selector, Value, ZResult, Zenoh};
+use zenoh_backend_traits::{IncomingDataInterceptor, OutgoingDataInterceptor};
 
 pub(crate) async fn start_backend(
     backend: Box<dyn zenoh_backend_traits::Backend>,
@@ -81,7 +78,7 @@ pub(crate) async fn start_backend(
         let mut backend = backend;
         // Map owning handles on alive storages for this backend.
         // Once dropped, a handle will release/stop the backend.
-        let mut storages_handles: HashMap<Path, Sender<bool>> = HashMap::new();
+        let mut storages_handles: HashMap<Path, Sender<StorageMessage>> = HashMap::new();
         loop {
             select!(
                 // on get request on backend_admin
@@ -95,23 +92,24 @@ pub(crate) async fn start_backend(
                     let change = change.unwrap();
                     trace!("{} received change for {}", admin_path, change.path);
                     match change.kind {
-                        ChangeKind::Put => {
-                            if let Some(value) = change.value {
-                                #[allow(clippy::map_entry)]
-                                if !storages_handles.contains_key(&change.path) {
-                                    match create_and_start_storage(change.path.clone(), value, &mut backend, in_interceptor.clone(), out_interceptor.clone(), zenoh.clone()).await {
+                        ChangeKind::Put => match change.value {
+                            Some(Value::Properties(props)) => {
+                                if let Some(handle) = storages_handles.get(&change.path) {
+                                    debug!("Reconfigure storage {}", change.path);
+                                    if handle.send(StorageMessage::Reconfigure(props)).await.is_err() {
+                                        warn!("Storage {} is not running", change.path);
+                                    }
+                                } else {
+                                    match create_and_start_storage(change.path.clone(), props, &mut backend, in_interceptor.clone(), out_interceptor.clone(), zenoh.clone()).await {
                                         Ok(handle) => {
                                             let _ = storages_handles.insert(change.path, handle);
                                         }
                                         Err(e) => warn!("{}", e),
                                     }
-                                } else {
-                                    warn!("Storage {} already exists", change.path);
                                 }
-                            } else {
-                                warn!("Received a PUT on {} with invalid value: {:?}", change.path, change.value);
                             }
-                        }
+                            value => warn!("Received a PUT on {} with invalid value: {:?}", change.path, value),
+                        },
                         ChangeKind::Delete =>  {
                             debug!("Delete storage {}", change.path);
                             let _ = storages_handles.remove(&change.path);
@@ -136,39 +134,23 @@ pub(crate) async fn start_backend(
 
 async fn create_and_start_storage(
     admin_path: Path,
-    value: Value,
+    props: Properties,
     backend: &mut Box<dyn zenoh_backend_traits::Backend>,
     in_interceptor: Option<Arc<RwLock<Box<dyn IncomingDataInterceptor>>>>,
     out_interceptor: Option<Arc<RwLock<Box<dyn OutgoingDataInterceptor>>>>,
     zenoh: Arc<Zenoh>,
-) -> ZResult<Sender<bool>> {
+) -> ZResult<Sender<StorageMessage>> {
     trace!("Create storage {}", admin_path);
-    if let Value::Properties(props) = value {
-        let path_expr_str = props.get(PROP_STORAGE_PATH_EXPR).ok_or_else(|| {
-            zerror2!(ZErrorKind::Other {
-                descr: format!(
-                    "Can't create storage {}: no {} property",
-                    admin_path, PROP_STORAGE_PATH_EXPR
-                )
-            })
-        })?;
-        let path_expr = PathExpr::try_from(path_expr_str.as_str())?;
-        let storage = backend.create_storage(props).await?;
-        start_storage(
-            storage,
-            admin_path.clone(),
-            path_expr,
-            in_interceptor,
-            out_interceptor,
-            zenoh,
-        )
-        .await
-    } else {
-        zerror!(ZErrorKind::Other {
-            descr: format!(
-                "Received a PUT on {} with invalid value: {:?}",
-                admin_path, value
-            )
-        })
-    }
+    // check the storage configuration before creating it
+    StorageConfig::new(&admin_path, &props)?;
+    let storage = backend.create_storage(props.clone()).await?;
+    start_storage(
+        storage,
+        admin_path,
+        props,
+        in_interceptor,
+        out_interceptor,
+        zenoh,
+    )
+    .await
 }
@@ -0,0 +1,465 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::fs::{self, File, OpenOptions};
+use async_std::io::SeekFrom;
+use async_std::prelude::*;
+use log::{trace, warn};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zenoh::net::{encoding, DataInfo, Sample, WBuf, ZBuf, ZInt};
+use zenoh::{ChangeKind, Timestamp, ZError, ZErrorKind, ZResult};
+use zenoh_util::zerror2;
+
+const LOG_FILE_NAME: &str = "data.log";
+const COMPACTED_LOG_FILE_NAME: &str = "data.log.compacted";
+const CORRUPTED_LOG_FILE_PREFIX: &str = "data.log.corrupted";
+
+// Each record in the log file is prefixed with its length, as a little-endian u32.
+const RECORD_LEN_SIZE: usize = 4;
+
+/// A change as stored in the log: its kind, its timestamp and the corresponding Sample.
+pub(crate) type Record = (ChangeKind, Timestamp, Sample);
+
+/// An append-only log of changes, stored in a single file.
+///
+/// Each append is synced to the disk before returning, so an acknowledged change survives a crash.
+pub(crate) struct DataLog {
+    dir: PathBuf,
+    file: File,
+    records: usize,
+    // the length in bytes of the log file
+    len: u64,
+}
+
+/// A compaction of a [`DataLog`]: the compacted log is written aside (see [`Compaction::write()`])
+/// while the log remains in use, and then swapped in (see [`DataLog::finish_compaction()`]).
+pub(crate) struct Compaction {
+    path: PathBuf,
+    buf: Vec<u8>,
+    count: usize,
+    // the number of records and the length of the log when the compaction started
+    from_records: usize,
+    from_len: u64,
+}
+
+impl DataLog {
+    /// Opens (or creates) the log in `dir`, returning it with all the valid records it contains.
+    /// If the log ends with data that can't be decoded (a record partially written during a crash,
+    /// or a corruption), this data is moved aside in a `data.log.corrupted.<time>` file.
+    pub(crate) async fn open(dir: &Path) -> ZResult<(DataLog, Vec<Record>)> {
+        fs::create_dir_all(dir).await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!("Failed to create directory {}: {}", dir.display(), e)
+            })
+        })?;
+        let path = dir.join(LOG_FILE_NAME);
+        let bytes = match fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => {
+                return Err(zerror2!(ZErrorKind::IoError {
+                    descr: format!("Failed to read {}: {}", path.display(), e)
+                }))
+            }
+        };
+        let (records, valid_len) = decode_records(&bytes);
+        if valid_len < bytes.len() {
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default();
+            let corrupted_path = dir.join(format!("{}.{}", CORRUPTED_LOG_FILE_PREFIX, millis));
+            warn!(
+                "Move {} bytes of corrupted or incomplete data at the end of {} to {}",
+                bytes.len() - valid_len,
+                path.display(),
+                corrupted_path.display()
+            );
+            let mut corrupted = File::create(&corrupted_path).await.map_err(|e| {
+                zerror2!(ZErrorKind::IoError {
+                    descr: format!("Failed to create {}: {}", corrupted_path.display(), e)
+                })
+            })?;
+            write_all(&mut corrupted, &bytes[valid_len..], &corrupted_path).await?;
+            sync_all(&corrupted, &corrupted_path).await?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(&path)
+            .await
+            .map_err(|e| {
+                zerror2!(ZErrorKind::IoError {
+                    descr: format!("Failed to open {}: {}", path.display(), e)
+                })
+            })?;
+        file.set_len(valid_len as u64).await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!("Failed to truncate {}: {}", path.display(), e)
+            })
+        })?;
+        drop(file);
+        let file = open_append(&path).await?;
+        trace!("Opened {} with {} records", path.display(), records.len());
+
+        Ok((
+            DataLog {
+                dir: dir.to_path_buf(),
+                file,
+                records: records.len(),
+                len: valid_len as u64,
+            },
+            records,
+        ))
+    }
+
+    /// Returns the number of records currently in the log file.
+    pub(crate) fn records(&self) -> usize {
+        self.records
+    }
+
+    /// Appends a change at the end of the log.
+    pub(crate) async fn append(
+        &mut self,
+        kind: &ChangeKind,
+        timestamp: &Timestamp,
+        sample: &Sample,
+    ) -> ZResult<()> {
+        let path = self.dir.join(LOG_FILE_NAME);
+        let record = encode_record(kind, timestamp, sample);
+        write_all(&mut self.file, &record, &path).await?;
+        self.file.sync_data().await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!("Failed to sync {}: {}", path.display(), e)
+            })
+        })?;
+        self.records += 1;
+        self.len += record.len() as u64;
+        Ok(())
+    }
+
+    /// Starts a compaction of the log, that will contain only the given records.
+    pub(crate) fn start_compaction<'a, I>(&self, records: I) -> Compaction
+    where
+        I: Iterator<Item = (ChangeKind, &'a Timestamp, &'a Sample)>,
+    {
+        let mut buf: Vec<u8> = vec![];
+        let mut count: usize = 0;
+        for (kind, timestamp, sample) in records {
+            buf.extend(encode_record(&kind, timestamp, sample));
+            count += 1;
+        }
+        Compaction {
+            path: self.dir.join(COMPACTED_LOG_FILE_NAME),
+            buf,
+            count,
+            from_records: self.records,
+            from_len: self.len,
+        }
+    }
+
+    /// Replaces the log with the compacted log written by `compaction`, keeping the records
+    /// appended since the start of the compaction.
+    /// The compacted log is atomically renamed over the old one.
+    pub(crate) async fn finish_compaction(&mut self, compaction: Compaction) -> ZResult<()> {
+        let path = self.dir.join(LOG_FILE_NAME);
+        let compacted_path = compaction.path;
+
+        let mut appended = vec![];
+        let mut file = File::open(&path).await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!("Failed to open {}: {}", path.display(), e)
+            })
+        })?;
+        file.seek(SeekFrom::Start(compaction.from_len))
+            .await
+            .map_err(|e| {
+                zerror2!(ZErrorKind::IoError {
+                    descr: format!("Failed to seek in {}: {}", path.display(), e)
+                })
+            })?;
+        file.read_to_end(&mut appended).await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!("Failed to read {}: {}", path.display(), e)
+            })
+        })?;
+        let mut compacted = open_append(&compacted_path).await?;
+        write_all(&mut compacted, &appended, &compacted_path).await?;
+        sync_all(&compacted, &compacted_path).await?;
+        drop(compacted);
+
+        fs::rename(&compacted_path, &path).await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!(
+                    "Failed to rename {} to {}: {}",
+                    compacted_path.display(),
+                    path.display(),
+                    e
+                )
+            })
+        })?;
+        self.file = open_append(&path).await?;
+        self.records = compaction.count + self.records - compaction.from_records;
+        self.len = (compaction.buf.len() + appended.len()) as u64;
+        Ok(())
+    }
+}
+
+impl Compaction {
+    /// Writes the compacted log aside, without blocking the use of the log.
+    pub(crate) async fn write(&self) -> ZResult<()> {
+        let mut compacted = File::create(&self.path).await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!("Failed to create {}: {}", self.path.display(), e)
+            })
+        })?;
+        write_all(&mut compacted, &self.buf, &self.path).await?;
+        sync_all(&compacted, &self.path).await
+    }
+}
+
+async fn open_append(path: &Path) -> ZResult<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: format!("Failed to open {}: {}", path.display(), e)
+            })
+        })
+}
+
+async fn sync_all(file: &File, path: &Path) -> ZResult<()> {
+    file.sync_all().await.map_err(|e| {
+        zerror2!(ZErrorKind::IoError {
+            descr: format!("Failed to sync {}: {}", path.display(), e)
+        })
+    })
+}
+
+async fn write_all(file: &mut File, bytes: &[u8], path: &Path) -> ZResult<()> {
+    file.write_all(bytes).await.map_err(|e| {
+        zerror2!(ZErrorKind::IoError {
+            descr: format!("Failed to write in {}: {}", path.display(), e)
+        })
+    })?;
+    file.flush().await.map_err(|e| {
+        zerror2!(ZErrorKind::IoError {
+            descr: format!("Failed to flush {}: {}", path.display(), e)
+        })
+    })
+}
+
+fn encode_record(kind: &ChangeKind, timestamp: &Timestamp, sample: &Sample) -> Vec<u8> {
+    let encoding = sample
+        .data_info
+        .as_ref()
+        .and_then(|info| info.encoding)
+        .unwrap_or(encoding::APP_OCTET_STREAM);
+    let mut wbuf = WBuf::new(64, false);
+    wbuf.write_zint(kind.clone() as ZInt);
+    wbuf.write_string(&timestamp.to_string());
+    wbuf.write_string(&sample.res_name);
+    wbuf.write_zint(encoding);
+    wbuf.write_bytes_array(&sample.payload.to_vec());
+    let body = ZBuf::from(wbuf).to_vec();
+
+    let mut record = Vec::with_capacity(RECORD_LEN_SIZE + body.len());
+    record.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    record.extend(body);
+    record
+}
+
+fn decode_record(body: &[u8]) -> Option<Record> {
+    let mut zbuf = ZBuf::from(body);
+    let kind = ChangeKind::from(zbuf.read_zint()?);
+    let timestamp = Timestamp::from_str(&zbuf.read_string()?).ok()?;
+    let res_name = zbuf.read_string()?;
+    let encoding = zbuf.read_zint()?;
+    let payload = zbuf.read_bytes_array()?;
+
+    let mut info = DataInfo::new();
+    info.kind = Some(kind.clone() as ZInt);
+    info.encoding = Some(encoding);
+    info.timestamp = Some(timestamp);
+    let sample = Sample {
+        res_name,
+        payload: payload.into(),
+        data_info: Some(info),
+    };
+    Some((kind, timestamp, sample))
+}
+
+// Decodes all the records from `bytes`, stopping at the first invalid one.
+// Returns the decoded records and the length of the valid part of `bytes`.
+fn decode_records(bytes: &[u8]) -> (Vec<Record>, usize) {
+    let mut records = vec![];
+    let mut offset: usize = 0;
+    while bytes.len() - offset >= RECORD_LEN_SIZE {
+        let len = u32::from_le_bytes(
+            <[u8; RECORD_LEN_SIZE]>::try_from(&bytes[offset..offset + RECORD_LEN_SIZE]).unwrap(),
+        ) as usize;
+        let start = offset + RECORD_LEN_SIZE;
+        if bytes.len() - start < len {
+            break;
+        }
+        match decode_record(&bytes[start..start + len]) {
+            Some(record) => records.push(record),
+            None => break,
+        }
+        offset = start + len;
+    }
+    (records, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh::utils::new_reception_timestamp;
+
+    fn sample(res_name: &str, payload: &[u8]) -> Sample {
+        let mut info = DataInfo::new();
+        info.encoding = Some(encoding::STRING);
+        Sample {
+            res_name: res_name.into(),
+            payload: payload.into(),
+            data_info: Some(info),
+        }
+    }
+
+    #[test]
+    fn records_codec() {
+        let ts = new_reception_timestamp();
+        let mut bytes = encode_record(&ChangeKind::Put, &ts, &sample("/a/b", b"hello"));
+        bytes.extend(encode_record(
+            &ChangeKind::Delete,
+            &ts,
+            &sample("/a/c", b""),
+        ));
+        let complete_len = bytes.len();
+        // simulate a partially written record
+        let partial = encode_record(&ChangeKind::Put, &ts, &sample("/a/d", b"world"));
+        bytes.extend(&partial[..partial.len() - 2]);
+
+        let (records, valid_len) = decode_records(&bytes);
+        assert_eq!(valid_len, complete_len);
+        assert_eq!(records.len(), 2);
+
+        let (kind, timestamp, s) = &records[0];
+        assert_eq!(*kind, ChangeKind::Put);
+        assert_eq!(*timestamp, ts);
+        assert_eq!(s.res_name, "/a/b");
+        assert_eq!(s.payload.to_vec(), b"hello".to_vec());
+        assert_eq!(
+            s.data_info.as_ref().unwrap().encoding,
+            Some(encoding::STRING)
+        );
+
+        let (kind, _, s) = &records[1];
+        assert_eq!(*kind, ChangeKind::Delete);
+        assert_eq!(s.res_name, "/a/c");
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zdata_log_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn corrupted_tail_moved_aside() {
+        async_std::task::block_on(async {
+            let dir = test_dir("corrupted");
+            std::fs::create_dir_all(&dir).unwrap();
+            let ts = new_reception_timestamp();
+            let mut bytes = encode_record(&ChangeKind::Put, &ts, &sample("/a/b", b"hello"));
+            let valid_len = bytes.len();
+            // a corrupted record followed by a valid one
+            bytes.extend(&3u32.to_le_bytes());
+            bytes.extend(&[0xff, 0xff, 0xff]);
+            bytes.extend(encode_record(
+                &ChangeKind::Put,
+                &ts,
+                &sample("/a/c", b"world"),
+            ));
+            std::fs::write(dir.join(LOG_FILE_NAME), &bytes).unwrap();
+
+            let (log, records) = DataLog::open(&dir).await.unwrap();
+            assert_eq!(records.len(), 1);
+            assert_eq!(log.records(), 1);
+            assert_eq!(
+                std::fs::metadata(dir.join(LOG_FILE_NAME)).unwrap().len(),
+                valid_len as u64
+            );
+            let corrupted: Vec<Vec<u8>> = std::fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    path.file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .starts_with(CORRUPTED_LOG_FILE_PREFIX)
+                })
+                .map(|path| std::fs::read(path).unwrap())
+                .collect();
+            assert_eq!(corrupted, vec![bytes[valid_len..].to_vec()]);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    }
+
+    #[test]
+    fn compaction_keeps_appended_records() {
+        async_std::task::block_on(async {
+            let dir = test_dir("compaction");
+            let ts = new_reception_timestamp();
+            let kept = sample("/a/b", b"kept");
+            let (mut log, _) = DataLog::open(&dir).await.unwrap();
+            for _ in 0..3 {
+                log.append(&ChangeKind::Put, &ts, &sample("/a/b", b"old"))
+                    .await
+                    .unwrap();
+            }
+
+            let compaction = log.start_compaction(vec![(ChangeKind::Put, &ts, &kept)].into_iter());
+            compaction.write().await.unwrap();
+            // appended while the compacted log is written
+            log.append(&ChangeKind::Delete, &ts, &sample("/a/c", b""))
+                .await
+                .unwrap();
+            log.finish_compaction(compaction).await.unwrap();
+            assert_eq!(log.records(), 2);
+            log.append(&ChangeKind::Put, &ts, &sample("/a/d", b"new"))
+                .await
+                .unwrap();
+            drop(log);
+
+            let (log, records) = DataLog::open(&dir).await.unwrap();
+            assert_eq!(log.records(), 3);
+            let names: Vec<&str> = records
+                .iter()
+                .map(|(_, _, s)| s.res_name.as_str())
+                .collect();
+            assert_eq!(names, vec!["/a/b", "/a/c", "/a/d"]);
+            assert_eq!(records[0].2.payload.to_vec(), b"kept".to_vec());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    }
+}
@@ -0,0 +1,543 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::sync::{Arc, Mutex, RwLock};
+use async_trait::async_trait;
+use log::{debug, trace, warn};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Component, PathBuf};
+use std::time::{Duration, SystemTime};
+use zenoh::net::utils::resource_name;
+use zenoh::net::{Sample, ZBuf};
+use zenoh::{
+    utils, ChangeKind, Properties, Selector, Timestamp, Value, ZError, ZErrorKind, ZResult,
+};
+use zenoh_backend_traits::utils::patch_sample;
+use zenoh_backend_traits::*;
+use zenoh_util::collections::{Timed, TimedEvent, Timer};
+use zenoh_util::{zerror, zerror2};
+
+mod data_log;
+use data_log::DataLog;
+
+/// The `"root"` property key for the directory where the file backend stores its storages.
+/// If not specified, `$HOME/.zenoh/zbackend_file` is used.
+pub const PROP_FILE_BACKEND_ROOT: &str = "root";
+
+/// The `"dir"` property key for the directory (relative to the backend's root)
+/// where a file storage stores its data.
+pub const PROP_FILE_STORAGE_DIR: &str = "dir";
+
+pub fn create_backend(properties: Properties) -> ZResult<Box<dyn Backend>> {
+    let root = match properties.get(PROP_FILE_BACKEND_ROOT) {
+        Some(root) => PathBuf::from(root),
+        None => default_root(),
+    };
+    std::fs::create_dir_all(&root).map_err(|e| {
+        zerror2!(ZErrorKind::IoError {
+            descr: format!("Failed to create directory {}: {}", root.display(), e)
+        })
+    })?;
+
+    let mut p = properties;
+    p.insert(PROP_BACKEND_TYPE.into(), "file".into());
+    p.insert(PROP_FILE_BACKEND_ROOT.into(), root.display().to_string());
+    let admin_status = utils::properties_to_json_value(&p);
+    Ok(Box::new(FileBackend { admin_status, root }))
+}
+
+fn default_root() -> PathBuf {
+    match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".zenoh").join("zbackend_file"),
+        None => PathBuf::from("zbackend_file"),
+    }
+}
+
+pub struct FileBackend {
+    admin_status: Value,
+    root: PathBuf,
+}
+
+#[async_trait]
+impl Backend for FileBackend {
+    async fn get_admin_status(&self) -> Value {
+        self.admin_status.clone()
+    }
+
+    async fn create_storage(&mut self, properties: Properties) -> ZResult<Box<dyn Storage>> {
+        debug!("Create File Storage with properties: {}", properties);
+        let dir = match properties.get(PROP_FILE_STORAGE_DIR) {
+            Some(dir) => PathBuf::from(dir),
+            None => {
+                return zerror!(ZErrorKind::Other {
+                    descr: format!(
+                        "Can't create file storage: no {} property",
+                        PROP_FILE_STORAGE_DIR
+                    )
+                })
+            }
+        };
+        // the storage's directory must remain within the backend's root
+        if !dir
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return zerror!(ZErrorKind::Other {
+                descr: format!(
+                    "Can't create file storage: {} must be a relative path without '..' ({})",
+                    PROP_FILE_STORAGE_DIR,
+                    dir.display()
+                )
+            });
+        }
+        let dir = self.root.join(dir);
+        Ok(Box::new(FileStorage::new(properties, dir).await?))
+    }
+
+    fn incoming_data_interceptor(&self) -> Option<Box<dyn IncomingDataInterceptor>> {
+        None
+    }
+
+    fn outgoing_data_interceptor(&self) -> Option<Box<dyn OutgoingDataInterceptor>> {
+        None
+    }
+}
+
+enum StoredValue {
+    Present { ts: Timestamp, sample: Sample },
+    Removed { ts: Timestamp, sample: Sample },
+}
+
+impl StoredValue {
+    fn ts(&self) -> &Timestamp {
+        match self {
+            Present { ts, sample: _ } => ts,
+            Removed { ts, sample: _ } => ts,
+        }
+    }
+
+    fn kind(&self) -> ChangeKind {
+        match self {
+            Present { ts: _, sample: _ } => ChangeKind::Put,
+            Removed { ts: _, sample: _ } => ChangeKind::Delete,
+        }
+    }
+
+    fn sample(&self) -> &Sample {
+        match self {
+            Present { ts: _, sample } => sample,
+            Removed { ts: _, sample } => sample,
+        }
+    }
+}
+use StoredValue::{Present, Removed};
+
+struct FileStorage {
+    admin_status: Value,
+    map: Arc<RwLock<HashMap<String, StoredValue>>>,
+    log: Arc<Mutex<DataLog>>,
+    _timer: Timer,
+}
+
+impl FileStorage {
+    async fn new(properties: Properties, dir: PathBuf) -> ZResult<FileStorage> {
+        let admin_status = utils::properties_to_json_value(&properties);
+
+        // rebuild the index replaying the log
+        let (log, records) = DataLog::open(&dir).await?;
+        let mut map: HashMap<String, StoredValue> = HashMap::new();
+        for (kind, ts, sample) in records {
+            let is_newer = match map.get(&sample.res_name) {
+                Some(v) => v.ts() < &ts,
+                None => true,
+            };
+            if is_newer {
+                let stored = match kind {
+                    ChangeKind::Delete => Removed { ts, sample },
+                    _ => Present { ts, sample },
+                };
+                map.insert(stored.sample().res_name.clone(), stored);
+            }
+        }
+        debug!(
+            "File storage in {} restored {} entries from {} records",
+            dir.display(),
+            map.len(),
+            log.records()
+        );
+
+        let map = Arc::new(RwLock::new(map));
+        let log = Arc::new(Mutex::new(log));
+        let timer = Timer::new();
+        timer
+            .add(TimedEvent::periodic(
+                Duration::from_millis(COMPACTION_PERIOD_MS),
+                TimedCompaction {
+                    map: map.clone(),
+                    log: log.clone(),
+                },
+            ))
+            .await;
+
+        Ok(FileStorage {
+            admin_status,
+            map,
+            log,
+            _timer: timer,
+        })
+    }
+}
+
+#[async_trait]
+impl Storage for FileStorage {
+    async fn get_admin_status(&self) -> Value {
+        self.admin_status.clone()
+    }
+
+    async fn on_sample(&mut self, sample: Sample) -> ZResult<()> {
+        trace!("on_sample for {}", sample.res_name);
+        let (kind, timestamp) = if let Some(ref info) = sample.data_info {
+            (
+                info.kind.map_or(ChangeKind::Put, ChangeKind::from),
+                match &info.timestamp {
+                    Some(ts) => *ts,
+                    None => utils::new_reception_timestamp(),
+                },
+            )
+        } else {
+            (ChangeKind::Put, utils::new_reception_timestamp())
+        };
+        let stored = match kind {
+            ChangeKind::Put => Present {
+                ts: timestamp,
+                sample,
+            },
+            ChangeKind::Delete => Removed {
+                ts: timestamp,
+                sample: Sample {
+                    res_name: sample.res_name,
+                    payload: ZBuf::new(),
+                    data_info: sample.data_info,
+                },
+            },
+            ChangeKind::Patch => {
+                // the patched value is logged as a PUT
+                let patched = match self.map.read().await.get(&sample.res_name) {
+                    Some(Present { ts: _, sample: old }) => patch_sample(Some(old), &sample)?,
+                    _ => patch_sample(None, &sample)?,
+                };
+                Present {
+                    ts: timestamp,
+                    sample: patched,
+                }
+            }
+        };
+
+        let mut map = self.map.write().await;
+        match map.entry(stored.sample().res_name.clone()) {
+            Entry::Vacant(v) => {
+                // NOTE: even if path is not known yet, we need to store the removal time:
+                // if ever a put with a lower timestamp arrive (e.g. msg inversion between put and remove)
+                // we must drop the put.
+                self.log
+                    .lock()
+                    .await
+                    .append(&stored.kind(), stored.ts(), stored.sample())
+                    .await?;
+                v.insert(stored);
+            }
+            Entry::Occupied(mut o) => {
+                if o.get().ts() < stored.ts() {
+                    self.log
+                        .lock()
+                        .await
+                        .append(&stored.kind(), stored.ts(), stored.sample())
+                        .await?;
+                    o.insert(stored);
+                } else {
+                    debug!(
+                        "{} on {} dropped: out-of-date",
+                        stored.kind(),
+                        stored.sample().res_name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_query(&mut self, query: Query) -> ZResult<()> {
+        trace!("on_query for {}", query.res_name());
+        let filter = Selector::try_from(&query)?.value_filter()?;
+        let matches = |sample: &Sample| filter.iter().all(|f| f.matches_sample(sample));
+        if !query.res_name().contains('*') {
+            if let Some(Present { sample, ts: _ }) = self.map.read().await.get(query.res_name()) {
+                if matches(sample) {
+                    query.reply(sample.clone()).await;
+                }
+            }
+        } else {
+            for (_, stored_value) in self.map.read().await.iter() {
+                if let Present { sample, ts: _ } = stored_value {
+                    if resource_name::intersect(query.res_name(), &sample.res_name)
+                        && matches(sample)
+                    {
+                        let s: Sample = sample.clone();
+                        query.reply(s).await;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_stats(&self) -> Option<StorageStats> {
+        let mut stats = StorageStats::default();
+        for stored_value in self.map.read().await.values() {
+            if let Present { sample, ts: _ } = stored_value {
+                stats.keys += 1;
+                stats.bytes += sample.payload.len();
+            }
+        }
+        Some(stats)
+    }
+}
+
+impl Drop for FileStorage {
+    fn drop(&mut self) {
+        // all the data are already written in the log
+        trace!("FileStorage::drop()");
+    }
+}
+
+const COMPACTION_PERIOD_MS: u64 = 30_000;
+// The log is compacted only if it contains at least this number of obsolete records.
+const COMPACTION_MIN_OBSOLETE_RECORDS: usize = 128;
+// Delay after which the removal of a path is forgotten at compaction.
+const REMOVAL_EXPIRATION_MS: u64 = 5000;
+
+struct TimedCompaction {
+    map: Arc<RwLock<HashMap<String, StoredValue>>>,
+    log: Arc<Mutex<DataLog>>,
+}
+
+#[async_trait]
+impl Timed for TimedCompaction {
+    async fn run(&mut self) {
+        // the compacted log is written without locking the map, the changes
+        // logged meanwhile being appended to it when it's swapped in
+        let (compaction, before) = {
+            let mut map = self.map.write().await;
+            let log = self.log.lock().await;
+            if log.records() < map.len() + COMPACTION_MIN_OBSOLETE_RECORDS {
+                return;
+            }
+
+            let expiration = SystemTime::now() - Duration::from_millis(REMOVAL_EXPIRATION_MS);
+            map.retain(|_, v| match v {
+                Present { ts: _, sample: _ } => true,
+                Removed { ts, sample: _ } => ts.get_time().to_system_time() > expiration,
+            });
+            let records = map.values().map(|v| (v.kind(), v.ts(), v.sample()));
+            (log.start_compaction(records), log.records())
+        };
+        if let Err(e) = compaction.write().await {
+            warn!("File storage log compaction failed: {}", e);
+            return;
+        }
+        let mut log = self.log.lock().await;
+        match log.finish_compaction(compaction).await {
+            Ok(()) => debug!(
+                "File storage log compacted from {} to {} records",
+                before,
+                log.records()
+            ),
+            Err(e) => warn!("File storage log compaction failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh::net::DataInfo;
+    use zenoh::TimestampId;
+
+    fn timestamp(secs: u64) -> Timestamp {
+        Timestamp::new(
+            Duration::from_secs(secs).into(),
+            TimestampId::new(1, [0u8; TimestampId::MAX_SIZE]),
+        )
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("zbackend_file_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn sample(res_name: &str, kind: ChangeKind, ts: Timestamp, payload: &[u8]) -> Sample {
+        let mut info = DataInfo::new();
+        info.kind = Some(kind as u64);
+        info.timestamp = Some(ts);
+        Sample {
+            res_name: res_name.into(),
+            payload: payload.into(),
+            data_info: Some(info),
+        }
+    }
+
+    #[test]
+    fn restore_after_restart() {
+        async_std::task::block_on(async {
+            let dir = test_dir("restore");
+            let (t0, t1, t2, t3) = (timestamp(10), timestamp(11), timestamp(12), timestamp(13));
+            {
+                let mut storage = FileStorage::new(Properties::default(), dir.clone())
+                    .await
+                    .unwrap();
+                storage
+                    .on_sample(sample("/a/1", ChangeKind::Put, t1, b"v1"))
+                    .await
+                    .unwrap();
+                storage
+                    .on_sample(sample("/a/2", ChangeKind::Put, t1, b"v2"))
+                    .await
+                    .unwrap();
+                storage
+                    .on_sample(sample("/a/1", ChangeKind::Put, t2, b"v1bis"))
+                    .await
+                    .unwrap();
+                storage
+                    .on_sample(sample("/a/2", ChangeKind::Delete, t3, b""))
+                    .await
+                    .unwrap();
+                // out-of-date: must be dropped and not logged
+                storage
+                    .on_sample(sample("/a/2", ChangeKind::Put, t0, b"old"))
+                    .await
+                    .unwrap();
+                assert_eq!(storage.log.lock().await.records(), 4);
+            }
+
+            let storage = FileStorage::new(Properties::default(), dir.clone())
+                .await
+                .unwrap();
+            let map = storage.map.read().await;
+            assert_eq!(map.len(), 2);
+            match map.get("/a/1") {
+                Some(Present { ts, sample }) => {
+                    assert_eq!(*ts, t2);
+                    assert_eq!(sample.payload.to_vec(), b"v1bis".to_vec());
+                }
+                _ => panic!("/a/1 not restored"),
+            }
+            assert!(matches!(map.get("/a/2"), Some(Removed { ts, sample: _ }) if *ts == t3));
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    }
+
+    #[test]
+    fn compaction() {
+        async_std::task::block_on(async {
+            let dir = test_dir("compaction");
+            let storage = {
+                let mut storage = FileStorage::new(Properties::default(), dir.clone())
+                    .await
+                    .unwrap();
+                for i in 0..(2 * COMPACTION_MIN_OBSOLETE_RECORDS as u64) {
+                    let payload = i.to_string();
+                    storage
+                        .on_sample(sample(
+                            "/a/1",
+                            ChangeKind::Put,
+                            timestamp(i + 1),
+                            payload.as_bytes(),
+                        ))
+                        .await
+                        .unwrap();
+                }
+                storage
+                    .on_sample(sample("/a/2", ChangeKind::Delete, timestamp(1), b""))
+                    .await
+                    .unwrap();
+                let mut compaction = TimedCompaction {
+                    map: storage.map.clone(),
+                    log: storage.log.clone(),
+                };
+                compaction.run().await;
+                // the old removal of /a/2 is forgotten
+                assert_eq!(storage.log.lock().await.records(), 1);
+                storage
+            };
+            drop(storage);
+
+            let storage = FileStorage::new(Properties::default(), dir.clone())
+                .await
+                .unwrap();
+            let map = storage.map.read().await;
+            assert_eq!(map.len(), 1);
+            let last = (2 * COMPACTION_MIN_OBSOLETE_RECORDS - 1).to_string();
+            assert!(
+                matches!(map.get("/a/1"), Some(Present { ts: _, sample }) if sample.payload.to_vec() == last.as_bytes())
+            );
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    }
+
+    #[test]
+    fn patch() {
+        async_std::task::block_on(async {
+            let dir = test_dir("patch");
+            let json = |kind, ts, payload: &str| {
+                let mut s = sample("/a/1", kind, ts, payload.as_bytes());
+                s.data_info.as_mut().unwrap().encoding = Some(zenoh::net::encoding::APP_JSON);
+                s
+            };
+            {
+                let mut storage = FileStorage::new(Properties::default(), dir.clone())
+                    .await
+                    .unwrap();
+                storage
+                    .on_sample(json(ChangeKind::Put, timestamp(1), r#"{"a":1,"b":2}"#))
+                    .await
+                    .unwrap();
+                storage
+                    .on_sample(json(ChangeKind::Patch, timestamp(2), r#"{"b":null,"c":3}"#))
+                    .await
+                    .unwrap();
+            }
+
+            let storage = FileStorage::new(Properties::default(), dir.clone())
+                .await
+                .unwrap();
+            let map = storage.map.read().await;
+            match map.get("/a/1") {
+                Some(Present { ts, sample }) => {
+                    assert_eq!(*ts, timestamp(2));
+                    assert!(
+                        matches!(Value::from_sample(sample, true), Ok(Some(Value::Json(s))) if s == r#"{"a":1,"c":3}"#)
+                    );
+                }
+                _ => panic!("/a/1 not restored"),
+            }
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    }
+}
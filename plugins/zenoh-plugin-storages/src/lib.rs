@@ -29,7 +29,9 @@ use zenoh_util::{zerror, LibLoader};
 
 mod backends_mgt;
 use backends_mgt::*;
+mod file_backend;
 mod memory_backend;
+mod replication;
 mod storages_mgt;
 
 pub fn get_expected_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
@@ -87,6 +89,7 @@ impl Plugin for StoragesPlugin {
 const BACKEND_LIB_PREFIX: &str = "zbackend_";
 const MEMORY_BACKEND_NAME: &str = "memory";
 const MEMORY_STORAGE_NAME: &str = "mem-storage";
+const FILE_BACKEND_NAME: &str = "file";
 
 async fn run(runtime: Runtime, args: ArgMatches<'_>) {
     // Try to initiate login.
@@ -195,6 +198,15 @@ const CREATE_BACKEND_FN_NAME: &[u8; 15] = b"create_backend\0";
 type CreateBackend<'lib> =
     Symbol<'lib, unsafe extern "C" fn(&Properties) -> ZResult<Box<dyn Backend>>>;
 
+// Creates a backend that is built in this plugin, if `name` corresponds to such a backend.
+fn create_builtin_backend(name: &str, props: &Properties) -> Option<ZResult<Box<dyn Backend>>> {
+    match name {
+        MEMORY_BACKEND_NAME => Some(memory_backend::create_backend(props.clone())),
+        FILE_BACKEND_NAME => Some(file_backend::create_backend(props.clone())),
+        _ => None,
+    }
+}
+
 async fn load_and_start_backend(
     path: &Path,
     value: Value,
@@ -203,6 +215,13 @@ async fn load_and_start_backend(
 ) -> ZResult<Sender<bool>> {
     if let Value::Properties(props) = value {
         let name = path.last_segment();
+        if props.get("lib").is_none() {
+            if let Some(backend) = create_builtin_backend(name, &props) {
+                debug!("Create built-in backend {}", name);
+                return start_backend(backend?, path.clone(), zenoh).await;
+            }
+        }
+
         let (lib, lib_path) = unsafe {
             if let Some(filename) = props.get("lib") {
                 LibLoader::load_file(filename)?
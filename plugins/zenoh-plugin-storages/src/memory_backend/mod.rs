@@ -13,15 +13,47 @@
 //
 use async_std::sync::{Arc, RwLock};
 use async_trait::async_trait;
-use log::{debug, trace, warn};
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use log::{debug, trace};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::time::{Duration, Instant};
 use zenoh::net::utils::resource_name;
 use zenoh::net::Sample;
-use zenoh::{utils, ChangeKind, Properties, Timestamp, Value, ZResult};
+use zenoh::{
+    utils, ChangeKind, Properties, Selector, Timestamp, Value, ZError, ZErrorKind, ZResult,
+};
+use zenoh_backend_traits::utils::patch_sample;
 use zenoh_backend_traits::*;
 use zenoh_util::collections::{Timed, TimedEvent, TimedHandle, Timer};
+use zenoh_util::zerror;
+
+mod time_series;
+use time_series::MemoryTimeSeriesStorage;
+
+/// The `"history"` property key to configure which values a memory storage keeps for each path:
+///  - `"latest"` (default): only the latest value
+///  - `"all"`: all the values, allowing to reply to queries with a time-range
+///    (i.e. with `"starttime"` and/or `"stoptime"` properties)
+pub const PROP_STORAGE_HISTORY: &str = "history";
+const HISTORY_LATEST: &str = "latest";
+const HISTORY_ALL: &str = "all";
+
+/// The `"max_keys"` property key to configure the maximum number of paths a memory storage keeps a value for
+/// (only with `"history=latest"`).
+pub const PROP_STORAGE_MAX_KEYS: &str = "max_keys";
+/// The `"max_bytes"` property key to configure the maximum total size of the payloads a memory storage keeps
+/// (only with `"history=latest"`).
+pub const PROP_STORAGE_MAX_BYTES: &str = "max_bytes";
+/// The `"ttl"` property key to configure the time (in seconds) after which a value is removed from a memory storage
+/// (only with `"history=latest"`).
+pub const PROP_STORAGE_TTL: &str = "ttl";
+/// The `"eviction"` property key to configure which values a memory storage evicts
+/// when its `"max_keys"` or `"max_bytes"` limit is reached:
+///  - `"lru"` (default): the least recently used values (i.e. put or replied to a query)
+///  - `"oldest"`: the least recently put values
+pub const PROP_STORAGE_EVICTION: &str = "eviction";
+const EVICTION_LRU: &str = "lru";
+const EVICTION_OLDEST: &str = "oldest";
 
 pub fn create_backend(_unused: Properties) -> ZResult<Box<dyn Backend>> {
     // For now admin status is static and only contains a PROP_BACKEND_TYPE entry
@@ -42,7 +74,34 @@ impl Backend for MemoryBackend {
 
     async fn create_storage(&mut self, properties: Properties) -> ZResult<Box<dyn Storage>> {
         debug!("Create Memory Storage with properties: {}", properties);
-        Ok(Box::new(MemoryStorage::new(properties).await?))
+        match properties.get(PROP_STORAGE_HISTORY).map(String::as_str) {
+            None | Some(HISTORY_LATEST) => Ok(Box::new(MemoryStorage::new(properties).await?)),
+            Some(HISTORY_ALL) => {
+                if let Some(key) = [
+                    PROP_STORAGE_MAX_KEYS,
+                    PROP_STORAGE_MAX_BYTES,
+                    PROP_STORAGE_TTL,
+                    PROP_STORAGE_EVICTION,
+                ]
+                .iter()
+                .find(|key| properties.contains_key(**key))
+                {
+                    return zerror!(ZErrorKind::Other {
+                        descr: format!(
+                            "Can't create memory storage: {} property is not supported with {}={}",
+                            key, PROP_STORAGE_HISTORY, HISTORY_ALL
+                        )
+                    });
+                }
+                Ok(Box::new(MemoryTimeSeriesStorage::new(properties).await?))
+            }
+            Some(history) => zerror!(ZErrorKind::Other {
+                descr: format!(
+                    "Can't create memory storage: unsupported {} property: {} (expected: '{}' or '{}')",
+                    PROP_STORAGE_HISTORY, history, HISTORY_LATEST, HISTORY_ALL
+                )
+            }),
+        }
     }
 
     fn incoming_data_interceptor(&self) -> Option<Box<dyn IncomingDataInterceptor>> {
@@ -87,10 +146,90 @@ impl Drop for MemoryBackend {
     }
 }
 
+// The limits of a MemoryStorage, configured via its properties
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Limits {
+    max_keys: Option<usize>,
+    max_bytes: Option<usize>,
+    ttl: Option<Duration>,
+    lru: bool,
+}
+
+impl Limits {
+    fn new(props: &Properties) -> ZResult<Limits> {
+        let max_keys = parse_prop(props, PROP_STORAGE_MAX_KEYS, "a positive integer", |s| {
+            s.parse::<usize>().ok().filter(|n| *n > 0)
+        })?;
+        let max_bytes = parse_prop(props, PROP_STORAGE_MAX_BYTES, "a positive integer", |s| {
+            s.parse::<usize>().ok().filter(|n| *n > 0)
+        })?;
+        let ttl = parse_prop(
+            props,
+            PROP_STORAGE_TTL,
+            "a positive number of seconds",
+            |s| {
+                s.parse::<f64>()
+                    .ok()
+                    .filter(|secs| *secs > 0.0)
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            },
+        )?;
+        let lru = match props.get(PROP_STORAGE_EVICTION).map(String::as_str) {
+            None | Some(EVICTION_LRU) => true,
+            Some(EVICTION_OLDEST) => false,
+            Some(eviction) => {
+                return zerror!(ZErrorKind::Other {
+                    descr: format!(
+                        "Invalid {} property: {} (expected: '{}' or '{}')",
+                        PROP_STORAGE_EVICTION, eviction, EVICTION_LRU, EVICTION_OLDEST
+                    )
+                })
+            }
+        };
+        Ok(Limits {
+            max_keys,
+            max_bytes,
+            ttl,
+            lru,
+        })
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.max_keys.is_some() || self.max_bytes.is_some()
+    }
+}
+
+fn parse_prop<T>(
+    props: &Properties,
+    key: &str,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> ZResult<Option<T>> {
+    match props.get(key) {
+        Some(s) => match parse(s) {
+            Some(value) => Ok(Some(value)),
+            None => zerror!(ZErrorKind::Other {
+                descr: format!("Invalid {} property: {} (expected {})", key, s, expected)
+            }),
+        },
+        None => Ok(None),
+    }
+}
+
+// The number of values evicted by a MemoryStorage, for each limit
+#[derive(Default)]
+struct Evictions {
+    max_keys: u64,
+    max_bytes: u64,
+    ttl: u64,
+}
+
 enum StoredValue {
     Present {
         ts: Timestamp,
         sample: Sample,
+        // rank of the value in the eviction order
+        rank: u64,
     },
     Removed {
         ts: Timestamp,
@@ -102,153 +241,297 @@ enum StoredValue {
 impl StoredValue {
     fn ts(&self) -> &Timestamp {
         match self {
-            Present { ts, sample: _ } => ts,
-            Removed {
-                ts,
-                cleanup_handle: _,
-            } => ts,
+            Present { ts, .. } => ts,
+            Removed { ts, .. } => ts,
         }
     }
 }
 use StoredValue::{Present, Removed};
 
+// The values of a MemoryStorage, with what is required to enforce its limits
+#[derive(Default)]
+struct StoredValues {
+    map: HashMap<String, StoredValue>,
+    // the paths of the present values, by rank (i.e. the first one is the first to be evicted)
+    eviction_order: BTreeMap<u64, String>,
+    next_rank: u64,
+    // the total size of the present values' payloads
+    bytes: usize,
+    evictions: Evictions,
+}
+
+impl StoredValues {
+    fn put(&mut self, path: String, ts: Timestamp, sample: Sample) {
+        let rank = self.next_rank;
+        self.next_rank += 1;
+        self.eviction_order.insert(rank, path.clone());
+        self.bytes += sample.payload.len();
+        self.replace(path, Present { ts, sample, rank });
+    }
+
+    fn remove(&mut self, path: String, ts: Timestamp, cleanup_handle: TimedHandle) {
+        self.replace(path, Removed { ts, cleanup_handle });
+    }
+
+    fn replace(&mut self, path: String, value: StoredValue) {
+        match self.map.insert(path, value) {
+            Some(Present { sample, rank, .. }) => {
+                self.eviction_order.remove(&rank);
+                self.bytes -= sample.payload.len();
+            }
+            Some(Removed { cleanup_handle, .. }) => {
+                // cancel timed cleanup
+                cleanup_handle.defuse();
+            }
+            None => (),
+        }
+    }
+
+    // Completely removes a present value (i.e. without keeping its removal time)
+    fn drop_present(&mut self, path: &str) {
+        if let Some(Present { .. }) = self.map.get(path) {
+            if let Some(Present { sample, rank, .. }) = self.map.remove(path) {
+                self.eviction_order.remove(&rank);
+                self.bytes -= sample.payload.len();
+            }
+        }
+    }
+
+    // Moves a present value at the end of the eviction order
+    fn touch(&mut self, path: &str) {
+        if let Some(Present { rank, .. }) = self.map.get_mut(path) {
+            self.eviction_order.remove(rank);
+            *rank = self.next_rank;
+            self.next_rank += 1;
+            self.eviction_order.insert(*rank, path.to_string());
+        }
+    }
+
+    // Evicts the first values in the eviction order, until the limits are respected
+    fn evict(&mut self, limits: &Limits) {
+        loop {
+            let keys_exceeded =
+                matches!(limits.max_keys, Some(max) if self.eviction_order.len() > max);
+            let bytes_exceeded = matches!(limits.max_bytes, Some(max) if self.bytes > max);
+            if !keys_exceeded && !bytes_exceeded {
+                return;
+            }
+            let path = match self.eviction_order.values().next() {
+                Some(path) => path.clone(),
+                None => return,
+            };
+            trace!("Evict {} from memory storage", path);
+            self.drop_present(&path);
+            if keys_exceeded {
+                self.evictions.max_keys += 1;
+            } else {
+                self.evictions.max_bytes += 1;
+            }
+        }
+    }
+}
+
 struct MemoryStorage {
-    admin_status: Value,
-    map: Arc<RwLock<HashMap<String, StoredValue>>>,
+    properties: Properties,
+    limits: Limits,
+    values: Arc<RwLock<StoredValues>>,
     timer: Timer,
 }
 
 impl MemoryStorage {
     async fn new(properties: Properties) -> ZResult<MemoryStorage> {
-        let admin_status = utils::properties_to_json_value(&properties);
+        let limits = Limits::new(&properties)?;
 
         Ok(MemoryStorage {
-            admin_status,
-            map: Arc::new(RwLock::new(HashMap::new())),
+            properties,
+            limits,
+            values: Arc::new(RwLock::new(StoredValues::default())),
             timer: Timer::new(),
         })
     }
 }
 
 impl MemoryStorage {
-    async fn schedule_cleanup(&self, path: String) -> TimedHandle {
-        let event = TimedEvent::once(
+    // NOTE: the returned events must be added to the timer only once the lock on the values is released,
+    // since the timer might be waiting for this lock to run a previous event.
+    fn cleanup_event(&self, path: String) -> TimedEvent {
+        TimedEvent::once(
             Instant::now() + Duration::from_millis(CLEANUP_TIMEOUT_MS),
             TimedCleanup {
-                map: self.map.clone(),
+                values: self.values.clone(),
                 path,
             },
-        );
-        let handle = event.get_handle();
-        self.timer.add(event).await;
-        handle
+        )
+    }
+
+    fn expiration_event(&self, path: String, ts: Timestamp) -> Option<TimedEvent> {
+        self.limits.ttl.map(|ttl| {
+            TimedEvent::once(
+                Instant::now() + ttl,
+                TimedExpiration {
+                    values: self.values.clone(),
+                    path,
+                    ts,
+                },
+            )
+        })
     }
 }
 
 #[async_trait]
 impl Storage for MemoryStorage {
     async fn get_admin_status(&self) -> Value {
-        self.admin_status.clone()
+        let mut json = self
+            .properties
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+        if self.limits.is_bounded() || self.limits.ttl.is_some() {
+            let values = self.values.read().await;
+            json.insert(
+                "evictions".into(),
+                serde_json::json!({
+                    "max_keys": values.evictions.max_keys,
+                    "max_bytes": values.evictions.max_bytes,
+                    "ttl": values.evictions.ttl,
+                }),
+            );
+        }
+        Value::Json(serde_json::Value::Object(json).to_string())
     }
 
     async fn on_sample(&mut self, sample: Sample) -> ZResult<()> {
         trace!("on_sample for {}", sample.res_name);
-        let (kind, timestamp) = if let Some(ref info) = sample.data_info {
-            (
-                info.kind.map_or(ChangeKind::Put, ChangeKind::from),
-                match &info.timestamp {
-                    Some(ts) => *ts,
-                    None => utils::new_reception_timestamp(),
-                },
-            )
-        } else {
-            (ChangeKind::Put, utils::new_reception_timestamp())
-        };
-        match kind {
-            ChangeKind::Put => match self.map.write().await.entry(sample.res_name.clone()) {
-                Entry::Vacant(v) => {
-                    v.insert(Present {
-                        sample,
-                        ts: timestamp,
-                    });
+        let (kind, timestamp) = get_kind_and_timestamp(&sample);
+        let path = sample.res_name.clone();
+        let mut values = self.values.write().await;
+        let event = match kind {
+            ChangeKind::Put => match values.map.get(&path) {
+                Some(old_val) if old_val.ts() >= &timestamp => {
+                    debug!("PUT on {} dropped: out-of-date", path);
+                    None
                 }
-                Entry::Occupied(mut o) => {
-                    let old_val = o.get();
-                    if old_val.ts() < &timestamp {
-                        if let Removed {
-                            ts: _,
-                            cleanup_handle,
-                        } = old_val
-                        {
-                            // cancel timed cleanup
-                            cleanup_handle.clone().defuse();
-                        }
-                        o.insert(Present {
-                            sample,
-                            ts: timestamp,
-                        });
-                    } else {
-                        debug!("PUT on {} dropped: out-of-date", sample.res_name);
-                    }
+                _ => {
+                    values.put(path.clone(), timestamp, sample);
+                    self.expiration_event(path, timestamp)
                 }
             },
-            ChangeKind::Delete => match self.map.write().await.entry(sample.res_name.clone()) {
-                Entry::Vacant(v) => {
-                    // NOTE: even if path is not known yet, we need to store the removal time:
-                    // if ever a put with a lower timestamp arrive (e.g. msg inversion between put and remove)
-                    // we must drop the put.
-                    let cleanup_handle = self.schedule_cleanup(sample.res_name.clone()).await;
-                    v.insert(Removed {
-                        ts: timestamp,
-                        cleanup_handle,
-                    });
+            ChangeKind::Delete => match values.map.get(&path) {
+                Some(Removed { .. }) => None, // nothing to do
+                Some(Present { ts, .. }) if ts >= &timestamp => {
+                    debug!("DEL on {} dropped: out-of-date", path);
+                    None
                 }
-                Entry::Occupied(mut o) => {
-                    match o.get() {
-                        Removed {
-                            ts: _,
-                            cleanup_handle: _,
-                        } => (), // nothing to do
-                        Present { sample: _, ts } => {
-                            if ts < &timestamp {
-                                let cleanup_handle =
-                                    self.schedule_cleanup(sample.res_name.clone()).await;
-                                o.insert(Removed {
-                                    ts: timestamp,
-                                    cleanup_handle,
-                                });
-                            } else {
-                                debug!("DEL on {} dropped: out-of-date", sample.res_name);
-                            }
-                        }
-                    }
+                // NOTE: even if path is not known yet, we need to store the removal time:
+                // if ever a put with a lower timestamp arrive (e.g. msg inversion between put and remove)
+                // we must drop the put.
+                _ => {
+                    let event = self.cleanup_event(path.clone());
+                    values.remove(path, timestamp, event.get_handle());
+                    Some(event)
                 }
             },
             ChangeKind::Patch => {
-                warn!("Received PATCH for {}: not yet supported", sample.res_name);
+                let patched = match values.map.get(&path) {
+                    Some(old_val) if old_val.ts() >= &timestamp => {
+                        debug!("PATCH on {} dropped: out-of-date", path);
+                        None
+                    }
+                    Some(Present {
+                        sample: old_sample, ..
+                    }) => Some(patch_sample(Some(old_sample), &sample)?),
+                    _ => Some(patch_sample(None, &sample)?),
+                };
+                match patched {
+                    Some(patched) => {
+                        values.put(path.clone(), timestamp, patched);
+                        self.expiration_event(path, timestamp)
+                    }
+                    None => None,
+                }
             }
+        };
+        values.evict(&self.limits);
+        drop(values);
+        if let Some(event) = event {
+            self.timer.add(event).await;
         }
         Ok(())
     }
 
     async fn on_query(&mut self, query: Query) -> ZResult<()> {
         trace!("on_query for {}", query.res_name());
-        if !query.res_name().contains('*') {
-            if let Some(Present { sample, ts: _ }) = self.map.read().await.get(query.res_name()) {
-                query.reply(sample.clone()).await;
-            }
-        } else {
-            for (_, stored_value) in self.map.read().await.iter() {
-                if let Present { sample, ts: _ } = stored_value {
-                    if resource_name::intersect(query.res_name(), &sample.res_name) {
-                        let s: Sample = sample.clone();
-                        query.reply(s).await;
-                    }
+        let selector = Selector::try_from(&query)?;
+        // only the latest value is stored: reply it only if within the time-range (if any)
+        let time_range = selector.time_range()?;
+        let filter = selector.value_filter()?;
+        let matches = |ts: &Timestamp, sample: &Sample| {
+            time_range.iter().all(|r| r.contains_timestamp(ts))
+                && filter.iter().all(|f| f.matches_sample(sample))
+        };
+        let replies: Vec<Sample> = {
+            let mut values = self.values.write().await;
+            let replies: Vec<Sample> = if !query.res_name().contains('*') {
+                match values.map.get(query.res_name()) {
+                    Some(Present { sample, ts, .. }) if matches(ts, sample) => vec![sample.clone()],
+                    _ => vec![],
+                }
+            } else {
+                values
+                    .map
+                    .values()
+                    .filter_map(|stored_value| match stored_value {
+                        Present { sample, ts, .. }
+                            if resource_name::intersect(query.res_name(), &sample.res_name)
+                                && matches(ts, sample) =>
+                        {
+                            Some(sample.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            };
+            // the replied values are used: they're the last ones to be evicted in LRU mode
+            if self.limits.lru && self.limits.is_bounded() {
+                for sample in replies.iter() {
+                    values.touch(&sample.res_name);
                 }
             }
+            replies
+        };
+        for sample in replies {
+            query.reply(sample).await;
         }
         Ok(())
     }
+
+    async fn get_stats(&self) -> Option<StorageStats> {
+        let values = self.values.read().await;
+        Some(StorageStats {
+            keys: values.eviction_order.len(),
+            bytes: values.bytes,
+        })
+    }
+
+    async fn reconfigure(&mut self, properties: Properties) -> ZResult<()> {
+        match properties.get(PROP_STORAGE_HISTORY).map(String::as_str) {
+            None | Some(HISTORY_LATEST) => (),
+            Some(history) => {
+                return zerror!(ZErrorKind::Other {
+                    descr: format!(
+                        "Can't change the {} property of a memory storage to {}",
+                        PROP_STORAGE_HISTORY, history
+                    )
+                })
+            }
+        }
+        // NOTE: a ttl change only applies to the values stored afterwards
+        let limits = Limits::new(&properties)?;
+        self.values.write().await.evict(&limits);
+        self.properties = properties;
+        self.limits = limits;
+        Ok(())
+    }
 }
 
 impl Drop for MemoryStorage {
@@ -258,16 +541,175 @@ impl Drop for MemoryStorage {
     }
 }
 
+// Returns the kind of change and the timestamp of a Sample,
+// generating a reception timestamp if the Sample has none.
+fn get_kind_and_timestamp(sample: &Sample) -> (ChangeKind, Timestamp) {
+    if let Some(ref info) = sample.data_info {
+        (
+            info.kind.map_or(ChangeKind::Put, ChangeKind::from),
+            match &info.timestamp {
+                Some(ts) => *ts,
+                None => utils::new_reception_timestamp(),
+            },
+        )
+    } else {
+        (ChangeKind::Put, utils::new_reception_timestamp())
+    }
+}
+
 const CLEANUP_TIMEOUT_MS: u64 = 5000;
 
 struct TimedCleanup {
-    map: Arc<RwLock<HashMap<String, StoredValue>>>,
+    values: Arc<RwLock<StoredValues>>,
     path: String,
 }
 
 #[async_trait]
 impl Timed for TimedCleanup {
     async fn run(&mut self) {
-        self.map.write().await.remove(&self.path);
+        let mut values = self.values.write().await;
+        if let Some(Removed { .. }) = values.map.get(&self.path) {
+            values.map.remove(&self.path);
+        }
+    }
+}
+
+struct TimedExpiration {
+    values: Arc<RwLock<StoredValues>>,
+    path: String,
+    // timestamp of the value to expire (the path might have been updated since)
+    ts: Timestamp,
+}
+
+#[async_trait]
+impl Timed for TimedExpiration {
+    async fn run(&mut self) {
+        let mut values = self.values.write().await;
+        if let Some(Present { ts, .. }) = values.map.get(&self.path) {
+            if ts == &self.ts {
+                trace!("Expire {} from memory storage", self.path);
+                values.drop_present(&self.path);
+                values.evictions.ttl += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh::net::DataInfo;
+    use zenoh::TimestampId;
+
+    fn timestamp(secs: u64) -> Timestamp {
+        Timestamp::new(
+            Duration::from_secs(secs).into(),
+            TimestampId::new(1, [0u8; TimestampId::MAX_SIZE]),
+        )
+    }
+
+    fn sample(res_name: &str, secs: u64, payload: &[u8]) -> Sample {
+        let mut info = DataInfo::new();
+        info.timestamp = Some(timestamp(secs));
+        Sample {
+            res_name: res_name.into(),
+            payload: payload.into(),
+            data_info: Some(info),
+        }
+    }
+
+    async fn paths(storage: &MemoryStorage) -> Vec<String> {
+        let values = storage.values.read().await;
+        let mut paths: Vec<String> = values.eviction_order.values().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn evict_max_keys_and_bytes() {
+        async_std::task::block_on(async {
+            let mut storage = MemoryStorage::new(Properties::from("max_keys=2;max_bytes=6"))
+                .await
+                .unwrap();
+            for (i, path) in ["/a/1", "/a/2", "/a/3"].iter().enumerate() {
+                storage
+                    .on_sample(sample(path, i as u64, b"v"))
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(paths(&storage).await, vec!["/a/2", "/a/3"]);
+            // an update moves the value at the end of the eviction order
+            storage.on_sample(sample("/a/2", 10, b"v")).await.unwrap();
+            storage
+                .on_sample(sample("/a/4", 11, b"vvvv"))
+                .await
+                .unwrap();
+            assert_eq!(paths(&storage).await, vec!["/a/2", "/a/4"]);
+            // too many keys: "/a/2" is evicted, then too many bytes: "/a/4" is evicted
+            storage.on_sample(sample("/a/5", 12, b"vvv")).await.unwrap();
+            storage.on_sample(sample("/a/6", 13, b"v")).await.unwrap();
+            assert_eq!(paths(&storage).await, vec!["/a/5", "/a/6"]);
+            assert_eq!(
+                storage.get_stats().await,
+                Some(StorageStats { keys: 2, bytes: 4 })
+            );
+            {
+                let values = storage.values.read().await;
+                assert_eq!(values.evictions.max_keys, 3);
+                assert_eq!(values.evictions.max_bytes, 1);
+            }
+
+            // lowering the limits at runtime evicts the oldest values
+            storage
+                .reconfigure(Properties::from("max_keys=1;eviction=oldest"))
+                .await
+                .unwrap();
+            assert_eq!(paths(&storage).await, vec!["/a/6"]);
+            assert!(storage
+                .reconfigure(Properties::from("max_keys=0"))
+                .await
+                .is_err());
+            assert!(storage
+                .reconfigure(Properties::from("history=all"))
+                .await
+                .is_err());
+        })
+    }
+
+    #[test]
+    fn expire_ttl() {
+        async_std::task::block_on(async {
+            // a ttl long enough for the timer not to interfere: the expirations are run explicitly
+            let mut storage = MemoryStorage::new(Properties::from("ttl=3600"))
+                .await
+                .unwrap();
+            storage.on_sample(sample("/a/1", 1, b"v")).await.unwrap();
+            storage.on_sample(sample("/a/2", 2, b"v")).await.unwrap();
+            storage.on_sample(sample("/a/2", 3, b"v")).await.unwrap();
+            let expire = |path: &str, secs| TimedExpiration {
+                values: storage.values.clone(),
+                path: path.to_string(),
+                ts: timestamp(secs),
+            };
+            // "/a/2" was updated since its first expiration was scheduled
+            expire("/a/1", 1).run().await;
+            expire("/a/2", 2).run().await;
+            assert_eq!(paths(&storage).await, vec!["/a/2"]);
+            expire("/a/2", 3).run().await;
+            assert!(paths(&storage).await.is_empty());
+            assert_eq!(storage.values.read().await.evictions.ttl, 2);
+
+            // the expirations are scheduled on the storage's timer
+            let mut storage = MemoryStorage::new(Properties::from("ttl=0.1"))
+                .await
+                .unwrap();
+            storage.on_sample(sample("/a/1", 1, b"v")).await.unwrap();
+            let start = Instant::now();
+            while !paths(&storage).await.is_empty() {
+                assert!(start.elapsed() < Duration::from_secs(10));
+                async_std::task::sleep(Duration::from_millis(50)).await;
+            }
+            assert_eq!(storage.values.read().await.evictions.ttl, 1);
+        })
     }
 }
@@ -0,0 +1,248 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use super::get_kind_and_timestamp;
+use async_trait::async_trait;
+use log::{debug, trace};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use zenoh::net::utils::resource_name;
+use zenoh::net::Sample;
+use zenoh::{utils, ChangeKind, Properties, Selector, Timestamp, Value, ZResult};
+use zenoh_backend_traits::utils::patch_sample;
+use zenoh_backend_traits::*;
+
+/// A memory storage keeping the history of all the changes for each path.
+///
+/// A query with a time-range (i.e. with `"starttime"` and/or `"stoptime"` properties)
+/// is replied with all the values put within this time-range.
+/// A query without time-range is replied with the latest value only (as for a [`super::MemoryStorage`]).
+/// A PATCH is applied to the latest value preceding it, the result being stored as a new value.
+pub(crate) struct MemoryTimeSeriesStorage {
+    admin_status: Value,
+    // for each path, its changes ordered by timestamp (a deletion being stored as None)
+    map: HashMap<String, BTreeMap<Timestamp, Option<Sample>>>,
+}
+
+impl MemoryTimeSeriesStorage {
+    pub(crate) async fn new(properties: Properties) -> ZResult<MemoryTimeSeriesStorage> {
+        let admin_status = utils::properties_to_json_value(&properties);
+
+        Ok(MemoryTimeSeriesStorage {
+            admin_status,
+            map: HashMap::new(),
+        })
+    }
+
+    // Returns the samples matching `res_name` and `selector` to be replied to a query
+    fn select(&self, res_name: &str, selector: &Selector) -> ZResult<Vec<Sample>> {
+        let time_range = selector.time_range()?;
+        let filter = selector.value_filter()?;
+        let matches = |sample: &Sample| filter.iter().all(|f| f.matches_sample(sample));
+        let mut samples = vec![];
+        for (path, history) in self.map.iter() {
+            if !resource_name::intersect(res_name, path) {
+                continue;
+            }
+            match time_range {
+                Some(range) => {
+                    for (ts, change) in history.iter() {
+                        if let Some(sample) = change {
+                            if range.contains_timestamp(ts) && matches(sample) {
+                                samples.push(sample.clone());
+                            }
+                        }
+                    }
+                }
+                None => {
+                    if let Some((_, Some(sample))) = history.iter().next_back() {
+                        if matches(sample) {
+                            samples.push(sample.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(samples)
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryTimeSeriesStorage {
+    async fn get_admin_status(&self) -> Value {
+        self.admin_status.clone()
+    }
+
+    async fn on_sample(&mut self, sample: Sample) -> ZResult<()> {
+        trace!("on_sample for {}", sample.res_name);
+        let (kind, timestamp) = get_kind_and_timestamp(&sample);
+        let history = self.map.entry(sample.res_name.clone()).or_default();
+        let change = match kind {
+            ChangeKind::Put => Some(sample.clone()),
+            ChangeKind::Delete => None,
+            ChangeKind::Patch => {
+                // apply the patch to the latest value preceding it (if any)
+                let previous = history
+                    .range(..timestamp)
+                    .next_back()
+                    .and_then(|(_, change)| change.as_ref());
+                Some(patch_sample(previous, &sample)?)
+            }
+        };
+        match history.entry(timestamp) {
+            Entry::Vacant(v) => {
+                v.insert(change);
+            }
+            Entry::Occupied(_) => {
+                debug!(
+                    "{} on {} dropped: already stored with timestamp {}",
+                    kind, sample.res_name, timestamp
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_query(&mut self, query: Query) -> ZResult<()> {
+        trace!("on_query for {}", query.res_name());
+        let selector = Selector::try_from(&query)?;
+        for sample in self.select(query.res_name(), &selector)? {
+            query.reply(sample).await;
+        }
+        Ok(())
+    }
+
+    async fn get_stats(&self) -> Option<StorageStats> {
+        let mut stats = StorageStats::default();
+        for history in self.map.values() {
+            if let Some((_, Some(_))) = history.iter().next_back() {
+                stats.keys += 1;
+            }
+            stats.bytes += history
+                .values()
+                .flatten()
+                .map(|sample| sample.payload.len())
+                .sum::<usize>();
+        }
+        Some(stats)
+    }
+}
+
+impl Drop for MemoryTimeSeriesStorage {
+    fn drop(&mut self) {
+        // nothing to do in case of memory backend
+        trace!("MemoryTimeSeriesStorage::drop()");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use zenoh::net::DataInfo;
+    use zenoh::TimestampId;
+
+    fn sample(res_name: &str, kind: ChangeKind, secs: u64, payload: &[u8]) -> Sample {
+        let mut info = DataInfo::new();
+        info.kind = Some(kind as u64);
+        info.timestamp = Some(Timestamp::new(
+            Duration::from_secs(secs).into(),
+            TimestampId::new(1, [0u8; TimestampId::MAX_SIZE]),
+        ));
+        Sample {
+            res_name: res_name.into(),
+            payload: payload.into(),
+            data_info: Some(info),
+        }
+    }
+
+    fn select(storage: &MemoryTimeSeriesStorage, selector: &str) -> Vec<(String, Vec<u8>)> {
+        let selector = Selector::try_from(selector).unwrap();
+        let mut samples: Vec<(String, Vec<u8>)> = storage
+            .select(selector.path_expr.as_str(), &selector)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.res_name, s.payload.to_vec()))
+            .collect();
+        samples.sort();
+        samples
+    }
+
+    fn expected(samples: &[(&str, &str)]) -> Vec<(String, Vec<u8>)> {
+        samples
+            .iter()
+            .map(|(p, v)| (p.to_string(), v.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn time_series() {
+        async_std::task::block_on(async {
+            let mut storage = MemoryTimeSeriesStorage::new(Properties::default())
+                .await
+                .unwrap();
+            for (path, kind, secs, payload) in [
+                ("/a/1", ChangeKind::Put, 10, "v10"),
+                ("/a/1", ChangeKind::Put, 20, "v20"),
+                ("/a/1", ChangeKind::Put, 30, "v30"),
+                ("/a/2", ChangeKind::Put, 15, "w15"),
+                ("/a/2", ChangeKind::Delete, 25, ""),
+            ] {
+                storage
+                    .on_sample(sample(path, kind, secs, payload.as_bytes()))
+                    .await
+                    .unwrap();
+            }
+
+            // without time range, only the latest values are replied
+            assert_eq!(select(&storage, "/a/*"), expected(&[("/a/1", "v30")]));
+
+            // with a time range, all the values within the range are replied
+            assert_eq!(
+                select(&storage, "/a/*?(starttime=15;stoptime=20)"),
+                expected(&[("/a/1", "v20"), ("/a/2", "w15")])
+            );
+            assert_eq!(
+                select(&storage, "/a/1?(starttime=20)"),
+                expected(&[("/a/1", "v20"), ("/a/1", "v30")])
+            );
+            assert_eq!(
+                select(&storage, "/a/*?(stoptime=10)"),
+                expected(&[("/a/1", "v10")])
+            );
+            assert_eq!(select(&storage, "/a/*?(starttime=0)").len(), 4);
+
+            // a deletion hides the latest value but keeps the history
+            storage
+                .on_sample(sample("/a/1", ChangeKind::Delete, 40, b""))
+                .await
+                .unwrap();
+            assert!(select(&storage, "/a/*").is_empty());
+            assert_eq!(
+                select(&storage, "/a/1?(starttime=25)"),
+                expected(&[("/a/1", "v30")])
+            );
+            // a value put after the deletion is the new latest value
+            storage
+                .on_sample(sample("/a/1", ChangeKind::Put, 50, b"v50"))
+                .await
+                .unwrap();
+            assert_eq!(select(&storage, "/a/*"), expected(&[("/a/1", "v50")]));
+            assert_eq!(
+                storage.get_stats().await,
+                Some(StorageStats { keys: 1, bytes: 15 })
+            );
+        })
+    }
+}
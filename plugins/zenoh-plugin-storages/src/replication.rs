@@ -0,0 +1,830 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+
+//! Anti-entropy between the storages having the same path expression.
+//!
+//! Each storage with replication enabled maintains a [`Digest`] of the keys it stores, with the timestamp
+//! of their latest change. The keys are distributed into buckets, each bucket having a checksum.
+//! Periodically, each storage publishes its digest on `<storage_admin_path>/digest`.
+//! When a storage receives a digest from a storage with the same path expression that differs from its own,
+//! it queries the content of the differing buckets on `<storage_admin_path>/digest/<bucket>`,
+//! and retrieves the values it misses (or that are stale) from this storage only, with a query on
+//! `<storage_admin_path>/replica/<key>` which is answered by the storage itself.
+//! Thus each storage only pulls from the others the changes it misses.
+//!
+//! When the replication starts, the digest is seeded with the content already held by the storage
+//! (e.g. restored from disk), querying it via its own `<storage_admin_path>/replica` path.
+use async_std::channel::Sender;
+use async_std::stream::Interval;
+use async_std::sync::Arc;
+use async_std::task;
+use futures::prelude::*;
+use futures::select;
+use log::{debug, trace, warn};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+use zenoh::net::utils::resource_name;
+use zenoh::net::{
+    data_kind, queryable, DataInfo, Query, QueryConsolidation, QueryTarget, Queryable, Sample,
+    Target, ZBuf,
+};
+use zenoh::{
+    ChangeReceiver, GetRequest, GetRequestStream, Path, PathExpr, Selector, Timestamp, Value,
+    Workspace, ZResult, Zenoh,
+};
+
+/// The suffix of a storage's admin path where its digest is published
+const DIGEST_SUFFIX: &str = "digest";
+/// The suffix of a storage's admin path where its content can be queried by the other storages
+const REPLICA_SUFFIX: &str = "replica";
+/// The selector to subscribe to the digests of all storages
+const DIGESTS_SELECTOR: &str = "/@/router/*/plugin/storages/backend/*/storage/*/digest";
+/// Number of buckets the keys are distributed into
+const BUCKETS: usize = 64;
+/// The removals older than this are forgotten
+const REMOVAL_EXPIRATION: Duration = Duration::from_secs(60);
+/// A peer storage not publishing its digest for this number of periods is forgotten
+const PEER_EXPIRATION_PERIODS: u32 = 3;
+
+/// The latest change for a key, as recorded in a [`Digest`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct DigestEntry {
+    pub(crate) ts: Timestamp,
+    pub(crate) deleted: bool,
+}
+
+/// The set of keys stored by a storage with the timestamp of their latest change,
+/// with a checksum per bucket of keys.
+pub(crate) struct Digest {
+    entries: HashMap<String, DigestEntry>,
+    checksums: [u64; BUCKETS],
+}
+
+impl Digest {
+    pub(crate) fn new() -> Digest {
+        Digest {
+            entries: HashMap::new(),
+            checksums: [0; BUCKETS],
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Records a change for `key`, if more recent than the one already recorded.
+    pub(crate) fn update(&mut self, key: &str, entry: DigestEntry) {
+        let bucket = bucket_of(key);
+        if let Some(old) = self.entries.get(key) {
+            if old.ts >= entry.ts {
+                return;
+            }
+            self.checksums[bucket] ^= entry_hash(key, old);
+        }
+        self.checksums[bucket] ^= entry_hash(key, &entry);
+        self.entries.insert(key.to_string(), entry);
+    }
+
+    /// Forgets the keys not matching `path_expr`.
+    pub(crate) fn retain_matching(&mut self, path_expr: &PathExpr) {
+        let checksums = &mut self.checksums;
+        self.entries.retain(|key, entry| {
+            if resource_name::intersect(path_expr.as_str(), key) {
+                true
+            } else {
+                checksums[bucket_of(key)] ^= entry_hash(key, entry);
+                false
+            }
+        });
+    }
+
+    /// Forgets the removals older than [`REMOVAL_EXPIRATION`].
+    pub(crate) fn expire_removals(&mut self, now: SystemTime) {
+        let checksums = &mut self.checksums;
+        self.entries.retain(|key, entry| {
+            if entry.deleted && is_expired(entry, now) {
+                checksums[bucket_of(key)] ^= entry_hash(key, entry);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Returns a checksum of the whole digest.
+    pub(crate) fn checksum(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        for c in self.checksums.iter() {
+            hash = fnv_extend(hash, &c.to_le_bytes());
+        }
+        hash
+    }
+
+    /// Returns the buckets whose checksum differ from the ones in `other`.
+    fn diff_buckets(&self, other: &[u64; BUCKETS]) -> Vec<usize> {
+        (0..BUCKETS)
+            .filter(|i| self.checksums[*i] != other[*i])
+            .collect()
+    }
+
+    /// Returns the entries in the given buckets.
+    fn buckets_entries(&self, buckets: &[usize]) -> HashMap<String, DigestEntry> {
+        self.entries
+            .iter()
+            .filter(|(key, _)| buckets.contains(&bucket_of(key)))
+            .map(|(key, entry)| (key.clone(), *entry))
+            .collect()
+    }
+
+    fn to_json(&self, path_expr: &PathExpr) -> String {
+        let buckets: Vec<serde_json::Value> = self
+            .checksums
+            .iter()
+            .map(|c| format!("{:016x}", c).into())
+            .collect();
+        serde_json::json!({
+            "path_expr": path_expr.as_str(),
+            "checksum": format!("{:016x}", self.checksum()),
+            "buckets": buckets,
+        })
+        .to_string()
+    }
+
+    fn bucket_to_json(&self, bucket: usize) -> String {
+        let entries: serde_json::Map<String, serde_json::Value> = self
+            .entries
+            .iter()
+            .filter(|(key, _)| bucket_of(key) == bucket)
+            .map(|(key, entry)| {
+                (
+                    key.clone(),
+                    serde_json::json!({ "ts": entry.ts.to_string(), "deleted": entry.deleted }),
+                )
+            })
+            .collect();
+        serde_json::Value::Object(entries).to_string()
+    }
+}
+
+/// A digest received from another storage.
+#[derive(Debug, PartialEq)]
+struct RemoteDigest {
+    path_expr: String,
+    checksum: u64,
+    buckets: [u64; BUCKETS],
+}
+
+impl FromStr for RemoteDigest {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let json: serde_json::Value = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        let parse_checksum = |v: &serde_json::Value| {
+            v.as_str()
+                .and_then(|s| u64::from_str_radix(s, 16).ok())
+                .ok_or_else(|| format!("invalid checksum: {}", v))
+        };
+        let path_expr = json["path_expr"]
+            .as_str()
+            .ok_or("missing path_expr")?
+            .to_string();
+        let checksum = parse_checksum(&json["checksum"])?;
+        let mut buckets = [0; BUCKETS];
+        match json["buckets"].as_array() {
+            Some(array) if array.len() == BUCKETS => {
+                for (i, v) in array.iter().enumerate() {
+                    buckets[i] = parse_checksum(v)?;
+                }
+            }
+            _ => return Err(format!("expecting {} buckets", BUCKETS)),
+        }
+        Ok(RemoteDigest {
+            path_expr,
+            checksum,
+            buckets,
+        })
+    }
+}
+
+fn parse_bucket(s: &str) -> Result<HashMap<String, DigestEntry>, String> {
+    let json: serde_json::Value = serde_json::from_str(s).map_err(|e| e.to_string())?;
+    let mut result = HashMap::new();
+    for (key, v) in json.as_object().ok_or("expecting a JSON object")? {
+        let ts = v["ts"]
+            .as_str()
+            .and_then(|s| Timestamp::from_str(s).ok())
+            .ok_or_else(|| format!("invalid timestamp for {}", key))?;
+        let deleted = v["deleted"].as_bool().unwrap_or(false);
+        result.insert(key.clone(), DigestEntry { ts, deleted });
+    }
+    Ok(result)
+}
+
+fn is_expired(entry: &DigestEntry, now: SystemTime) -> bool {
+    entry.ts.get_time().to_system_time() + REMOVAL_EXPIRATION < now
+}
+
+fn bucket_of(key: &str) -> usize {
+    (fnv_extend(FNV_OFFSET, key.as_bytes()) % BUCKETS as u64) as usize
+}
+
+fn entry_hash(key: &str, entry: &DigestEntry) -> u64 {
+    let hash = fnv_extend(FNV_OFFSET, key.as_bytes());
+    let hash = fnv_extend(hash, entry.ts.to_string().as_bytes());
+    fnv_extend(hash, &[entry.deleted as u8])
+}
+
+// FNV-1a hash: unlike std's DefaultHasher, it's guaranteed to be the same on all hosts
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+fn fnv_extend(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PeerStatus {
+    InSync,
+    Diverged,
+    Repairing,
+}
+
+impl PeerStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PeerStatus::InSync => "in_sync",
+            PeerStatus::Diverged => "diverged",
+            PeerStatus::Repairing => "repairing",
+        }
+    }
+}
+
+struct Peer {
+    status: PeerStatus,
+    last_seen: Instant,
+}
+
+enum RepairEvent {
+    Seed(Box<Sample>),
+    Sample(Box<Sample>),
+    Done(Path),
+}
+
+/// An event of the replication to be handled by the storage.
+pub(crate) enum ReplicationEvent {
+    /// A Sample repaired from another storage, to be stored
+    Repair(Sample),
+    /// A query from another storage (or from the replication itself when seeding the digest),
+    /// to be answered by the storage. Its resource name is already stripped of the replica prefix.
+    Query(Query),
+}
+
+/// The replication of a storage with the other storages having the same path expression.
+pub(crate) struct Replication<'a> {
+    zenoh: Arc<Zenoh>,
+    workspace: &'a Workspace<'a>,
+    path_expr: PathExpr,
+    digest_path: Path,
+    replica_path: Path,
+    period: Duration,
+    digest: Digest,
+    peers: HashMap<Path, Peer>,
+    repaired: u64,
+    ticker: Interval,
+    digests_sub: ChangeReceiver<'a>,
+    buckets_eval: GetRequestStream<'a>,
+    replica_queryable: Queryable<'a>,
+    repair_tx: Sender<RepairEvent>,
+    repair_rx: async_std::channel::Receiver<RepairEvent>,
+}
+
+impl<'a> Replication<'a> {
+    pub(crate) async fn start(
+        zenoh: Arc<Zenoh>,
+        workspace: &'a Workspace<'a>,
+        admin_path: &Path,
+        path_expr: PathExpr,
+        period: Duration,
+        digest: Digest,
+    ) -> ZResult<Replication<'a>> {
+        let digest_path = admin_path / DIGEST_SUFFIX;
+        debug!(
+            "Start replication for storage {} every {:?}",
+            admin_path, period
+        );
+        let digests_sub = workspace
+            .subscribe(&Selector::try_from(DIGESTS_SELECTOR)?)
+            .await?;
+        let buckets_eval = workspace
+            .register_eval(&PathExpr::try_from(format!("{}/*", digest_path))?)
+            .await?;
+        let replica_path = admin_path / REPLICA_SUFFIX;
+        let replica_queryable = workspace
+            .session()
+            .declare_queryable(&format!("{}/**", replica_path).into(), queryable::STORAGE)
+            .await?;
+        let (repair_tx, repair_rx) = async_std::channel::unbounded();
+        task::spawn(seed(
+            zenoh.clone(),
+            format!("{}{}", replica_path, path_expr),
+            repair_tx.clone(),
+        ));
+        Ok(Replication {
+            zenoh,
+            workspace,
+            path_expr,
+            digest_path,
+            replica_path,
+            period,
+            digest,
+            peers: HashMap::new(),
+            repaired: 0,
+            ticker: async_std::stream::interval(period),
+            digests_sub,
+            buckets_eval,
+            replica_queryable,
+            repair_tx,
+            repair_rx,
+        })
+    }
+
+    /// Records a Sample received by the storage in the digest.
+    /// Samples without timestamp are ignored, since they can't be ordered among replicas.
+    pub(crate) fn on_sample(&mut self, sample: &Sample) {
+        if let Some(info) = &sample.data_info {
+            if let Some(ts) = info.timestamp {
+                let deleted = info.kind == Some(data_kind::DELETE);
+                self.digest
+                    .update(&sample.res_name, DigestEntry { ts, deleted });
+            }
+        }
+    }
+
+    /// Stops the replication, returning the digest of the storage.
+    pub(crate) fn into_digest(self) -> Digest {
+        self.digest
+    }
+
+    /// Returns the replication status, to be added to the storage's admin status.
+    pub(crate) fn status(&self) -> serde_json::Value {
+        let peers: serde_json::Map<String, serde_json::Value> = self
+            .peers
+            .iter()
+            .map(|(path, peer)| (path.to_string(), peer.status.as_str().into()))
+            .collect();
+        serde_json::json!({
+            "period": self.period.as_secs_f64(),
+            "keys": self.digest.len(),
+            "checksum": format!("{:016x}", self.digest.checksum()),
+            "repaired": self.repaired,
+            "peers": peers,
+        })
+    }
+
+    /// Handles the replication events until a repaired Sample has to be stored in the storage,
+    /// or a query has to be answered by the storage.
+    pub(crate) async fn next_event(&mut self) -> ReplicationEvent {
+        loop {
+            select!(
+                _ = self.ticker.next().fuse() => self.publish_digest().await,
+                change = self.digests_sub.next().fuse() => {
+                    if let Some(change) = change {
+                        if let Some(Value::Json(s)) = change.value {
+                            let digest_path = change.path;
+                            match RemoteDigest::from_str(&s) {
+                                Ok(digest) => self.on_digest(digest_path, digest),
+                                Err(e) => warn!("Received invalid digest on {}: {}", digest_path, e),
+                            }
+                        }
+                    }
+                },
+                get = self.buckets_eval.next().fuse() => {
+                    if let Some(get) = get {
+                        self.reply_bucket(get).await;
+                    }
+                },
+                query = self.replica_queryable.receiver().next().fuse() => {
+                    if let Some(query) = query {
+                        if let Some(query) = self.strip_replica_prefix(query) {
+                            return ReplicationEvent::Query(query);
+                        }
+                    }
+                },
+                event = self.repair_rx.next().fuse() => match event {
+                    Some(RepairEvent::Seed(sample)) => self.on_sample(&sample),
+                    Some(RepairEvent::Sample(sample)) => {
+                        self.repaired += 1;
+                        return ReplicationEvent::Repair(*sample);
+                    }
+                    Some(RepairEvent::Done(peer)) => {
+                        if let Some(peer) = self.peers.get_mut(&peer) {
+                            peer.status = PeerStatus::Diverged;
+                        }
+                    }
+                    None => (),
+                }
+            );
+        }
+    }
+
+    async fn publish_digest(&mut self) {
+        self.digest.expire_removals(SystemTime::now());
+        let expiration = self.period * PEER_EXPIRATION_PERIODS;
+        self.peers
+            .retain(|_, peer| peer.last_seen.elapsed() < expiration);
+        trace!("Publish digest on {}", self.digest_path);
+        if let Err(e) = self
+            .workspace
+            .put(
+                &self.digest_path,
+                Value::Json(self.digest.to_json(&self.path_expr)),
+            )
+            .await
+        {
+            warn!("Failed to publish digest on {}: {}", self.digest_path, e);
+        }
+    }
+
+    fn on_digest(&mut self, digest_path: Path, digest: RemoteDigest) {
+        if digest_path == self.digest_path || digest.path_expr != self.path_expr.as_str() {
+            return;
+        }
+        let in_sync = digest.checksum == self.digest.checksum();
+        let peer = self.peers.entry(digest_path.clone()).or_insert(Peer {
+            status: PeerStatus::InSync,
+            last_seen: Instant::now(),
+        });
+        peer.last_seen = Instant::now();
+        if peer.status == PeerStatus::Repairing {
+            return;
+        }
+        if in_sync {
+            peer.status = PeerStatus::InSync;
+            return;
+        }
+        let buckets = self.digest.diff_buckets(&digest.buckets);
+        debug!(
+            "Storage {} diverges from {} on {} buckets: repairing",
+            self.digest_path,
+            digest_path,
+            buckets.len()
+        );
+        peer.status = PeerStatus::Repairing;
+        let local = self.digest.buckets_entries(&buckets);
+        task::spawn(repair(
+            self.zenoh.clone(),
+            digest_path,
+            buckets,
+            local,
+            self.repair_tx.clone(),
+        ));
+    }
+
+    // Returns the query with the key targeted within the storage as resource name,
+    // or None if the query doesn't target a key under the replica path.
+    fn strip_replica_prefix(&self, query: Query) -> Option<Query> {
+        match query
+            .res_name
+            .strip_prefix(self.replica_path.as_str())
+            .filter(|key| key.starts_with('/'))
+        {
+            Some(key) => Some(Query {
+                res_name: key.to_string(),
+                predicate: query.predicate,
+                replies_sender: query.replies_sender,
+            }),
+            None => {
+                debug!(
+                    "Ignore query on {}: not under {}",
+                    query.res_name, self.replica_path
+                );
+                None
+            }
+        }
+    }
+
+    async fn reply_bucket(&self, get: GetRequest) {
+        let path = get.selector.path_expr.as_str();
+        match path
+            .rsplit('/')
+            .next()
+            .and_then(|s| usize::from_str(s).ok())
+        {
+            Some(bucket) if bucket < BUCKETS => {
+                let reply_path = &self.digest_path / bucket.to_string();
+                get.reply_async(reply_path, Value::Json(self.digest.bucket_to_json(bucket)))
+                    .await;
+            }
+            _ => debug!("Ignore query on {}: not a valid digest bucket", path),
+        }
+    }
+}
+
+// Seeds the digest with the latest values already held by the storage, querying it on `selector`
+// (i.e. its replica path) and sending the replies via `tx`.
+async fn seed(zenoh: Arc<Zenoh>, selector: String, tx: Sender<RepairEvent>) {
+    let query_target = QueryTarget {
+        kind: queryable::STORAGE,
+        target: Target::All,
+    };
+    let replies = zenoh
+        .session()
+        .query(
+            &selector.as_str().into(),
+            "",
+            query_target,
+            QueryConsolidation::none(),
+        )
+        .await;
+    let mut replies = match replies {
+        Ok(replies) => replies,
+        Err(e) => {
+            warn!("Can't seed digest from {}: {}", selector, e);
+            return;
+        }
+    };
+    while let Some(reply) = replies.next().await {
+        match reply.data {
+            Ok(sample) => {
+                let _ = tx.send(RepairEvent::Seed(Box::new(sample))).await;
+            }
+            Err(e) => warn!("Can't seed digest from {}: {}", selector, e),
+        }
+    }
+}
+
+// Retrieves from the storage publishing its digest on `digest_path` the changes
+// more recent than the `local` ones in the given buckets, and sends them via `tx`.
+async fn repair(
+    zenoh: Arc<Zenoh>,
+    digest_path: Path,
+    buckets: Vec<usize>,
+    local: HashMap<String, DigestEntry>,
+    tx: Sender<RepairEvent>,
+) {
+    if let Err(e) = repair_buckets(&zenoh, &digest_path, &buckets, &local, &tx).await {
+        warn!("Repair from {} failed: {}", digest_path, e);
+    }
+    let _ = tx.send(RepairEvent::Done(digest_path)).await;
+}
+
+async fn repair_buckets(
+    zenoh: &Zenoh,
+    digest_path: &Path,
+    buckets: &[usize],
+    local: &HashMap<String, DigestEntry>,
+    tx: &Sender<RepairEvent>,
+) -> ZResult<()> {
+    let workspace = zenoh.workspace(None).await?;
+    // the peer's admin path is the parent of its digest path
+    let replica_path = format!(
+        "{}/{}",
+        digest_path
+            .as_str()
+            .strip_suffix(DIGEST_SUFFIX)
+            .unwrap_or_else(|| digest_path.as_str())
+            .trim_end_matches('/'),
+        REPLICA_SUFFIX
+    );
+    let now = SystemTime::now();
+    for bucket in buckets {
+        let selector = Selector::from(digest_path / bucket.to_string());
+        let mut replies = workspace.get(&selector).await?;
+        while let Some(data) = replies.next().await {
+            let data = match data {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("Can't get digest bucket: {}", e);
+                    continue;
+                }
+            };
+            let remote = match data.value {
+                Value::Json(s) => match parse_bucket(&s) {
+                    Ok(remote) => remote,
+                    Err(e) => {
+                        warn!("Received invalid digest bucket on {}: {}", data.path, e);
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+            for (key, entry) in remote {
+                if local.get(&key).map(|l| l.ts >= entry.ts).unwrap_or(false) {
+                    continue;
+                }
+                if entry.deleted {
+                    if !is_expired(&entry, now) {
+                        trace!("Repair removal of {} from {}", key, digest_path);
+                        let mut info = DataInfo::new();
+                        info.kind = Some(data_kind::DELETE);
+                        info.timestamp = Some(entry.ts);
+                        let sample = Sample {
+                            res_name: key,
+                            payload: ZBuf::new(),
+                            data_info: Some(info),
+                        };
+                        let _ = tx.send(RepairEvent::Sample(Box::new(sample))).await;
+                    }
+                } else {
+                    trace!("Repair {} from {}", key, digest_path);
+                    // query only the peer storage (not all the storages on key),
+                    // with starttime to get historical data (in case of time-series)
+                    let query_target = QueryTarget {
+                        kind: queryable::STORAGE,
+                        target: Target::All,
+                    };
+                    let mut replies = workspace
+                        .session()
+                        .query(
+                            &format!("{}{}", replica_path, key).into(),
+                            "?(starttime=0)",
+                            query_target,
+                            QueryConsolidation::none(),
+                        )
+                        .await?;
+                    while let Some(reply) = replies.next().await {
+                        match reply.data {
+                            Ok(sample) => {
+                                let _ = tx.send(RepairEvent::Sample(Box::new(sample))).await;
+                            }
+                            Err(e) => warn!("Can't repair {}: {}", key, e),
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh::TimestampId;
+
+    fn entry(secs: u64, deleted: bool) -> DigestEntry {
+        DigestEntry {
+            ts: Timestamp::new(
+                Duration::from_secs(secs).into(),
+                TimestampId::new(1, [0u8; TimestampId::MAX_SIZE]),
+            ),
+            deleted,
+        }
+    }
+
+    #[test]
+    fn digest_checksums() {
+        let mut d1 = Digest::new();
+        let mut d2 = Digest::new();
+        d1.update("/a/1", entry(1, false));
+        d1.update("/a/2", entry(1, false));
+        d1.update("/a/1", entry(2, false));
+        // insertion order doesn't matter
+        d2.update("/a/2", entry(1, false));
+        d2.update("/a/1", entry(2, false));
+        // older changes are ignored
+        d2.update("/a/1", entry(1, false));
+        assert_eq!(d1.checksum(), d2.checksum());
+        assert!(d1.diff_buckets(&d2.checksums).is_empty());
+
+        d2.update("/a/2", entry(3, true));
+        assert_ne!(d1.checksum(), d2.checksum());
+        let diff = d1.diff_buckets(&d2.checksums);
+        assert_eq!(diff, vec![bucket_of("/a/2")]);
+        assert_eq!(d2.buckets_entries(&diff).get("/a/2"), Some(&entry(3, true)));
+
+        // once expired, the removal is forgotten
+        d2.expire_removals(SystemTime::UNIX_EPOCH + Duration::from_secs(3600));
+        assert_eq!(d2.len(), 1);
+    }
+
+    #[test]
+    fn digest_json() {
+        let mut digest = Digest::new();
+        digest.update("/a/1", entry(1, false));
+        digest.update("/a/2", entry(2, true));
+        let path_expr = PathExpr::try_from("/a/**").unwrap();
+        let remote = RemoteDigest::from_str(&digest.to_json(&path_expr)).unwrap();
+        assert_eq!(remote.path_expr, "/a/**");
+        assert_eq!(remote.checksum, digest.checksum());
+        assert_eq!(remote.buckets, digest.checksums);
+
+        let bucket = bucket_of("/a/2");
+        let entries = parse_bucket(&digest.bucket_to_json(bucket)).unwrap();
+        assert_eq!(entries.get("/a/2"), Some(&entry(2, true)));
+
+        assert!(RemoteDigest::from_str(r#"{"path_expr":"/a/**","checksum":"0"}"#).is_err());
+    }
+
+    // Queries the content of a storage on its replica path, returning the stored keys
+    async fn replica_keys(zenoh: &Zenoh, admin_path: &Path) -> Vec<String> {
+        let query_target = QueryTarget {
+            kind: queryable::STORAGE,
+            target: Target::All,
+        };
+        let mut replies = zenoh
+            .session()
+            .query(
+                &format!("{}/{}/repair/**", admin_path, REPLICA_SUFFIX).into(),
+                "",
+                query_target,
+                QueryConsolidation::none(),
+            )
+            .await
+            .unwrap();
+        let mut keys = vec![];
+        while let Some(reply) = replies.next().await {
+            if let Ok(sample) = reply.data {
+                keys.push(sample.res_name);
+            }
+        }
+        keys.sort();
+        keys
+    }
+
+    async fn wait_keys(zenoh: &Zenoh, admin_path: &Path, expected: &[&str]) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while replica_keys(zenoh, admin_path).await != expected {
+            assert!(
+                Instant::now() < deadline,
+                "storage {} doesn't hold {:?}",
+                admin_path,
+                expected
+            );
+            task::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    #[test]
+    fn repair_between_storages() {
+        use crate::memory_backend::create_backend;
+        use crate::storages_mgt::start_storage;
+        use zenoh::{utils, Properties};
+        use zenoh_util::properties::config::*;
+
+        task::block_on(async {
+            let mut config = zenoh::net::config::peer();
+            config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+            let zenoh = Arc::new(Zenoh::new(config).await.unwrap());
+            let props = Properties::from("path_expr=/repair/**;replication_period=0.1");
+            let mut backend = create_backend(Properties::default()).unwrap();
+            let admin_a =
+                Path::try_from("/@/router/test/plugin/storages/backend/memory/storage/a").unwrap();
+            let admin_b =
+                Path::try_from("/@/router/test/plugin/storages/backend/memory/storage/b").unwrap();
+
+            // the storage b starts empty
+            let storage_b = backend.create_storage(props.clone()).await.unwrap();
+            let _b = start_storage(
+                storage_b,
+                admin_b.clone(),
+                props.clone(),
+                None,
+                None,
+                zenoh.clone(),
+            )
+            .await
+            .unwrap();
+            task::sleep(Duration::from_millis(200)).await;
+
+            // the storage a starts with some content (e.g. restored from disk) that was never published:
+            // b can only get it through the repair, once a's digest has been seeded with it
+            let mut storage_a = backend.create_storage(props.clone()).await.unwrap();
+            for key in ["/repair/1", "/repair/2"].iter() {
+                let mut info = DataInfo::new();
+                info.timestamp = Some(utils::new_reception_timestamp());
+                storage_a
+                    .on_sample(Sample {
+                        res_name: key.to_string(),
+                        payload: ZBuf::from(&b"value"[..]),
+                        data_info: Some(info),
+                    })
+                    .await
+                    .unwrap();
+            }
+            let _a = start_storage(storage_a, admin_a.clone(), props, None, None, zenoh.clone())
+                .await
+                .unwrap();
+            // the storages are started asynchronously: wait for a to reply,
+            // then for b to be repaired from a
+            wait_keys(&zenoh, &admin_a, &["/repair/1", "/repair/2"]).await;
+            wait_keys(&zenoh, &admin_b, &["/repair/1", "/repair/2"]).await;
+        })
+    }
+}
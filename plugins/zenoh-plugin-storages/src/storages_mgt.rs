@@ -11,6 +11,7 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use super::replication::{Digest, Replication, ReplicationEvent};
 use async_std::channel::{bounded, Sender};
 use async_std::sync::{Arc, RwLock};
 use async_std::task;
@@ -18,81 +19,148 @@ use futures::select;
 use futures::stream::StreamExt;
 use futures::FutureExt;
 use log::{debug, error, trace, warn};
+use std::convert::TryFrom;
+use std::time::Duration;
 use zenoh::net::{
-    queryable, QueryConsolidation, QueryTarget, Reliability, SubInfo, SubMode, Target,
+    queryable, QueryConsolidation, QueryTarget, Queryable, Reliability, ReplyError, Sample,
+    SubInfo, SubMode, Subscriber, Target,
 };
-use zenoh::{Path, PathExpr, ZResult, Zenoh};
-use zenoh_backend_traits::{IncomingDataInterceptor, OutgoingDataInterceptor, Query};
+use zenoh::{
+    utils, Path, PathExpr, Properties, Timestamp, Value, Workspace, ZError, ZErrorKind, ZResult,
+    Zenoh,
+};
+use zenoh_backend_traits::{
+    IncomingDataInterceptor, OutgoingDataInterceptor, Query, Storage, PROP_STORAGE_PATH_EXPR,
+    PROP_STORAGE_REPLICATION_PERIOD,
+};
+use zenoh_util::{zerror, zerror2};
+
+/// The configuration of a storage that is handled by the storages plugin, whatever the backend.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct StorageConfig {
+    pub(crate) path_expr: PathExpr,
+    pub(crate) replication_period: Option<Duration>,
+}
+
+impl StorageConfig {
+    pub(crate) fn new(admin_path: &Path, props: &Properties) -> ZResult<StorageConfig> {
+        let path_expr_str = props.get(PROP_STORAGE_PATH_EXPR).ok_or_else(|| {
+            zerror2!(ZErrorKind::Other {
+                descr: format!(
+                    "Can't configure storage {}: no {} property",
+                    admin_path, PROP_STORAGE_PATH_EXPR
+                )
+            })
+        })?;
+        let path_expr = PathExpr::try_from(path_expr_str.as_str())?;
+        let replication_period = match props.get(PROP_STORAGE_REPLICATION_PERIOD) {
+            Some(s) => match s
+                .parse::<f64>()
+                .ok()
+                .filter(|secs| *secs > 0.0)
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            {
+                Some(period) => Some(period),
+                None => {
+                    return zerror!(ZErrorKind::Other {
+                        descr: format!(
+                            "Can't configure storage {}: invalid {} property: {} (expected a positive number of seconds)",
+                            admin_path, PROP_STORAGE_REPLICATION_PERIOD, s
+                        )
+                    })
+                }
+            },
+            None => None,
+        };
+        Ok(StorageConfig {
+            path_expr,
+            replication_period,
+        })
+    }
+}
+
+// Returns the properties that are not handled by the storages plugin (i.e. only by the storage itself)
+fn storage_specific_props(props: &Properties) -> Properties {
+    let mut props = props.clone();
+    props.remove(PROP_STORAGE_PATH_EXPR);
+    props.remove(PROP_STORAGE_REPLICATION_PERIOD);
+    props
+}
+
+/// A message sent to a running storage via its handle (i.e. the Sender returned by [`start_storage()`]).
+/// Dropping the handle stops the storage.
+pub(crate) enum StorageMessage {
+    /// Apply new properties to the storage, keeping its data
+    Reconfigure(Properties),
+}
+
+// Metrics on the samples and queries received by a storage, reported in its admin status
+#[derive(Default)]
+struct StorageMetrics {
+    samples: u64,
+    queries: u64,
+    last_sample: Option<Timestamp>,
+}
 
 pub(crate) async fn start_storage(
-    mut storage: Box<dyn zenoh_backend_traits::Storage>,
+    mut storage: Box<dyn Storage>,
     admin_path: Path,
-    path_expr: PathExpr,
+    props: Properties,
     in_interceptor: Option<Arc<RwLock<Box<dyn IncomingDataInterceptor>>>>,
     out_interceptor: Option<Arc<RwLock<Box<dyn OutgoingDataInterceptor>>>>,
     zenoh: Arc<Zenoh>,
-) -> ZResult<Sender<bool>> {
-    debug!("Start storage {} on {}", admin_path, path_expr);
+) -> ZResult<Sender<StorageMessage>> {
+    let mut config = StorageConfig::new(&admin_path, &props)?;
+    debug!("Start storage {} on {}", admin_path, config.path_expr);
 
-    let (tx, rx) = bounded::<bool>(1);
+    let (tx, rx) = bounded::<StorageMessage>(1);
     task::spawn(async move {
+        let mut props = props;
+        let mut metrics = StorageMetrics::default();
         let workspace = zenoh.workspace(Some(admin_path.clone())).await.unwrap();
 
-        // subscribe on path_expr
-        let sub_info = SubInfo {
-            reliability: Reliability::Reliable,
-            mode: SubMode::Push,
-            period: None,
-        };
-        let mut storage_sub = match workspace
-            .session()
-            .declare_subscriber(&path_expr.to_string().into(), &sub_info)
-            .await
+        // start the replication with the other storages on path_expr (if configured)
+        let mut replication = match start_replication(
+            &zenoh,
+            &workspace,
+            &admin_path,
+            &config,
+            Digest::new(),
+        )
+        .await
         {
-            Ok(storage_sub) => storage_sub,
+            Ok(replication) => replication,
             Err(e) => {
-                error!("Error starting storage {} : {}", admin_path, e);
+                error!(
+                    "Error starting replication for storage {} : {}",
+                    admin_path, e
+                );
                 return;
             }
         };
 
-        // align with other storages, querying them on path_expr,
-        // with starttime to get historical data (in case of time-series)
-        let query_target = QueryTarget {
-            kind: queryable::STORAGE,
-            target: Target::All,
-        };
-        let mut replies = match workspace
-            .session()
-            .query(
-                &path_expr.to_string().into(),
-                "?(starttime=0)",
-                query_target,
-                QueryConsolidation::none(),
-            )
-            .await
-        {
-            Ok(replies) => replies,
+        // subscribe on path_expr
+        let mut storage_sub = match subscribe(&workspace, &config.path_expr).await {
+            Ok(storage_sub) => storage_sub,
             Err(e) => {
-                error!("Error aligning storage {} : {}", admin_path, e);
+                error!("Error starting storage {} : {}", admin_path, e);
                 return;
             }
         };
-        while let Some(reply) = replies.next().await {
-            log::trace!("Storage {} aligns data {}", admin_path, reply.data.res_name);
-            // Call incoming data interceptor (if any)
-            let sample = if let Some(ref interceptor) = in_interceptor {
-                interceptor.read().await.on_sample(reply.data).await
-            } else {
-                reply.data
-            };
-            // Call storage
-            if let Err(e) = storage.on_sample(sample).await {
-                warn!(
-                    "Storage {} raised an error aligning a sample: {}",
-                    admin_path, e
-                );
-            }
+
+        // align with other storages, querying them on path_expr
+        if let Err(e) = align(
+            &workspace,
+            &config.path_expr,
+            &mut storage,
+            &in_interceptor,
+            &mut replication,
+            &mut metrics,
+        )
+        .await
+        {
+            error!("Error aligning storage {} : {}", admin_path, e);
+            return;
         }
 
         // admin_path is "/@/.../storage/<stid>"
@@ -106,12 +174,8 @@ pub(crate) async fn start_storage(
         };
 
         // answer to queries on path_expr
-        let mut storage_queryable = match workspace
-            .session()
-            .declare_queryable(&path_expr.to_string().into(), queryable::STORAGE)
-            .await
-        {
-            Ok(storage_queryable) => storage_queryable,
+        let mut storage_queryable = match declare_queryable(&workspace, &config.path_expr).await {
+            Ok(storage_queryable) => Some(storage_queryable),
             Err(e) => {
                 error!("Error starting storage {} : {}", admin_path, e);
                 return;
@@ -123,35 +187,112 @@ pub(crate) async fn start_storage(
                 // on get request on storage_admin
                 get = storage_admin.next().fuse() => {
                     let get = get.unwrap();
-                    get.reply_async(admin_path.clone(), storage.get_admin_status().await).await;
+                    let status = get_admin_status(storage.as_ref(), &props, &metrics, &replication).await;
+                    get.reply_async(admin_path.clone(), status).await;
                 },
                 // on sample for path_expr
                 sample = storage_sub.receiver().next().fuse() => {
-                    // Call incoming data interceptor (if any)
-                    let sample = if let Some(ref interceptor) = in_interceptor {
-                        interceptor.read().await.on_sample(sample.unwrap()).await
-                    } else {
-                        sample.unwrap()
-                    };
-                    // Call storage
-                    if let Err(e) = storage.on_sample(sample).await {
+                    if let Err(e) = store(&mut storage, &in_interceptor, &mut replication, &mut metrics, sample.unwrap()).await {
                         warn!("Storage {} raised an error receiving a sample: {}", admin_path, e);
                     }
                 },
+                // on replication event
+                event = next_replication_event(&mut replication).fuse() => match event {
+                    // on sample repaired from another storage
+                    ReplicationEvent::Repair(sample) => {
+                        trace!("Storage {} repairs data {}", admin_path, sample.res_name);
+                        if let Err(e) = store(&mut storage, &in_interceptor, &mut replication, &mut metrics, sample).await {
+                            warn!("Storage {} raised an error repairing a sample: {}", admin_path, e);
+                        }
+                    }
+                    // on query on the storage's replica path
+                    ReplicationEvent::Query(q) => {
+                        if let Err(e) = answer(&mut storage, &out_interceptor, q).await {
+                            warn!("Storage {} raised an error receiving a replica query: {}", admin_path, e);
+                        }
+                    }
+                },
                 // on query on path_expr
-                query = storage_queryable.receiver().next().fuse() => {
-                    let q = query.unwrap();
-                    // wrap zenoh::net::Query in zenoh_backend_traits::Query
-                    // with outgoing interceptor
-                    let query = Query::new(q, out_interceptor.clone());
-                    if let Err(e) = storage.on_query(query).await {
+                query = next_query(&mut storage_queryable).fuse() => {
+                    metrics.queries += 1;
+                    if let Err(e) = answer(&mut storage, &out_interceptor, query.unwrap()).await {
                         warn!("Storage {} raised an error receiving a query: {}", admin_path, e);
                     }
                 },
-                // on storage handle drop
-                _ = rx.recv().fuse() => {
-                    trace!("Dropping storage {}", admin_path);
-                    return
+                // on message via the storage handle
+                msg = rx.recv().fuse() => match msg {
+                    Ok(StorageMessage::Reconfigure(new_props)) => {
+                        debug!("Reconfigure storage {} with {}", admin_path, new_props);
+                        let new_config = match StorageConfig::new(&admin_path, &new_props) {
+                            Ok(new_config) => new_config,
+                            Err(e) => {
+                                warn!("{}", e);
+                                continue;
+                            }
+                        };
+                        // the changes of other properties are delegated to the storage
+                        if storage_specific_props(&new_props) != storage_specific_props(&props) {
+                            if let Err(e) = storage.reconfigure(new_props.clone()).await {
+                                warn!("Can't reconfigure storage {}: {}", admin_path, e);
+                                continue;
+                            }
+                        }
+                        let path_expr_changed = new_config.path_expr != config.path_expr;
+                        if path_expr_changed {
+                            match subscribe(&workspace, &new_config.path_expr).await {
+                                // replacing the subscriber undeclares the previous one
+                                Ok(sub) => storage_sub = sub,
+                                Err(e) => {
+                                    warn!("Can't change path expression of storage {}: {}", admin_path, e);
+                                    continue;
+                                }
+                            }
+                            // as at startup, the queryable is undeclared until the storage is aligned,
+                            // since the storage can't reply to its own alignment query
+                            if let Some(queryable) = storage_queryable.take() {
+                                if let Err(e) = queryable.undeclare().await {
+                                    warn!("Error undeclaring queryable of storage {} : {}", admin_path, e);
+                                }
+                            }
+                        }
+                        if new_config != config {
+                            // restart the replication, keeping the digest of the data already stored
+                            // (the samples stored while the replication was disabled are seeded at restart)
+                            let mut digest = replication.take().map_or_else(Digest::new, Replication::into_digest);
+                            digest.retain_matching(&new_config.path_expr);
+                            match start_replication(&zenoh, &workspace, &admin_path, &new_config, digest).await {
+                                Ok(r) => replication = r,
+                                Err(e) => warn!("Error restarting replication for storage {} : {}", admin_path, e),
+                            }
+                        }
+                        if path_expr_changed {
+                            if let Err(e) = align(&workspace, &new_config.path_expr, &mut storage, &in_interceptor, &mut replication, &mut metrics).await {
+                                warn!("Error aligning storage {} : {}", admin_path, e);
+                            }
+                            match declare_queryable(&workspace, &new_config.path_expr).await {
+                                Ok(queryable) => storage_queryable = Some(queryable),
+                                Err(e) => error!("Error declaring queryable of storage {} : {}", admin_path, e),
+                            }
+                        }
+                        props = new_props;
+                        config = new_config;
+                    }
+                    // on storage handle drop
+                    Err(_) => {
+                        trace!("Dropping storage {}", admin_path);
+                        if let Err(e) = storage_sub.undeclare().await {
+                            warn!("Error undeclaring subscriber of storage {} : {}", admin_path, e);
+                        }
+                        if let Some(queryable) = storage_queryable {
+                            if let Err(e) = queryable.undeclare().await {
+                                warn!("Error undeclaring queryable of storage {} : {}", admin_path, e);
+                            }
+                        }
+                        if let Err(e) = storage_admin.close().await {
+                            warn!("Error closing admin of storage {} : {}", admin_path, e);
+                        }
+                        return
+                    }
                 }
             );
         }
@@ -159,3 +300,217 @@ pub(crate) async fn start_storage(
 
     Ok(tx)
 }
+
+async fn start_replication<'a>(
+    zenoh: &Arc<Zenoh>,
+    workspace: &'a Workspace<'a>,
+    admin_path: &Path,
+    config: &StorageConfig,
+    digest: Digest,
+) -> ZResult<Option<Replication<'a>>> {
+    match config.replication_period {
+        Some(period) => Ok(Some(
+            Replication::start(
+                zenoh.clone(),
+                workspace,
+                admin_path,
+                config.path_expr.clone(),
+                period,
+                digest,
+            )
+            .await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+async fn subscribe<'a>(
+    workspace: &'a Workspace<'a>,
+    path_expr: &PathExpr,
+) -> ZResult<Subscriber<'a>> {
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable,
+        mode: SubMode::Push,
+        period: None,
+    };
+    workspace
+        .session()
+        .declare_subscriber(&path_expr.to_string().into(), &sub_info)
+        .await
+}
+
+async fn declare_queryable<'a>(
+    workspace: &'a Workspace<'a>,
+    path_expr: &PathExpr,
+) -> ZResult<Queryable<'a>> {
+    workspace
+        .session()
+        .declare_queryable(&path_expr.to_string().into(), queryable::STORAGE)
+        .await
+}
+
+// Aligns the storage with the other storages, querying them on path_expr,
+// with starttime to get historical data (in case of time-series)
+async fn align(
+    workspace: &Workspace<'_>,
+    path_expr: &PathExpr,
+    storage: &mut Box<dyn Storage>,
+    in_interceptor: &Option<Arc<RwLock<Box<dyn IncomingDataInterceptor>>>>,
+    replication: &mut Option<Replication<'_>>,
+    metrics: &mut StorageMetrics,
+) -> ZResult<()> {
+    let query_target = QueryTarget {
+        kind: queryable::STORAGE,
+        target: Target::All,
+    };
+    let mut replies = workspace
+        .session()
+        .query(
+            &path_expr.to_string().into(),
+            "?(starttime=0)",
+            query_target,
+            QueryConsolidation::none(),
+        )
+        .await?;
+    while let Some(reply) = replies.next().await {
+        let sample = match reply.data {
+            Ok(sample) => sample,
+            Err(e) => {
+                warn!("Storage can't align data: {}", e);
+                continue;
+            }
+        };
+        trace!("Storage aligns data {}", sample.res_name);
+        if let Err(e) = store(storage, in_interceptor, replication, metrics, sample).await {
+            warn!("Storage raised an error aligning a sample: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// Stores a sample in the storage, after calling the incoming data interceptor (if any)
+async fn store(
+    storage: &mut Box<dyn Storage>,
+    in_interceptor: &Option<Arc<RwLock<Box<dyn IncomingDataInterceptor>>>>,
+    replication: &mut Option<Replication<'_>>,
+    metrics: &mut StorageMetrics,
+    sample: Sample,
+) -> ZResult<()> {
+    // Call incoming data interceptor (if any)
+    let sample = if let Some(ref interceptor) = in_interceptor {
+        interceptor.read().await.on_sample(sample).await
+    } else {
+        sample
+    };
+    if let Some(replication) = replication.as_mut() {
+        replication.on_sample(&sample);
+    }
+    metrics.samples += 1;
+    metrics.last_sample = Some(
+        sample
+            .data_info
+            .as_ref()
+            .and_then(|info| info.timestamp)
+            .unwrap_or_else(utils::new_reception_timestamp),
+    );
+    // Call storage
+    storage.on_sample(sample).await
+}
+
+// Calls the storage with a query, after wrapping it with the outgoing data interceptor (if any)
+async fn answer(
+    storage: &mut Box<dyn Storage>,
+    out_interceptor: &Option<Arc<RwLock<Box<dyn OutgoingDataInterceptor>>>>,
+    q: zenoh::net::Query,
+) -> ZResult<()> {
+    let res_name = q.res_name.clone();
+    let replies_sender = q.replies_sender.clone();
+    // wrap zenoh::net::Query in zenoh_backend_traits::Query
+    // with outgoing interceptor
+    let query = Query::new(q, out_interceptor.clone());
+    let result = storage.on_query(query).await;
+    if let Err(e) = &result {
+        // let the querier know why this storage didn't reply
+        replies_sender.send_error(ReplyError {
+            res_name,
+            payload: e.to_string().into_bytes().into(),
+            data_info: None,
+        });
+    }
+    result
+}
+
+async fn next_query(queryable: &mut Option<Queryable<'_>>) -> Option<zenoh::net::Query> {
+    match queryable {
+        Some(queryable) => queryable.receiver().next().await,
+        None => futures::future::pending().await,
+    }
+}
+
+async fn next_replication_event(replication: &mut Option<Replication<'_>>) -> ReplicationEvent {
+    match replication {
+        Some(replication) => replication.next_event().await,
+        None => futures::future::pending().await,
+    }
+}
+
+// Returns the storage's admin status, completed with its current properties, some statistics
+// and the replication status. This is possible only if the storage's admin status is a JSON object,
+// otherwise it's returned as such.
+async fn get_admin_status(
+    storage: &dyn Storage,
+    props: &Properties,
+    metrics: &StorageMetrics,
+    replication: &Option<Replication<'_>>,
+) -> Value {
+    let status = storage.get_admin_status().await;
+    let mut json = match &status {
+        Value::Json(s) => match serde_json::from_str::<serde_json::Value>(s) {
+            Ok(serde_json::Value::Object(json)) => json,
+            _ => return status,
+        },
+        _ => return status,
+    };
+    for (key, value) in props.iter() {
+        json.insert(key.clone(), value.clone().into());
+    }
+    let mut stats = serde_json::json!({
+        "samples": metrics.samples,
+        "queries": metrics.queries,
+        "last_sample": metrics.last_sample.map(|ts| ts.get_time().to_string()),
+    });
+    if let Some(storage_stats) = storage.get_stats().await {
+        stats["keys"] = storage_stats.keys.into();
+        stats["bytes"] = storage_stats.bytes.into();
+    }
+    json.insert("stats".into(), stats);
+    if let Some(replication) = replication {
+        json.insert("replication".into(), replication.status());
+    }
+    Value::Json(serde_json::Value::Object(json).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_config() {
+        let admin_path =
+            Path::try_from("/@/router/test/plugin/storages/backend/memory/storage/s").unwrap();
+        let config = StorageConfig::new(
+            &admin_path,
+            &Properties::from("path_expr=/demo/**;replication_period=0.5"),
+        )
+        .unwrap();
+        assert_eq!(config.replication_period, Some(Duration::from_millis(500)));
+        for period in ["0", "-1", "x", "inf", "NaN", "1e20"].iter() {
+            let props =
+                Properties::from(format!("path_expr=/demo/**;replication_period={}", period));
+            assert!(StorageConfig::new(&admin_path, &props).is_err());
+        }
+        assert!(
+            StorageConfig::new(&admin_path, &Properties::from("replication_period=1")).is_err()
+        );
+    }
+}
@@ -296,10 +296,15 @@ async fn net_event_handler(z: Arc<Session>, state: Arc<GroupState>) {
                                     .await
                                     .unwrap();
 
-                                while let Some(sample) = receiver.next().await {
-                                    match bincode::deserialize::<Member>(
-                                        &sample.data.payload.to_vec(),
-                                    ) {
+                                while let Some(reply) = receiver.next().await {
+                                    let sample = match reply.data {
+                                        Ok(sample) => sample,
+                                        Err(e) => {
+                                            log::debug!("Error replied to member query: {}", e);
+                                            continue;
+                                        }
+                                    };
+                                    match bincode::deserialize::<Member>(&sample.payload.to_vec()) {
                                         Ok(m) => {
                                             let mut expiry = Instant::now();
                                             expiry = expiry.add(m.lease);
@@ -278,11 +278,14 @@ impl Stream for InnerState {
             while i < mself.replies_recv_queue.len() {
                 loop {
                     match mself.replies_recv_queue[i].poll_next(cx) {
-                        Poll::Ready(Some(mut reply)) => {
-                            log::trace!("Reply received: {}", reply.data.res_name);
-                            reply.data.ensure_timestamp();
-                            mself.merge_queue.push(reply.data);
-                        }
+                        Poll::Ready(Some(reply)) => match reply.data {
+                            Ok(mut sample) => {
+                                log::trace!("Reply received: {}", sample.res_name);
+                                sample.ensure_timestamp();
+                                mself.merge_queue.push(sample);
+                            }
+                            Err(e) => log::warn!("Error replied to query: {}", e),
+                        },
                         Poll::Ready(None) => {
                             // query completed - remove the receiver and break loop
                             mself.replies_recv_queue.remove(i);
@@ -345,10 +348,15 @@ impl InnerState {
         if !self.replies_recv_queue.is_empty() {
             // get all replies and add them to merge_queue
             for recv in self.replies_recv_queue.drain(..) {
-                while let Ok(mut reply) = recv.recv() {
-                    log::trace!("Reply received: {}", reply.data.res_name);
-                    reply.data.ensure_timestamp();
-                    self.merge_queue.push(reply.data);
+                while let Ok(reply) = recv.recv() {
+                    match reply.data {
+                        Ok(mut sample) => {
+                            log::trace!("Reply received: {}", sample.res_name);
+                            sample.ensure_timestamp();
+                            self.merge_queue.push(sample);
+                        }
+                        Err(e) => log::warn!("Error replied to query: {}", e),
+                    }
                 }
             }
             log::debug!(
@@ -397,11 +405,14 @@ impl InnerState {
             while i < self.replies_recv_queue.len() {
                 loop {
                     match self.replies_recv_queue[i].try_recv() {
-                        Ok(mut reply) => {
-                            log::trace!("Reply received: {}", reply.data.res_name);
-                            reply.data.ensure_timestamp();
-                            self.merge_queue.push(reply.data);
-                        }
+                        Ok(reply) => match reply.data {
+                            Ok(mut sample) => {
+                                log::trace!("Reply received: {}", sample.res_name);
+                                sample.ensure_timestamp();
+                                self.merge_queue.push(sample);
+                            }
+                            Err(e) => log::warn!("Error replied to query: {}", e),
+                        },
                         Err(TryRecvError::Disconnected) => {
                             // query completed - remove the receiver and break loop
                             self.replies_recv_queue.remove(i);
@@ -468,11 +479,14 @@ impl InnerState {
             while i < self.replies_recv_queue.len() {
                 loop {
                     match self.replies_recv_queue[i].recv_deadline(deadline) {
-                        Ok(mut reply) => {
-                            log::trace!("Reply received: {}", reply.data.res_name);
-                            reply.data.ensure_timestamp();
-                            self.merge_queue.push(reply.data);
-                        }
+                        Ok(reply) => match reply.data {
+                            Ok(mut sample) => {
+                                log::trace!("Reply received: {}", sample.res_name);
+                                sample.ensure_timestamp();
+                                self.merge_queue.push(sample);
+                            }
+                            Err(e) => log::warn!("Error replied to query: {}", e),
+                        },
                         Err(RecvTimeoutError::Disconnected) => {
                             // query completed - remove the receiver and break loop
                             self.replies_recv_queue.remove(i);
@@ -56,6 +56,10 @@ pub enum ZErrorKind {
     Other {
         descr: String,
     },
+    ReplyError {
+        path: String,
+        descr: String,
+    },
     Timeout {},
     UnkownResourceId {
         rid: String,
@@ -105,6 +109,9 @@ impl fmt::Display for ZErrorKind {
             }
             ZErrorKind::IoError { descr } => write!(f, "IO error ({})", descr),
             ZErrorKind::Other { descr } => write!(f, "zenoh error: ({})", descr),
+            ZErrorKind::ReplyError { path, descr } => {
+                write!(f, "Error replied for {} ({})", path, descr)
+            }
             ZErrorKind::Timeout {} => write!(f, "Timeout"),
             ZErrorKind::UnkownResourceId { rid } => write!(f, "Unkown ResourceId ({})", rid),
             ZErrorKind::ValueEncodingFailed { descr } => {
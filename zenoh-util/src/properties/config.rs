@@ -38,6 +38,7 @@ mod consts {
     pub const ZN_PEER_STR: &str = "peer";
 
     /// A locator to listen on.
+    /// Multicast locators (ex: `"udp/224.0.0.224:7447"`) join the corresponding multicast group.
     /// String key : `"listener"`.
     /// Accepted values : `<locator>` (ex: `"tcp/10.10.10.10:7447"`).
     /// Default value : None.
@@ -307,6 +308,38 @@ mod consts {
     pub const ZN_MULTICAST_IPV6_ADDRESS_KEY: u64 = 0x77;
     pub const ZN_MULTICAST_IPV6_ADDRESS_STR: &str = "multicast_ipv6_address";
     pub const ZN_MULTICAST_IPV6_ADDRESS_DEFAULT: &str = "[ff24::224]:7447";
+
+    /// Configures the default timeout of the queries expressed in milliseconds:
+    /// the timeout of the queries issued by a session without an explicit timeout,
+    /// and the delay after which a router stops waiting for the replies of a queryable.
+    /// As this delay is not transmitted with the queries, the effective timeout of a routed
+    /// query is the minimum of its own timeout and of the routers' default timeout; a query
+    /// finalized by a router after this delay is reported as partial to its issuer.
+    /// String key : `"queries_default_timeout"`.
+    /// Accepted values : `<unsigned integer>`.
+    /// Default value : `10000` (10 seconds).
+    pub const ZN_QUERIES_DEFAULT_TIMEOUT_KEY: u64 = 0x78;
+    pub const ZN_QUERIES_DEFAULT_TIMEOUT_STR: &str = "queries_default_timeout";
+    pub const ZN_QUERIES_DEFAULT_TIMEOUT_DEFAULT: &str = "10000";
+
+    /// Configures the compression of the batches sent on the links.
+    /// Compression is used on a unicast transport only if both peers enable it,
+    /// while all the members of a multicast group are expected to share the same configuration.
+    /// String key : `"compression"`.
+    /// Accepted values : `"true"`, `"false"`.
+    /// Default value : `"false"`.
+    pub const ZN_COMPRESSION_KEY: u64 = 0x79;
+    pub const ZN_COMPRESSION_STR: &str = "compression";
+    pub const ZN_COMPRESSION_DEFAULT: &str = ZN_FALSE;
+
+    /// Configures the size in bytes below which the batches are sent uncompressed
+    /// when compression is enabled.
+    /// String key : `"compression_threshold"`.
+    /// Accepted values : `<unsigned integer>`.
+    /// Default value : `128`.
+    pub const ZN_COMPRESSION_THRESHOLD_KEY: u64 = 0x7A;
+    pub const ZN_COMPRESSION_THRESHOLD_STR: &str = "compression_threshold";
+    pub const ZN_COMPRESSION_THRESHOLD_DEFAULT: &str = "128";
 }
 
 pub use consts::*;
@@ -356,6 +389,9 @@ impl KeyTranscoder for ConfigTranscoder {
             ZN_DEFRAG_BUFF_SIZE_STR => Some(ZN_DEFRAG_BUFF_SIZE_KEY),
             ZN_LINK_RX_BUFF_SIZE_STR => Some(ZN_LINK_RX_BUFF_SIZE_KEY),
             ZN_MULTICAST_IPV6_ADDRESS_STR => Some(ZN_MULTICAST_IPV6_ADDRESS_KEY),
+            ZN_QUERIES_DEFAULT_TIMEOUT_STR => Some(ZN_QUERIES_DEFAULT_TIMEOUT_KEY),
+            ZN_COMPRESSION_STR => Some(ZN_COMPRESSION_KEY),
+            ZN_COMPRESSION_THRESHOLD_STR => Some(ZN_COMPRESSION_THRESHOLD_KEY),
             _ => None,
         }
     }
@@ -403,6 +439,9 @@ impl KeyTranscoder for ConfigTranscoder {
             ZN_DEFRAG_BUFF_SIZE_KEY => Some(ZN_DEFRAG_BUFF_SIZE_STR.to_string()),
             ZN_LINK_RX_BUFF_SIZE_KEY => Some(ZN_LINK_RX_BUFF_SIZE_STR.to_string()),
             ZN_MULTICAST_IPV6_ADDRESS_KEY => Some(ZN_MULTICAST_IPV6_ADDRESS_STR.to_string()),
+            ZN_QUERIES_DEFAULT_TIMEOUT_KEY => Some(ZN_QUERIES_DEFAULT_TIMEOUT_STR.to_string()),
+            ZN_COMPRESSION_KEY => Some(ZN_COMPRESSION_STR.to_string()),
+            ZN_COMPRESSION_THRESHOLD_KEY => Some(ZN_COMPRESSION_THRESHOLD_STR.to_string()),
             _ => None,
         }
     }
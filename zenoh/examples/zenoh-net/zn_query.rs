@@ -40,11 +40,14 @@ async fn main() {
         .await
         .unwrap();
     while let Some(reply) = replies.next().await {
-        println!(
-            ">> [Reply handler] received ('{}': '{}')",
-            reply.data.res_name,
-            String::from_utf8_lossy(&reply.data.payload.contiguous())
-        )
+        match reply.data {
+            Ok(sample) => println!(
+                ">> [Reply handler] received ('{}': '{}')",
+                sample.res_name,
+                String::from_utf8_lossy(&sample.payload.contiguous())
+            ),
+            Err(error) => println!(">> [Reply handler] received error ({})", error),
+        }
     }
 }
 
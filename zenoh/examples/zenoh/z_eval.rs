@@ -61,11 +61,12 @@ async fn main() {
             println!("   >> Get name to use from path: {}", name);
             if let Ok(selector) = Selector::try_from(name.as_str()) {
                 match workspace.get(&selector).await.unwrap().next().await {
-                    Some(Data {
+                    Some(Ok(Data {
                         path: _,
                         value: Value::StringUtf8(s),
                         timestamp: _,
-                    }) => name = s,
+                    })) => name = s,
+                    Some(Err(e)) => println!("Failed to get name from '{}' : {}", name, e),
                     Some(_) => println!("Failed to get name from '{}' : not a UTF-8 String", name),
                     None => println!("Failed to get name from '{}' : not found", name),
                 }
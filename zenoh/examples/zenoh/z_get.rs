@@ -32,13 +32,16 @@ async fn main() {
     println!("Get Data from {}'...\n", selector);
     let mut data_stream = workspace.get(&selector.try_into().unwrap()).await.unwrap();
     while let Some(data) = data_stream.next().await {
-        println!(
-            "  {} : {:?} (encoding: {} , timestamp: {})",
-            data.path,
-            data.value,
-            data.value.encoding_descr(),
-            data.timestamp
-        )
+        match data {
+            Ok(data) => println!(
+                "  {} : {:?} (encoding: {} , timestamp: {})",
+                data.path,
+                data.value,
+                data.value.encoding_descr(),
+                data.timestamp
+            ),
+            Err(e) => println!("  Error: {}", e),
+        }
     }
 
     zenoh.close().await.unwrap();
@@ -69,9 +69,12 @@
 //!     let workspace = zenoh.workspace(None).await.unwrap();
 //!     let mut data_stream = workspace.get(&"/demo/example/**".try_into().unwrap()).await.unwrap();
 //!     while let Some(data) = data_stream.next().await {
-//!         println!(">> {} : {:?} at {}",
-//!             data.path, data.value, data.timestamp
-//!         )
+//!         match data {
+//!             Ok(data) => println!(">> {} : {:?} at {}",
+//!                 data.path, data.value, data.timestamp
+//!             ),
+//!             Err(e) => println!(">> Error: {}", e),
+//!         }
 //!     }
 //!     zenoh.close().await.unwrap();
 //! }
@@ -108,8 +111,8 @@ mod path;
 pub use path::{path, Path};
 mod pathexpr;
 pub use pathexpr::{pathexpr, PathExpr};
-mod selector;
-pub use selector::{selector, Selector};
+pub mod selector;
+pub use selector::{parse_time_expr, selector, Selector, TimeRange};
 mod values;
 pub use values::*;
 
@@ -13,14 +13,20 @@
 //
 #[cfg(feature = "transport_quic")]
 use super::quic::{LocatorConfigQuic, LocatorQuic};
+#[cfg(all(feature = "transport_serial", target_family = "unix"))]
+use super::serial::{LocatorConfigSerial, LocatorSerial};
 #[cfg(feature = "transport_tcp")]
 use super::tcp::{LocatorConfigTcp, LocatorTcp};
 #[cfg(feature = "transport_tls")]
 use super::tls::{LocatorConfigTls, LocatorTls};
 #[cfg(feature = "transport_udp")]
 use super::udp::{LocatorConfigUdp, LocatorUdp};
+#[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+use super::unixsock_seqpacket::{LocatorConfigUnixSocketSeqPacket, LocatorUnixSocketSeqPacket};
 #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
 use super::unixsock_stream::{LocatorConfigUnixSocketStream, LocatorUnixSocketStream};
+#[cfg(feature = "transport_ws")]
+use super::ws::{LocatorConfigWs, LocatorWs};
 use std::cmp::PartialEq;
 use std::collections::HashMap;
 use std::fmt;
@@ -46,6 +52,12 @@ pub const STR_TLS: &str = "tls";
 pub const STR_QUIC: &str = "quic";
 #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
 pub const STR_UNIXSOCK_STREAM: &str = "unixsock-stream";
+#[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+pub const STR_UNIXSOCK_SEQPACKET: &str = "unixsock-seqpacket";
+#[cfg(all(feature = "transport_serial", target_family = "unix"))]
+pub const STR_SERIAL: &str = "serial";
+#[cfg(feature = "transport_ws")]
+pub const STR_WS: &str = "ws";
 
 // Parsing chars
 pub const PROTO_SEPARATOR: char = '/';
@@ -175,6 +187,12 @@ pub enum LocatorProtocol {
     Quic,
     #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
     UnixSocketStream,
+    #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+    UnixSocketSeqPacket,
+    #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+    Serial,
+    #[cfg(feature = "transport_ws")]
+    Ws,
 }
 
 impl fmt::Display for LocatorProtocol {
@@ -190,6 +208,12 @@ impl fmt::Display for LocatorProtocol {
             LocatorProtocol::Quic => write!(f, "{}", STR_QUIC)?,
             #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
             LocatorProtocol::UnixSocketStream => write!(f, "{}", STR_UNIXSOCK_STREAM)?,
+            #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+            LocatorProtocol::UnixSocketSeqPacket => write!(f, "{}", STR_UNIXSOCK_SEQPACKET)?,
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            LocatorProtocol::Serial => write!(f, "{}", STR_SERIAL)?,
+            #[cfg(feature = "transport_ws")]
+            LocatorProtocol::Ws => write!(f, "{}", STR_WS)?,
         }
         Ok(())
     }
@@ -238,6 +262,27 @@ impl LocatorConfig {
                 ps.insert(LocatorProtocol::UnixSocketStream, p);
             }
         }
+        #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+        {
+            let mut res = LocatorConfigUnixSocketSeqPacket::from_config(config)?;
+            if let Some(p) = res.take() {
+                ps.insert(LocatorProtocol::UnixSocketSeqPacket, p);
+            }
+        }
+        #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+        {
+            let mut res = LocatorConfigSerial::from_config(config)?;
+            if let Some(p) = res.take() {
+                ps.insert(LocatorProtocol::Serial, p);
+            }
+        }
+        #[cfg(feature = "transport_ws")]
+        {
+            let mut res = LocatorConfigWs::from_config(config)?;
+            if let Some(p) = res.take() {
+                ps.insert(LocatorProtocol::Ws, p);
+            }
+        }
         Ok(ps)
     }
 }
@@ -325,6 +370,12 @@ pub enum LocatorAddress {
     Quic(LocatorQuic),
     #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
     UnixSocketStream(LocatorUnixSocketStream),
+    #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+    UnixSocketSeqPacket(LocatorUnixSocketSeqPacket),
+    #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+    Serial(LocatorSerial),
+    #[cfg(feature = "transport_ws")]
+    Ws(LocatorWs),
 }
 
 impl FromStr for LocatorAddress {
@@ -349,6 +400,12 @@ impl FromStr for LocatorAddress {
             STR_QUIC => addr.parse().map(LocatorAddress::Quic),
             #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
             STR_UNIXSOCK_STREAM => addr.parse().map(LocatorAddress::UnixSocketStream),
+            #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+            STR_UNIXSOCK_SEQPACKET => addr.parse().map(LocatorAddress::UnixSocketSeqPacket),
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            STR_SERIAL => addr.parse().map(LocatorAddress::Serial),
+            #[cfg(feature = "transport_ws")]
+            STR_WS => addr.parse().map(LocatorAddress::Ws),
             unknown => {
                 let e = format!(
                     "Invalid locator address: {}. Unknown protocol: {}.",
@@ -373,6 +430,12 @@ impl LocatorAddress {
             LocatorAddress::Quic(..) => LocatorProtocol::Quic,
             #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
             LocatorAddress::UnixSocketStream(..) => LocatorProtocol::UnixSocketStream,
+            #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+            LocatorAddress::UnixSocketSeqPacket(..) => LocatorProtocol::UnixSocketSeqPacket,
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            LocatorAddress::Serial(..) => LocatorProtocol::Serial,
+            #[cfg(feature = "transport_ws")]
+            LocatorAddress::Ws(..) => LocatorProtocol::Ws,
         }
     }
 
@@ -388,6 +451,12 @@ impl LocatorAddress {
             LocatorAddress::Quic(l) => l.is_multicast(),
             #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
             LocatorAddress::UnixSocketStream(l) => l.is_multicast(),
+            #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+            LocatorAddress::UnixSocketSeqPacket(l) => l.is_multicast(),
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            LocatorAddress::Serial(l) => l.is_multicast(),
+            #[cfg(feature = "transport_ws")]
+            LocatorAddress::Ws(l) => l.is_multicast(),
         }
     }
 }
@@ -407,6 +476,14 @@ impl fmt::Display for LocatorAddress {
             LocatorAddress::UnixSocketStream(addr) => {
                 write!(f, "{}{}{}", STR_UNIXSOCK_STREAM, PROTO_SEPARATOR, addr)?
             }
+            #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+            LocatorAddress::UnixSocketSeqPacket(addr) => {
+                write!(f, "{}{}{}", STR_UNIXSOCK_SEQPACKET, PROTO_SEPARATOR, addr)?
+            }
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            LocatorAddress::Serial(addr) => write!(f, "{}{}{}", STR_SERIAL, PROTO_SEPARATOR, addr)?,
+            #[cfg(feature = "transport_ws")]
+            LocatorAddress::Ws(addr) => write!(f, "{}{}{}", STR_WS, PROTO_SEPARATOR, addr)?,
         }
         Ok(())
     }
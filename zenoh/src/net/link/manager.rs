@@ -13,14 +13,20 @@
 //
 #[cfg(feature = "transport_quic")]
 use super::quic::LinkManagerUnicastQuic;
+#[cfg(all(feature = "transport_serial", target_family = "unix"))]
+use super::serial::LinkManagerUnicastSerial;
 #[cfg(feature = "transport_tcp")]
 use super::tcp::LinkManagerUnicastTcp;
 #[cfg(feature = "transport_tls")]
 use super::tls::LinkManagerUnicastTls;
 #[cfg(feature = "transport_udp")]
 use super::udp::{LinkManagerMulticastUdp, LinkManagerUnicastUdp};
+#[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+use super::unixsock_seqpacket::LinkManagerUnicastUnixSocketSeqPacket;
 #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
 use super::unixsock_stream::LinkManagerUnicastUnixSocketStream;
+#[cfg(feature = "transport_ws")]
+use super::ws::LinkManagerUnicastWs;
 use super::{EndPoint, LinkMulticast, LinkUnicast, Locator, LocatorProtocol};
 use crate::net::transport::TransportManager;
 use async_std::sync::Arc;
@@ -61,6 +67,14 @@ impl LinkManagerBuilderUnicast {
             LocatorProtocol::UnixSocketStream => {
                 Ok(Arc::new(LinkManagerUnicastUnixSocketStream::new(manager)))
             }
+            #[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+            LocatorProtocol::UnixSocketSeqPacket => Ok(Arc::new(
+                LinkManagerUnicastUnixSocketSeqPacket::new(manager),
+            )),
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            LocatorProtocol::Serial => Ok(Arc::new(LinkManagerUnicastSerial::new(manager))),
+            #[cfg(feature = "transport_ws")]
+            LocatorProtocol::Ws => Ok(Arc::new(LinkManagerUnicastWs::new(manager))),
         }
     }
 }
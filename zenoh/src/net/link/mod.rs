@@ -15,14 +15,20 @@ mod endpoint;
 mod manager;
 #[cfg(feature = "transport_quic")]
 pub mod quic;
+#[cfg(all(feature = "transport_serial", target_family = "unix"))]
+pub mod serial;
 #[cfg(feature = "transport_tcp")]
 pub mod tcp;
 #[cfg(feature = "transport_tls")]
 pub mod tls;
 #[cfg(feature = "transport_udp")]
 pub mod udp;
+#[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+pub mod unixsock_seqpacket;
 #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
 pub mod unixsock_stream;
+#[cfg(feature = "transport_ws")]
+pub mod ws;
 
 use crate::net::protocol::io::{WBuf, ZBuf};
 use crate::net::protocol::proto::TransportMessage;
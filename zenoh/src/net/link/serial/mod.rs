@@ -0,0 +1,41 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+mod endpoint;
+mod unicast;
+
+use super::*;
+pub use endpoint::*;
+pub use unicast::*;
+
+// Maximum MTU (Serial PDU) in bytes.
+// NOTE: Serial lines have no notion of message boundaries, hence each batch is framed
+//       with a sync word, a 16 bits length and a CRC32. A corrupted length on the line
+//       would make the receiver wait for up to that amount of bytes before detecting the
+//       error via the CRC. The MTU is then bounded to keep the resync time small.
+const SERIAL_MAX_MTU: u16 = 8_192;
+
+zconfigurable! {
+    // Default MTU (Serial PDU) in bytes.
+    static ref SERIAL_DEFAULT_MTU: u16 = 1_500;
+    // Default baud rate of the serial device when not provided in the endpoint configuration.
+    static ref SERIAL_DEFAULT_BAUDRATE: u32 = 115_200;
+    // Amount of time in microseconds to throttle the reopening of the serial device
+    // upon an error or when the previous link has been closed.
+    // Default set to 100 ms.
+    static ref SERIAL_ACCEPT_THROTTLE_TIME: u64 = 100_000;
+}
+
+pub mod config {
+    pub const SERIAL_BAUDRATE: &str = "baudrate";
+}
@@ -0,0 +1,652 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use super::*;
+use crate::net::transport::TransportManager;
+use async_io::Async;
+use async_std::path::PathBuf;
+use async_std::prelude::*;
+use async_std::sync::Mutex as AsyncMutex;
+use async_std::task;
+use async_std::task::JoinHandle;
+use async_trait::async_trait;
+use event_listener::Event;
+use nix::fcntl::OFlag;
+use nix::sys::termios::{self, BaudRate, ControlFlags, SetArg};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use zenoh_util::core::{ZError, ZErrorKind, ZResult};
+use zenoh_util::sync::Signal;
+use zenoh_util::{zasynclock, zerror, zerror2, zread, zwrite};
+
+/*************************************/
+/*             FRAMING               */
+/*************************************/
+// Each batch is sent on the serial line as:
+//   [0xA5 0x5A][len: u16 LE][payload: len bytes][crc32 LE of len and payload]
+const SERIAL_SYNC: [u8; 2] = [0xA5, 0x5A];
+const SERIAL_LEN_LEN: usize = 2;
+const SERIAL_CRC_LEN: usize = 4;
+const SERIAL_HEADER_LEN: usize = SERIAL_SYNC.len() + SERIAL_LEN_LEN;
+// Size of the chunks read from the serial device
+const SERIAL_READ_BUFFER_SIZE: usize = 1_024;
+
+fn serial_encode(payload: &[u8], buffer: &mut Vec<u8>) {
+    buffer.clear();
+    buffer.extend_from_slice(&SERIAL_SYNC);
+    buffer.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(payload);
+    let crc = crc32fast::hash(&buffer[SERIAL_SYNC.len()..]);
+    buffer.extend_from_slice(&crc.to_le_bytes());
+}
+
+#[derive(Default)]
+struct SerialDecoder {
+    // The bytes received from the serial device and not yet decoded
+    buffer: Vec<u8>,
+}
+
+impl SerialDecoder {
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    // Try to decode a frame from the received bytes and copy its payload into `output`.
+    // Any garbage preceding a valid frame, including frames with a wrong CRC, is discarded.
+    fn decode(&mut self, output: &mut [u8]) -> ZResult<Option<usize>> {
+        loop {
+            // Look for the sync word
+            match self
+                .buffer
+                .windows(SERIAL_SYNC.len())
+                .position(|w| w == SERIAL_SYNC)
+            {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    // Keep the last byte since it might be the beginning of the sync word
+                    let keep = self.buffer.last() == Some(&SERIAL_SYNC[0]);
+                    let len = self.buffer.len();
+                    self.buffer.drain(..len - keep as usize);
+                    return Ok(None);
+                }
+            }
+
+            if self.buffer.len() < SERIAL_HEADER_LEN {
+                return Ok(None);
+            }
+            let len = u16::from_le_bytes([self.buffer[2], self.buffer[3]]) as usize;
+            if len > SERIAL_MAX_MTU as usize {
+                // Not a valid frame, resync on the next sync word
+                self.buffer.drain(..1);
+                continue;
+            }
+
+            let end = SERIAL_HEADER_LEN + len;
+            if self.buffer.len() < end + SERIAL_CRC_LEN {
+                return Ok(None);
+            }
+            let crc = u32::from_le_bytes([
+                self.buffer[end],
+                self.buffer[end + 1],
+                self.buffer[end + 2],
+                self.buffer[end + 3],
+            ]);
+            if crc != crc32fast::hash(&self.buffer[SERIAL_SYNC.len()..end]) {
+                log::trace!("Discarding Serial frame with invalid CRC");
+                self.buffer.drain(..1);
+                continue;
+            }
+
+            if len > output.len() {
+                self.buffer.drain(..end + SERIAL_CRC_LEN);
+                let e = format!(
+                    "Serial frame of {} bytes exceeds the read buffer of {} bytes",
+                    len,
+                    output.len()
+                );
+                return zerror!(ZErrorKind::IoError { descr: e });
+            }
+            output[..len].copy_from_slice(&self.buffer[SERIAL_HEADER_LEN..end]);
+            self.buffer.drain(..end + SERIAL_CRC_LEN);
+            return Ok(Some(len));
+        }
+    }
+}
+
+fn get_baudrate(baudrate: u32) -> ZResult<BaudRate> {
+    let br = match baudrate {
+        50 => BaudRate::B50,
+        75 => BaudRate::B75,
+        110 => BaudRate::B110,
+        134 => BaudRate::B134,
+        150 => BaudRate::B150,
+        200 => BaudRate::B200,
+        300 => BaudRate::B300,
+        600 => BaudRate::B600,
+        1_200 => BaudRate::B1200,
+        1_800 => BaudRate::B1800,
+        2_400 => BaudRate::B2400,
+        4_800 => BaudRate::B4800,
+        9_600 => BaudRate::B9600,
+        19_200 => BaudRate::B19200,
+        38_400 => BaudRate::B38400,
+        57_600 => BaudRate::B57600,
+        115_200 => BaudRate::B115200,
+        230_400 => BaudRate::B230400,
+        #[cfg(target_os = "linux")]
+        460_800 => BaudRate::B460800,
+        #[cfg(target_os = "linux")]
+        921_600 => BaudRate::B921600,
+        _ => {
+            let e = format!("Unsupported Serial baud rate: {}", baudrate);
+            return zerror!(ZErrorKind::InvalidLocator { descr: e });
+        }
+    };
+    Ok(br)
+}
+
+fn open_serial(path: &PathBuf, baudrate: u32) -> ZResult<Async<File>> {
+    let baudrate = get_baudrate(baudrate)?;
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(OFlag::O_NOCTTY.bits())
+        .open(path)
+        .map_err(|e| {
+            let e = format!("Can not open Serial device {}: {}", path.display(), e);
+            zerror2!(ZErrorKind::InvalidLink { descr: e })
+        })?;
+
+    // Configure the device in raw mode with the requested baud rate
+    let fd = file.as_raw_fd();
+    let configure = || -> nix::Result<()> {
+        let mut tio = termios::tcgetattr(fd)?;
+        termios::cfmakeraw(&mut tio);
+        tio.control_flags |= ControlFlags::CLOCAL | ControlFlags::CREAD;
+        termios::cfsetspeed(&mut tio, baudrate)?;
+        termios::tcsetattr(fd, SetArg::TCSANOW, &tio)
+    };
+    configure().map_err(|e| {
+        let e = format!("Can not configure Serial device {}: {}", path.display(), e);
+        zerror2!(ZErrorKind::InvalidLink { descr: e })
+    })?;
+
+    Async::new(file).map_err(|e| {
+        let e = format!("Can not open Serial device {}: {}", path.display(), e);
+        zerror2!(ZErrorKind::InvalidLink { descr: e })
+    })
+}
+
+/*************************************/
+/*              LINK                 */
+/*************************************/
+struct LinkUnicastSerialRecv {
+    // The decoder of the frames received on the serial device
+    decoder: SerialDecoder,
+    // The buffer used to read from the serial device
+    buffer: Box<[u8]>,
+}
+
+pub struct LinkUnicastSerial {
+    // The serial device as an async file
+    file: Async<File>,
+    // The path of the serial device
+    path: PathBuf,
+    // The buffer used to frame the outgoing batches
+    send: AsyncMutex<Vec<u8>>,
+    // The state of the frames being received
+    recv: AsyncMutex<LinkUnicastSerialRecv>,
+    // Whether the link is still active and the event notified upon close
+    active: AtomicBool,
+    closed: Event,
+}
+
+impl LinkUnicastSerial {
+    fn new(file: Async<File>, path: PathBuf) -> LinkUnicastSerial {
+        LinkUnicastSerial {
+            file,
+            path,
+            send: AsyncMutex::new(vec![]),
+            recv: AsyncMutex::new(LinkUnicastSerialRecv {
+                decoder: SerialDecoder::default(),
+                buffer: vec![0u8; SERIAL_READ_BUFFER_SIZE].into_boxed_slice(),
+            }),
+            active: AtomicBool::new(true),
+            closed: Event::new(),
+        }
+    }
+
+    async fn wait_closed(&self) {
+        loop {
+            if !self.active.load(Ordering::Acquire) {
+                return;
+            }
+            let listener = self.closed.listen();
+            if !self.active.load(Ordering::Acquire) {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    fn closed_error(&self) -> ZError {
+        let e = format!("Serial link {} has been closed", self);
+        log::trace!("{}", e);
+        zerror2!(ZErrorKind::IoError { descr: e })
+    }
+}
+
+#[async_trait]
+impl LinkUnicastTrait for LinkUnicastSerial {
+    async fn close(&self) -> ZResult<()> {
+        log::trace!("Closing Serial link: {}", self);
+        // Stop any pending read and notify the listener, if any
+        self.active.store(false, Ordering::Release);
+        self.closed.notify_additional(usize::MAX);
+        Ok(())
+    }
+
+    async fn write(&self, buffer: &[u8]) -> ZResult<usize> {
+        self.write_all(buffer).await?;
+        Ok(buffer.len())
+    }
+
+    async fn write_all(&self, buffer: &[u8]) -> ZResult<()> {
+        if !self.active.load(Ordering::Acquire) {
+            return Err(self.closed_error());
+        }
+        if buffer.len() > SERIAL_MAX_MTU as usize {
+            let e = format!(
+                "Write error on Serial link {}: {} bytes exceed the MTU",
+                self,
+                buffer.len()
+            );
+            log::trace!("{}", e);
+            return zerror!(ZErrorKind::IoError { descr: e });
+        }
+
+        let mut guard = zasynclock!(self.send);
+        serial_encode(buffer, &mut guard);
+        (&self.file).write_all(&guard).await.map_err(|e| {
+            let e = format!("Write error on Serial link {}: {}", self, e);
+            log::trace!("{}", e);
+            zerror2!(ZErrorKind::IoError { descr: e })
+        })
+    }
+
+    async fn read(&self, buffer: &mut [u8]) -> ZResult<usize> {
+        enum Action {
+            Read(usize),
+            Stop,
+        }
+
+        let mut guard = zasynclock!(self.recv);
+        let guard = &mut *guard;
+        loop {
+            if let Some(n) = guard.decoder.decode(buffer)? {
+                return Ok(n);
+            }
+
+            let read = async { (&self.file).read(&mut guard.buffer).await.map(Action::Read) };
+            let stop = async {
+                self.wait_closed().await;
+                Ok(Action::Stop)
+            };
+            match read.race(stop).await {
+                Ok(Action::Read(0)) => {
+                    let e = format!("Read error on Serial link {}: end of file", self);
+                    log::trace!("{}", e);
+                    return zerror!(ZErrorKind::IoError { descr: e });
+                }
+                Ok(Action::Read(n)) => guard.decoder.push(&guard.buffer[..n]),
+                Ok(Action::Stop) => return Err(self.closed_error()),
+                Err(e) => {
+                    let e = format!("Read error on Serial link {}: {}", self, e);
+                    log::trace!("{}", e);
+                    return zerror!(ZErrorKind::IoError { descr: e });
+                }
+            }
+        }
+    }
+
+    async fn read_exact(&self, buffer: &mut [u8]) -> ZResult<()> {
+        let mut read = 0;
+        while read < buffer.len() {
+            read += self.read(&mut buffer[read..]).await?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn get_src(&self) -> Locator {
+        Locator {
+            address: LocatorAddress::Serial(LocatorSerial {
+                path: self.path.clone(),
+            }),
+            metadata: None,
+        }
+    }
+
+    #[inline(always)]
+    fn get_dst(&self) -> Locator {
+        Locator {
+            address: LocatorAddress::Serial(LocatorSerial {
+                path: self.path.clone(),
+            }),
+            metadata: None,
+        }
+    }
+
+    #[inline(always)]
+    fn get_mtu(&self) -> u16 {
+        *SERIAL_DEFAULT_MTU
+    }
+
+    #[inline(always)]
+    fn is_reliable(&self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn is_streamed(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for LinkUnicastSerial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LinkUnicastSerial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Serial").field("path", &self.path).finish()
+    }
+}
+
+/*************************************/
+/*          LISTENER                 */
+/*************************************/
+struct ListenerUnicastSerial {
+    endpoint: EndPoint,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    handle: JoinHandle<ZResult<()>>,
+}
+
+impl ListenerUnicastSerial {
+    fn new(
+        endpoint: EndPoint,
+        active: Arc<AtomicBool>,
+        signal: Signal,
+        handle: JoinHandle<ZResult<()>>,
+    ) -> ListenerUnicastSerial {
+        ListenerUnicastSerial {
+            endpoint,
+            active,
+            signal,
+            handle,
+        }
+    }
+}
+
+pub struct LinkManagerUnicastSerial {
+    manager: TransportManager,
+    listeners: Arc<RwLock<HashMap<PathBuf, ListenerUnicastSerial>>>,
+}
+
+impl LinkManagerUnicastSerial {
+    pub(crate) fn new(manager: TransportManager) -> Self {
+        Self {
+            manager,
+            listeners: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkManagerUnicastTrait for LinkManagerUnicastSerial {
+    async fn new_link(&self, endpoint: EndPoint) -> ZResult<LinkUnicast> {
+        let path = get_serial_path(&endpoint.locator)?;
+        let baudrate = get_serial_baudrate(&endpoint)?;
+
+        let file = open_serial(&path, baudrate)?;
+        let link = Arc::new(LinkUnicastSerial::new(file, path));
+
+        Ok(LinkUnicast(link))
+    }
+
+    async fn new_listener(&self, endpoint: EndPoint) -> ZResult<Locator> {
+        let path = get_serial_path(&endpoint.locator)?;
+        let baudrate = get_serial_baudrate(&endpoint)?;
+
+        if zread!(self.listeners).contains_key(&path) {
+            let e = format!(
+                "Can not create a new Serial listener on {}: already listening",
+                path.display()
+            );
+            log::trace!("{}", e);
+            return zerror!(ZErrorKind::InvalidLink { descr: e });
+        }
+
+        // Open the device straight away to report any configuration error
+        let file = open_serial(&path, baudrate)?;
+
+        // Spawn the listen loop for the listener
+        let active = Arc::new(AtomicBool::new(true));
+        let signal = Signal::new();
+
+        let c_active = active.clone();
+        let c_signal = signal.clone();
+        let c_manager = self.manager.clone();
+        let c_listeners = self.listeners.clone();
+        let c_path = path.clone();
+        let handle = task::spawn(async move {
+            // Wait for the listen loop to terminate
+            let res = listen_task(
+                file,
+                c_path.clone(),
+                baudrate,
+                c_active,
+                c_signal,
+                c_manager,
+            )
+            .await;
+            zwrite!(c_listeners).remove(&c_path);
+            res
+        });
+
+        let locator = endpoint.locator.clone();
+        let listener = ListenerUnicastSerial::new(endpoint, active, signal, handle);
+        // Update the list of active listeners on the manager
+        zwrite!(self.listeners).insert(path, listener);
+
+        Ok(locator)
+    }
+
+    async fn del_listener(&self, endpoint: &EndPoint) -> ZResult<()> {
+        let path = get_serial_path(&endpoint.locator)?;
+
+        // Stop the listener
+        let listener = zwrite!(self.listeners).remove(&path).ok_or_else(|| {
+            let e = format!(
+                "Can not delete the Serial listener because it has not been found: {}",
+                path.display()
+            );
+            log::trace!("{}", e);
+            zerror2!(ZErrorKind::InvalidLink { descr: e })
+        })?;
+
+        // Send the stop signal
+        listener.active.store(false, Ordering::Release);
+        listener.signal.trigger();
+        listener.handle.await
+    }
+
+    fn get_listeners(&self) -> Vec<EndPoint> {
+        zread!(self.listeners)
+            .values()
+            .map(|l| l.endpoint.clone())
+            .collect()
+    }
+
+    fn get_locators(&self) -> Vec<Locator> {
+        zread!(self.listeners)
+            .values()
+            .map(|l| l.endpoint.locator.clone())
+            .collect()
+    }
+}
+
+// A serial line has no notion of connection: the listener hands a link over the device to the
+// transport manager and waits for it to be closed (e.g. upon establishment timeout or lease
+// expiration) before reopening the device and waiting for the next peer.
+async fn listen_task(
+    file: Async<File>,
+    path: PathBuf,
+    baudrate: u32,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    manager: TransportManager,
+) -> ZResult<()> {
+    enum Action {
+        Closed,
+        Stop,
+    }
+
+    async fn closed(link: &LinkUnicastSerial) -> Action {
+        link.wait_closed().await;
+        Action::Closed
+    }
+
+    async fn stop(signal: Signal) -> Action {
+        signal.wait().await;
+        Action::Stop
+    }
+
+    log::trace!("Ready to accept Serial links on: {}", path.display());
+    let mut file = Some(file);
+    while active.load(Ordering::Acquire) {
+        let f = match file.take() {
+            Some(f) => f,
+            None => match open_serial(&path, baudrate) {
+                Ok(f) => f,
+                Err(e) => {
+                    log::warn!("{}", e);
+                    // Throttle the listen loop upon an error
+                    let throttle = async {
+                        task::sleep(Duration::from_micros(*SERIAL_ACCEPT_THROTTLE_TIME)).await;
+                        Action::Closed
+                    };
+                    match throttle.race(stop(signal.clone())).await {
+                        Action::Closed => continue,
+                        Action::Stop => break,
+                    }
+                }
+            },
+        };
+
+        log::debug!("Accepted Serial link on: {}", path.display());
+        // Create the new link object
+        let link = Arc::new(LinkUnicastSerial::new(f, path.clone()));
+
+        // Communicate the new link to the initial transport manager
+        manager
+            .handle_new_link_unicast(LinkUnicast(link.clone()))
+            .await;
+
+        // Wait for the link to be closed before reopening the device
+        match closed(&link).race(stop(signal.clone())).await {
+            Action::Closed => {
+                task::sleep(Duration::from_micros(*SERIAL_ACCEPT_THROTTLE_TIME)).await
+            }
+            Action::Stop => {
+                // The device is owned by the listener, release it
+                let _ = link.close().await;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(decoder: &mut SerialDecoder) -> Vec<Vec<u8>> {
+        let mut output = vec![0u8; SERIAL_MAX_MTU as usize];
+        let mut frames = vec![];
+        while let Some(n) = decoder.decode(&mut output).unwrap() {
+            frames.push(output[..n].to_vec());
+        }
+        frames
+    }
+
+    #[test]
+    fn serial_framing() {
+        let payloads: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![0xA5, 0x5A, 0xA5],
+            (0..=255u8).collect(),
+            vec![0x5A; SERIAL_MAX_MTU as usize],
+        ];
+        let mut bytes = vec![];
+        let mut buffer = vec![];
+        for p in payloads.iter() {
+            serial_encode(p, &mut buffer);
+            bytes.extend_from_slice(&buffer);
+        }
+
+        // Decode the frames received one byte at a time
+        let mut decoder = SerialDecoder::default();
+        let mut frames = vec![];
+        for b in bytes.iter() {
+            decoder.push(&[*b]);
+            frames.extend(decode_all(&mut decoder));
+        }
+        assert_eq!(frames, payloads);
+
+        // Decode the frames surrounded by garbage and with a corrupted frame
+        let mut corrupted = vec![0x00, 0xA5, 0x5A, 0xFF, 0xFF, 0xA5];
+        serial_encode(&[1, 2, 3, 4], &mut buffer);
+        let n = buffer.len();
+        buffer[n - 1] ^= 0xFF;
+        corrupted.extend_from_slice(&buffer);
+        corrupted.extend_from_slice(&bytes);
+        corrupted.extend_from_slice(&[0xA5]);
+
+        let mut decoder = SerialDecoder::default();
+        decoder.push(&corrupted);
+        assert_eq!(decode_all(&mut decoder), payloads);
+        assert_eq!(decoder.buffer, vec![0xA5]);
+
+        // A frame larger than the read buffer is discarded with an error
+        serial_encode(&[0u8; 16], &mut buffer);
+        decoder.push(&buffer);
+        assert!(decoder.decode(&mut [0u8; 8]).is_err());
+        assert!(decoder.buffer.is_empty());
+    }
+}
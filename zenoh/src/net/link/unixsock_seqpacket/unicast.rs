@@ -0,0 +1,494 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use super::*;
+use crate::net::transport::TransportManager;
+use async_io::Async;
+use async_std::path::PathBuf;
+use async_std::prelude::*;
+use async_std::task;
+use async_std::task::JoinHandle;
+use async_trait::async_trait;
+use nix::sys::socket::{
+    accept4, bind, connect, listen, recv, send, shutdown, socket, AddressFamily, MsgFlags,
+    Shutdown, SockAddr, SockFlag, SockType,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::remove_file;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+use zenoh_util::core::{ZError, ZErrorKind, ZResult};
+use zenoh_util::sync::Signal;
+use zenoh_util::{zerror, zerror2, zread, zwrite};
+
+// A Unix domain socket of type SOCK_SEQPACKET which is closed when dropped
+struct SeqPacketSocket(RawFd);
+
+impl SeqPacketSocket {
+    fn new() -> io::Result<SeqPacketSocket> {
+        let fd = socket(
+            AddressFamily::Unix,
+            SockType::SeqPacket,
+            SockFlag::SOCK_CLOEXEC,
+            None,
+        )?;
+        Ok(SeqPacketSocket(fd))
+    }
+}
+
+impl AsRawFd for SeqPacketSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Drop for SeqPacketSocket {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.0);
+    }
+}
+
+pub struct LinkUnicastUnixSocketSeqPacket {
+    // The underlying socket registered in the async reactor
+    socket: Async<SeqPacketSocket>,
+    // The Unix domain socket source path
+    src_path: String,
+    // The Unix domain socker destination path (random UUIDv4)
+    dst_path: String,
+}
+
+impl LinkUnicastUnixSocketSeqPacket {
+    fn new(
+        socket: Async<SeqPacketSocket>,
+        src_path: String,
+        dst_path: String,
+    ) -> LinkUnicastUnixSocketSeqPacket {
+        LinkUnicastUnixSocketSeqPacket {
+            socket,
+            src_path,
+            dst_path,
+        }
+    }
+}
+
+#[async_trait]
+impl LinkUnicastTrait for LinkUnicastUnixSocketSeqPacket {
+    async fn close(&self) -> ZResult<()> {
+        log::trace!("Closing UnixSocketSeqPacket link: {}", self);
+        // Close the underlying UnixSocketSeqPacket socket
+        let res = shutdown(self.socket.as_raw_fd(), Shutdown::Both);
+        log::trace!("UnixSocketSeqPacket link shutdown {}: {:?}", self, res);
+        res.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: e.to_string(),
+            })
+        })
+    }
+
+    async fn write(&self, buffer: &[u8]) -> ZResult<usize> {
+        self.socket
+            .write_with(|s| send(s.0, buffer, MsgFlags::empty()).map_err(io::Error::from))
+            .await
+            .map_err(|e| {
+                let e = format!("Write error on UnixSocketSeqPacket link {}: {}", self, e);
+                log::trace!("{}", e);
+                zerror2!(ZErrorKind::IoError { descr: e })
+            })
+    }
+
+    async fn write_all(&self, buffer: &[u8]) -> ZResult<()> {
+        // Each packet is sent atomically, a partial write is not possible
+        let n = self.write(buffer).await?;
+        if n != buffer.len() {
+            let e = format!(
+                "Write error on UnixSocketSeqPacket link {}: sent {} out of {} bytes",
+                self,
+                n,
+                buffer.len()
+            );
+            log::trace!("{}", e);
+            return zerror!(ZErrorKind::IoError { descr: e });
+        }
+        Ok(())
+    }
+
+    async fn read(&self, buffer: &mut [u8]) -> ZResult<usize> {
+        let n = self
+            .socket
+            .read_with(|s| recv(s.0, buffer, MsgFlags::empty()).map_err(io::Error::from))
+            .await
+            .map_err(|e| {
+                let e = format!("Read error on UnixSocketSeqPacket link {}: {}", self, e);
+                log::trace!("{}", e);
+                zerror2!(ZErrorKind::IoError { descr: e })
+            })?;
+        // A zero-length read means the peer has closed the connection
+        if n == 0 {
+            let e = format!("Read error on UnixSocketSeqPacket link {}: closed", self);
+            log::trace!("{}", e);
+            return zerror!(ZErrorKind::IoError { descr: e });
+        }
+        Ok(n)
+    }
+
+    async fn read_exact(&self, buffer: &mut [u8]) -> ZResult<()> {
+        let mut read = 0;
+        while read < buffer.len() {
+            read += self.read(&mut buffer[read..]).await?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn get_src(&self) -> Locator {
+        Locator {
+            address: LocatorAddress::UnixSocketSeqPacket(LocatorUnixSocketSeqPacket {
+                path: PathBuf::from(self.src_path.clone()),
+            }),
+            metadata: None,
+        }
+    }
+
+    #[inline(always)]
+    fn get_dst(&self) -> Locator {
+        Locator {
+            address: LocatorAddress::UnixSocketSeqPacket(LocatorUnixSocketSeqPacket {
+                path: PathBuf::from(self.dst_path.clone()),
+            }),
+            metadata: None,
+        }
+    }
+
+    #[inline(always)]
+    fn get_mtu(&self) -> u16 {
+        *UNIXSOCKSEQPACKET_DEFAULT_MTU
+    }
+
+    #[inline(always)]
+    fn is_reliable(&self) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn is_streamed(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for LinkUnicastUnixSocketSeqPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} => {}", self.src_path, self.dst_path)?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LinkUnicastUnixSocketSeqPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnixSocketSeqPacket")
+            .field("src", &self.src_path)
+            .field("dst", &self.dst_path)
+            .finish()
+    }
+}
+
+/*************************************/
+/*          LISTENER                 */
+/*************************************/
+struct ListenerUnixSocketSeqPacket {
+    endpoint: EndPoint,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    handle: JoinHandle<ZResult<()>>,
+    lock_fd: RawFd,
+}
+
+impl ListenerUnixSocketSeqPacket {
+    fn new(
+        endpoint: EndPoint,
+        active: Arc<AtomicBool>,
+        signal: Signal,
+        handle: JoinHandle<ZResult<()>>,
+        lock_fd: RawFd,
+    ) -> ListenerUnixSocketSeqPacket {
+        ListenerUnixSocketSeqPacket {
+            endpoint,
+            active,
+            signal,
+            handle,
+            lock_fd,
+        }
+    }
+}
+
+pub struct LinkManagerUnicastUnixSocketSeqPacket {
+    manager: TransportManager,
+    listeners: Arc<RwLock<HashMap<String, ListenerUnixSocketSeqPacket>>>,
+}
+
+impl LinkManagerUnicastUnixSocketSeqPacket {
+    pub(crate) fn new(manager: TransportManager) -> Self {
+        Self {
+            manager,
+            listeners: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkManagerUnicastTrait for LinkManagerUnicastUnixSocketSeqPacket {
+    async fn new_link(&self, endpoint: EndPoint) -> ZResult<LinkUnicast> {
+        let path = get_unix_path_as_string(&endpoint.locator);
+
+        // Create the UnixSocketSeqPacket connection
+        // NOTE: connecting to a Unix domain socket does not block unless the listener
+        //       backlog is full, the socket is then made non-blocking once connected.
+        let connect = || -> io::Result<Async<SeqPacketSocket>> {
+            let socket = SeqPacketSocket::new()?;
+            let addr = SockAddr::new_unix(path.as_str())?;
+            connect(socket.0, &addr)?;
+            Async::new(socket)
+        };
+        let socket = connect().map_err(|e| {
+            let e = format!(
+                "Can not create a new UnixSocketSeqPacket link bound to {}: {}",
+                path, e
+            );
+            log::warn!("{}", e);
+            zerror2!(ZErrorKind::Other { descr: e })
+        })?;
+
+        // The connecting socket is not bound to any path
+        let local_path_str = format!("{}", Uuid::new_v4());
+
+        let link = Arc::new(LinkUnicastUnixSocketSeqPacket::new(
+            socket,
+            local_path_str,
+            path,
+        ));
+
+        Ok(LinkUnicast(link))
+    }
+
+    async fn new_listener(&self, endpoint: EndPoint) -> ZResult<Locator> {
+        let path = get_unix_path_as_string(&endpoint.locator);
+
+        // Because of the lack of SO_REUSEADDR we have to check if the
+        // file is still there and if it is not used by another process.
+        // In order to do so we use a separate lock file, as for the
+        // UnixSocketStream listener.
+        let lock_file_path = format!("{}.lock", path);
+
+        let mut open_flags = nix::fcntl::OFlag::empty();
+        open_flags.insert(nix::fcntl::OFlag::O_CREAT);
+        open_flags.insert(nix::fcntl::OFlag::O_RDONLY);
+
+        let mut open_mode = nix::sys::stat::Mode::empty();
+        open_mode.insert(nix::sys::stat::Mode::S_IRUSR);
+        open_mode.insert(nix::sys::stat::Mode::S_IWUSR);
+
+        let lock_fd = nix::fcntl::open(
+            std::path::Path::new(&lock_file_path),
+            open_flags,
+            open_mode,
+        ).map_err(|e| {
+            let e = format!(
+                "Can not create a new UnixSocketSeqPacket listener on {} - Unable to open lock file: {}",
+                path, e
+            );
+            log::warn!("{}", e);
+            zerror2!(ZErrorKind::InvalidLink { descr: e })
+        })?;
+
+        // We try to acquire the lock
+        nix::fcntl::flock(lock_fd, nix::fcntl::FlockArg::LockExclusiveNonblock).map_err(|e| {
+            let _ = nix::unistd::close(lock_fd);
+            let e = format!(
+                "Can not create a new UnixSocketSeqPacket listener on {} - Unable to acquire look: {}",
+                path, e
+            );
+            log::warn!("{}", e);
+            zerror2!(ZErrorKind::InvalidLink { descr: e })
+        })?;
+
+        // Lock is acquired we can remove the socket file
+        let _ = remove_file(path.clone());
+
+        // Bind the Unix socket
+        let bind = || -> io::Result<Async<SeqPacketSocket>> {
+            let socket = SeqPacketSocket::new()?;
+            let addr = SockAddr::new_unix(path.as_str())?;
+            bind(socket.0, &addr)?;
+            listen(socket.0, *UNIXSOCKSEQPACKET_BACKLOG)?;
+            Async::new(socket)
+        };
+        let socket = bind().map_err(|e| {
+            let _ = nix::unistd::close(lock_fd);
+            let e = format!(
+                "Can not create a new UnixSocketSeqPacket listener on {}: {}",
+                path, e
+            );
+            log::warn!("{}", e);
+            zerror2!(ZErrorKind::InvalidLink { descr: e })
+        })?;
+
+        // Spawn the accept loop for the listener
+        let active = Arc::new(AtomicBool::new(true));
+        let signal = Signal::new();
+
+        let c_active = active.clone();
+        let c_signal = signal.clone();
+        let c_manager = self.manager.clone();
+        let c_listeners = self.listeners.clone();
+        let c_path = path.clone();
+        let handle = task::spawn(async move {
+            // Wait for the accept loop to terminate
+            let res = accept_task(socket, c_path.clone(), c_active, c_signal, c_manager).await;
+            zwrite!(c_listeners).remove(&c_path);
+            res
+        });
+
+        let locator = endpoint.locator.clone();
+        let listener = ListenerUnixSocketSeqPacket::new(endpoint, active, signal, handle, lock_fd);
+        zwrite!(self.listeners).insert(path, listener);
+
+        Ok(locator)
+    }
+
+    async fn del_listener(&self, endpoint: &EndPoint) -> ZResult<()> {
+        let path = get_unix_path_as_string(&endpoint.locator);
+
+        // Stop the listener
+        let listener = zwrite!(self.listeners).remove(&path).ok_or_else(|| {
+            let e = format!(
+                "Can not delete the UnixSocketSeqPacket listener because it has not been found: {}",
+                path
+            );
+            log::trace!("{}", e);
+            zerror2!(ZErrorKind::InvalidLink { descr: e })
+        })?;
+
+        // Send the stop signal
+        listener.active.store(false, Ordering::Release);
+        listener.signal.trigger();
+        let res = listener.handle.await;
+
+        //Release the lock
+        let _ = nix::fcntl::flock(listener.lock_fd, nix::fcntl::FlockArg::UnlockNonblock);
+        let _ = nix::unistd::close(listener.lock_fd);
+        let _ = remove_file(path.clone());
+
+        // Remove the Unix Domain Socket file
+        let lock_file_path = format!("{}.lock", path);
+        let tmp = remove_file(lock_file_path);
+        log::trace!(
+            "UnixSocketSeqPacket Domain Socket removal result: {:?}",
+            tmp
+        );
+        res
+    }
+
+    fn get_listeners(&self) -> Vec<EndPoint> {
+        zread!(self.listeners)
+            .values()
+            .map(|x| x.endpoint.clone())
+            .collect()
+    }
+
+    fn get_locators(&self) -> Vec<Locator> {
+        zread!(self.listeners)
+            .values()
+            .map(|x| x.endpoint.locator.clone())
+            .collect()
+    }
+}
+
+async fn accept_task(
+    socket: Async<SeqPacketSocket>,
+    src_path: String,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    manager: TransportManager,
+) -> ZResult<()> {
+    enum Action {
+        Accept(Async<SeqPacketSocket>),
+        Stop,
+    }
+
+    async fn accept(socket: &Async<SeqPacketSocket>) -> ZResult<Action> {
+        let accept = || -> io::Result<Async<SeqPacketSocket>> {
+            let fd = accept4(socket.as_raw_fd(), SockFlag::SOCK_CLOEXEC)?;
+            Async::new(SeqPacketSocket(fd))
+        };
+        let stream = socket.read_with(|_| accept()).await.map_err(|e| {
+            zerror2!(ZErrorKind::IoError {
+                descr: e.to_string()
+            })
+        })?;
+        Ok(Action::Accept(stream))
+    }
+
+    async fn stop(signal: Signal) -> ZResult<Action> {
+        signal.wait().await;
+        Ok(Action::Stop)
+    }
+
+    // The accept future
+    log::trace!(
+        "Ready to accept UnixSocketSeqPacket connections on: {}",
+        src_path
+    );
+    while active.load(Ordering::Acquire) {
+        // Wait for incoming connections
+        let stream = match accept(&socket).race(stop(signal.clone())).await {
+            Ok(action) => match action {
+                Action::Accept(stream) => stream,
+                Action::Stop => break,
+            },
+            Err(e) => {
+                log::warn!("{}. Hint: increase the system open file limit.", e);
+                // Throttle the accept loop upon an error
+                // NOTE: This might be due to various factors. However, the most common case is that
+                //       the process has reached the maximum number of open files in the system. On
+                //       Linux systems this limit can be changed by using the "ulimit" command line
+                //       tool. In case of systemd-based systems, this can be changed by using the
+                //       "sysctl" command line tool.
+                task::sleep(Duration::from_micros(
+                    *UNIXSOCKSEQPACKET_ACCEPT_THROTTLE_TIME,
+                ))
+                .await;
+                continue;
+            }
+        };
+
+        let dst_path = format!("{}", Uuid::new_v4());
+
+        log::debug!("Accepted UnixSocketSeqPacket connection on: {}", src_path);
+
+        // Create the new link object
+        let link = Arc::new(LinkUnicastUnixSocketSeqPacket::new(
+            stream,
+            src_path.clone(),
+            dst_path,
+        ));
+
+        // Communicate the new link to the initial transport manager
+        manager.handle_new_link_unicast(LinkUnicast(link)).await;
+    }
+
+    Ok(())
+}
@@ -115,6 +115,7 @@ pub mod utils {
     pub mod resource_name {
         pub use super::super::protocol::core::rname::include;
         pub use super::super::protocol::core::rname::intersect;
+        pub use super::super::protocol::core::rname::LIVELINESS_PREFIX;
     }
 }
 
@@ -451,6 +451,7 @@ pub mod queryable {
     pub const ALL_KINDS: super::ZInt = 0x01;
     pub const STORAGE: super::ZInt = 0x02;
     pub const EVAL: super::ZInt = 0x04;
+    pub const LIVELINESS: super::ZInt = 0x08;
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
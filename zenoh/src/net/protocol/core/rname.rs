@@ -153,6 +153,9 @@ pub fn include(this: &str, sub: &str) -> bool {
 
 pub const ADMIN_PREFIX: &str = "/@/";
 
+/// The prefix of the resource names of the liveliness tokens.
+pub const LIVELINESS_PREFIX: &str = "/@/liveliness";
+
 #[inline(always)]
 pub fn matches(s1: &str, s2: &str) -> bool {
     if s1.starts_with(ADMIN_PREFIX) == s2.starts_with(ADMIN_PREFIX) {
@@ -46,6 +46,7 @@ pub(crate) mod imsg {
         pub(crate) const PULL: u8 = 0x0e;
         pub(crate) const UNIT: u8 = 0x0f;
         pub(crate) const LINK_STATE_LIST: u8 = 0x10;
+        pub(crate) const QUERY_CANCEL: u8 = 0x11;
 
         // Message decorators
         pub(crate) const PRIORITY: u8 = 0x1c;
@@ -126,12 +127,14 @@ pub mod tmsg {
         use super::ZInt;
 
         pub const QOS: ZInt = 1 << 0; // 0x01 QoS       if PRIORITY==1 then the transport supports QoS
+        pub const COMPRESSION: ZInt = 1 << 1; // 0x02 Compression if C==1 then batches may be compressed
     }
 
     pub mod join_options {
         use super::ZInt;
 
         pub const QOS: ZInt = 1 << 0; // 0x01 QoS       if PRIORITY==1 then the transport supports QoS
+        pub const COMPRESSION: ZInt = 1 << 1; // 0x02 Compression if C==1 then batches may be compressed
     }
 
     // Reason for the Close message
@@ -172,6 +175,7 @@ pub mod zmsg {
         pub const PULL: u8 = imsg::id::PULL;
         pub const UNIT: u8 = imsg::id::UNIT;
         pub const LINK_STATE_LIST: u8 = imsg::id::LINK_STATE_LIST;
+        pub const QUERY_CANCEL: u8 = imsg::id::QUERY_CANCEL;
 
         // Message decorators
         pub const PRIORITY: u8 = imsg::id::PRIORITY;
@@ -183,6 +187,7 @@ pub mod zmsg {
     // Zenoh message flags
     pub mod flag {
         pub const D: u8 = 1 << 5; // 0x20 Drop          if D==1 then the message can be dropped
+        pub const E: u8 = 1 << 6; // 0x40 Error         if E==1 then the reply is an error (e.g., ReplyContext)
         pub const F: u8 = 1 << 5; // 0x20 Final         if F==1 then this is the final message (e.g., ReplyContext, Pull)
         pub const I: u8 = 1 << 6; // 0x40 DataInfo      if I==1 then DataInfo is present
         pub const K: u8 = 1 << 7; // 0x80 ResourceKey   if K==1 then resource key has name
@@ -295,6 +300,10 @@ pub mod zmsg {
             priority: Priority::Control,
             reliability: Reliability::Reliable,
         };
+        pub const QUERY_CANCEL: Channel = Channel {
+            priority: Priority::Data,
+            reliability: Reliability::Reliable,
+        };
     }
 
     // Default congestion control for each Zenoh Message
@@ -308,6 +317,7 @@ pub mod zmsg {
         pub const REPLY: CongestionControl = CongestionControl::Block;
         pub const UNIT: CongestionControl = CongestionControl::Block;
         pub const LINK_STATE_LIST: CongestionControl = CongestionControl::Block;
+        pub const QUERY_CANCEL: CongestionControl = CongestionControl::Block;
     }
 }
 
@@ -381,10 +391,14 @@ impl Attachment {
 ///   - or a **Unit** message in case the message is a
 ///     SOURCE_FINAL or REPLY_FINAL.
 ///  The **replier-id** (eval or storage id) is represented as a byte-array.
+///  A **Data** message decorated with E==1 is an error returned by the replier
+///  instead of a result, its payload describing the error.
+///  A **Unit** message decorated with F==1 and E==1 is a partial REPLY_FINAL:
+///  the query was finalized (e.g. it timed out) before all the replies were received.
 ///
 ///  7 6 5 4 3 2 1 0
 /// +-+-+-+-+-+-+-+-+
-/// |X|X|F|  R_CTX  |
+/// |X|E|F|  R_CTX  |
 /// +-+-+-+---------+
 /// ~      qid      ~
 /// +---------------+
@@ -394,6 +408,7 @@ impl Attachment {
 /// +---------------+
 ///
 /// - if F==1 then the message is a REPLY_FINAL
+/// - if E==1 then the message is a REPLY_ERROR (a partial REPLY_FINAL if F==1)
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReplierInfo {
@@ -404,6 +419,7 @@ pub struct ReplierInfo {
 pub struct ReplyContext {
     pub qid: ZInt,
     pub replier: Option<ReplierInfo>,
+    pub is_error: bool,
 }
 
 impl Header for ReplyContext {
@@ -413,6 +429,9 @@ impl Header for ReplyContext {
         if self.is_final() {
             header |= zmsg::flag::F;
         }
+        if self.is_error {
+            header |= zmsg::flag::E;
+        }
         header
     }
 }
@@ -421,13 +440,40 @@ impl ReplyContext {
     // Note: id replier_id=None flag F is set, meaning it's a REPLY_FINAL
     #[inline(always)]
     pub fn new(qid: ZInt, replier: Option<ReplierInfo>) -> ReplyContext {
-        ReplyContext { qid, replier }
+        ReplyContext {
+            qid,
+            replier,
+            is_error: false,
+        }
+    }
+
+    #[inline(always)]
+    pub fn new_error(qid: ZInt, replier: ReplierInfo) -> ReplyContext {
+        ReplyContext {
+            qid,
+            replier: Some(replier),
+            is_error: true,
+        }
+    }
+
+    #[inline(always)]
+    pub fn new_final(qid: ZInt, is_partial: bool) -> ReplyContext {
+        ReplyContext {
+            qid,
+            replier: None,
+            is_error: is_partial,
+        }
     }
 
     #[inline(always)]
     pub fn is_final(&self) -> bool {
         self.replier.is_none()
     }
+
+    #[inline(always)]
+    pub fn is_partial(&self) -> bool {
+        self.is_final() && self.is_error
+    }
 }
 
 /// -- RoutingContext decorator
@@ -1011,6 +1057,31 @@ impl Header for Query {
     }
 }
 
+/// # QueryCancel message
+///
+/// ```text
+/// The **QueryCancel** message is sent by the source of a query to the destination
+/// of that query, to indicate that it is no longer interested in the replies.
+///
+///  7 6 5 4 3 2 1 0
+/// +-+-+-+-+-+-+-+-+
+/// |X|X|X| Q_CANCL |
+/// +-+-+-+---------+
+/// ~      qid      ~
+/// +---------------+
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryCancel {
+    pub qid: ZInt,
+}
+
+impl Header for QueryCancel {
+    #[inline(always)]
+    fn header(&self) -> u8 {
+        zmsg::id::QUERY_CANCEL
+    }
+}
+
 //  7 6 5 4 3 2 1 0
 // +-+-+-+-+-+-+-+-+
 // ~X|X|X|X|X|L|W|P~
@@ -1085,6 +1156,7 @@ pub enum ZenohBody {
     Pull(Pull),
     Unit(Unit),
     LinkStateList(LinkStateList),
+    QueryCancel(QueryCancel),
 }
 
 #[derive(Clone, PartialEq)]
@@ -1234,6 +1306,17 @@ impl ZenohMessage {
         }
     }
 
+    pub fn make_query_cancel(qid: ZInt, attachment: Option<Attachment>) -> ZenohMessage {
+        ZenohMessage {
+            body: ZenohBody::QueryCancel(QueryCancel { qid }),
+            channel: zmsg::default_channel::QUERY_CANCEL,
+            routing_context: None,
+            attachment,
+            #[cfg(feature = "stats")]
+            size: None,
+        }
+    }
+
     pub fn make_link_state_list(
         link_states: Vec<LinkState>,
         attachment: Option<Attachment>,
@@ -1267,6 +1350,7 @@ impl ZenohMessage {
             ZenohBody::Pull(_) => zmsg::default_congestion_control::PULL,
             ZenohBody::Query(_) => zmsg::default_congestion_control::QUERY,
             ZenohBody::LinkStateList(_) => zmsg::default_congestion_control::LINK_STATE_LIST,
+            ZenohBody::QueryCancel(_) => zmsg::default_congestion_control::QUERY_CANCEL,
         };
 
         cc == CongestionControl::Drop
@@ -1418,7 +1502,7 @@ impl fmt::Display for Hello {
 /// +-+-+-+-+-+-+-+-+
 /// |O|S|A|   INIT  |
 /// +-+-+-+-+-------+
-/// ~             |Q~ if O==1
+/// ~           |C|Q~ if O==1
 /// +---------------+
 /// | v_maj | v_min | if A==0 -- Protocol Version VMaj.VMin
 /// +-------+-------+
@@ -1435,6 +1519,7 @@ impl fmt::Display for Hello {
 ///     if A==1 and S==0 then the agreed resolution is the one communicated by the initiator.
 ///
 /// - if Q==1 then the initiator/responder support QoS.
+/// - if C==1 then the initiator/responder support batch compression.
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct InitSyn {
@@ -1443,6 +1528,7 @@ pub struct InitSyn {
     pub pid: PeerId,
     pub sn_resolution: ZInt,
     pub is_qos: bool,
+    pub is_compression: bool,
 }
 
 impl Header for InitSyn {
@@ -1465,11 +1551,14 @@ impl Options for InitSyn {
         if self.is_qos {
             options |= tmsg::init_options::QOS;
         }
+        if self.is_compression {
+            options |= tmsg::init_options::COMPRESSION;
+        }
         options
     }
 
     fn has_options(&self) -> bool {
-        self.is_qos
+        self.is_qos || self.is_compression
     }
 }
 
@@ -1479,6 +1568,7 @@ pub struct InitAck {
     pub pid: PeerId,
     pub sn_resolution: Option<ZInt>,
     pub is_qos: bool,
+    pub is_compression: bool,
     pub cookie: ZSlice,
 }
 
@@ -1503,11 +1593,14 @@ impl Options for InitAck {
         if self.is_qos {
             options |= tmsg::init_options::QOS;
         }
+        if self.is_compression {
+            options |= tmsg::init_options::COMPRESSION;
+        }
         options
     }
 
     fn has_options(&self) -> bool {
-        self.is_qos
+        self.is_qos || self.is_compression
     }
 }
 
@@ -1589,7 +1682,7 @@ impl Header for OpenAck {
 /// +-+-+-+-+-+-+-+-+
 /// |O|S|T|   JOIN  |
 /// +-+-+-+-+-------+
-/// ~             |Q~ if O==1
+/// ~           |C|Q~ if O==1
 /// +---------------+
 /// | v_maj | v_min | -- Protocol Version VMaj.VMin
 /// +-------+-------+
@@ -1605,6 +1698,8 @@ impl Header for OpenAck {
 /// +---------------+
 ///
 /// - if Q==1 then the sender supports QoS.
+/// - if C==1 then the batches of the sender are preceded by a compression header, except its
+///   JOIN messages that are always sent raw.
 ///
 /// (*)   if T==1 then the lease period is expressed in seconds, otherwise in milliseconds
 /// (**)  if S==0 then 2^28 is assumed.
@@ -1620,6 +1715,7 @@ pub struct Join {
     pub lease: Duration,
     pub sn_resolution: ZInt,
     pub next_sns: ConduitSnList,
+    pub is_compression: bool,
 }
 
 impl Join {
@@ -1654,6 +1750,9 @@ impl Options for Join {
         if self.is_qos() {
             options |= tmsg::join_options::QOS;
         }
+        if self.is_compression {
+            options |= tmsg::join_options::COMPRESSION;
+        }
         options
     }
 
@@ -1722,7 +1821,8 @@ impl Header for Close {
 /// The SYNC message allows to signal the corresponding peer the sequence number of the next message
 /// to be transmitted on the reliable or best-effort channel. In the case of reliable channel, the
 /// peer can optionally include the number of unacknowledged messages. A SYNC sent on the reliable
-/// channel triggers the transmission of an ACKNACK message.
+/// channel triggers the transmission of an ACKNACK message. Like a FRAME, a SYNC concerning a
+/// non-default priority is preceded by a PRIORITY decorator.
 ///
 ///  7 6 5 4 3 2 1 0
 /// +-+-+-+-+-+-+-+-+
@@ -1737,7 +1837,7 @@ impl Header for Close {
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct Sync {
-    pub reliability: Reliability,
+    pub channel: Channel,
     pub sn: ZInt,
     pub count: Option<ZInt>,
 }
@@ -1746,7 +1846,7 @@ impl Header for Sync {
     #[inline(always)]
     fn header(&self) -> u8 {
         let mut header = tmsg::id::SYNC;
-        if let Reliability::Reliable = self.reliability {
+        if let Reliability::Reliable = self.channel.reliability {
             header |= tmsg::flag::R;
         }
         if self.count.is_some() {
@@ -1766,19 +1866,21 @@ impl Header for Sync {
 ///       In any case, the length of a message must not exceed 65_535 bytes.
 ///
 /// The ACKNACK messages is used on the reliable channel to signal the corresponding peer the last
-/// sequence number received and optionally a bitmask of the non-received messages.
+/// sequence number received and optionally a bitmask of the non-received messages. Like a FRAME,
+/// an ACKNACK concerning a non-default priority is preceded by a PRIORITY decorator.
 ///
 ///  7 6 5 4 3 2 1 0
 /// +-+-+-+-+-+-+-+-+
 /// |X|X|M| ACKNACK |
 /// +-+-+-+-+-------+
-/// ~      sn       ~
+/// ~      sn       ~ -- Sequence number of the next message expected on this channel.
 /// +---------------+
-/// ~     mask      ~ if M==1
+/// ~     mask      ~ if M==1 -- Bit i is set if the message with sequence number sn+i is missing.
 /// +---------------+
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct AckNack {
+    pub priority: Priority,
     pub sn: ZInt,
     pub mask: Option<ZInt>,
 }
@@ -2039,6 +2141,7 @@ impl TransportMessage {
         pid: PeerId,
         sn_resolution: ZInt,
         is_qos: bool,
+        is_compression: bool,
         attachment: Option<Attachment>,
     ) -> TransportMessage {
         TransportMessage {
@@ -2048,6 +2151,7 @@ impl TransportMessage {
                 pid,
                 sn_resolution,
                 is_qos,
+                is_compression,
             }),
             attachment,
             #[cfg(feature = "stats")]
@@ -2060,6 +2164,7 @@ impl TransportMessage {
         pid: PeerId,
         sn_resolution: Option<ZInt>,
         is_qos: bool,
+        is_compression: bool,
         cookie: ZSlice,
         attachment: Option<Attachment>,
     ) -> TransportMessage {
@@ -2069,6 +2174,7 @@ impl TransportMessage {
                 pid,
                 sn_resolution,
                 is_qos,
+                is_compression,
                 cookie,
             }),
             attachment,
@@ -2108,6 +2214,7 @@ impl TransportMessage {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn make_join(
         version: u8,
         whatami: WhatAmI,
@@ -2115,6 +2222,7 @@ impl TransportMessage {
         lease: Duration,
         sn_resolution: ZInt,
         next_sns: ConduitSnList,
+        is_compression: bool,
         attachment: Option<Attachment>,
     ) -> TransportMessage {
         TransportMessage {
@@ -2125,6 +2233,7 @@ impl TransportMessage {
                 lease,
                 sn_resolution,
                 next_sns,
+                is_compression,
             }),
             attachment,
             #[cfg(feature = "stats")]
@@ -2151,17 +2260,13 @@ impl TransportMessage {
     }
 
     pub fn make_sync(
-        reliability: Reliability,
+        channel: Channel,
         sn: ZInt,
         count: Option<ZInt>,
         attachment: Option<Attachment>,
     ) -> TransportMessage {
         TransportMessage {
-            body: TransportBody::Sync(Sync {
-                reliability,
-                sn,
-                count,
-            }),
+            body: TransportBody::Sync(Sync { channel, sn, count }),
             attachment,
             #[cfg(feature = "stats")]
             size: None,
@@ -2169,12 +2274,13 @@ impl TransportMessage {
     }
 
     pub fn make_ack_nack(
+        priority: Priority,
         sn: ZInt,
         mask: Option<ZInt>,
         attachment: Option<Attachment>,
     ) -> TransportMessage {
         TransportMessage {
-            body: TransportBody::AckNack(AckNack { sn, mask }),
+            body: TransportBody::AckNack(AckNack { priority, sn, mask }),
             attachment,
             #[cfg(feature = "stats")]
             size: None,
@@ -87,8 +87,8 @@ impl ZBuf {
                 }
                 JOIN => break self.read_join(header)?,
                 CLOSE => break self.read_close(header)?,
-                SYNC => break self.read_sync(header)?,
-                ACK_NACK => break self.read_ack_nack(header)?,
+                SYNC => break self.read_sync(header, priority)?,
+                ACK_NACK => break self.read_ack_nack(header, priority)?,
                 KEEP_ALIVE => break self.read_keep_alive(header)?,
                 PING_PONG => {
                     if imsg::has_flag(header, tmsg::flag::P) {
@@ -206,6 +206,7 @@ impl ZBuf {
             SEQ_NUM_RES
         };
         let is_qos = imsg::has_option(options, tmsg::init_options::QOS);
+        let is_compression = imsg::has_option(options, tmsg::init_options::COMPRESSION);
 
         Some(TransportBody::InitSyn(InitSyn {
             version,
@@ -213,6 +214,7 @@ impl ZBuf {
             pid,
             sn_resolution,
             is_qos,
+            is_compression,
         }))
     }
 
@@ -230,6 +232,7 @@ impl ZBuf {
             None
         };
         let is_qos = imsg::has_option(options, tmsg::init_options::QOS);
+        let is_compression = imsg::has_option(options, tmsg::init_options::COMPRESSION);
         let cookie = self.read_zslice_array()?;
 
         Some(TransportBody::InitAck(InitAck {
@@ -237,6 +240,7 @@ impl ZBuf {
             pid,
             sn_resolution,
             is_qos,
+            is_compression,
             cookie,
         }))
     }
@@ -291,6 +295,7 @@ impl ZBuf {
             SEQ_NUM_RES
         };
         let is_qos = imsg::has_option(options, tmsg::join_options::QOS);
+        let is_compression = imsg::has_option(options, tmsg::join_options::COMPRESSION);
         let next_sns = if is_qos {
             let mut sns = Box::new([ConduitSn::default(); Priority::NUM]);
             for i in 0..Priority::NUM {
@@ -312,6 +317,7 @@ impl ZBuf {
             lease,
             sn_resolution,
             next_sns,
+            is_compression,
         }))
     }
 
@@ -331,11 +337,15 @@ impl ZBuf {
         }))
     }
 
-    fn read_sync(&mut self, header: u8) -> Option<TransportBody> {
+    fn read_sync(&mut self, header: u8, priority: Priority) -> Option<TransportBody> {
         let reliability = match imsg::has_flag(header, tmsg::flag::R) {
             true => Reliability::Reliable,
             false => Reliability::BestEffort,
         };
+        let channel = Channel {
+            priority,
+            reliability,
+        };
         let sn = self.read_zint()?;
         let count = if imsg::has_flag(header, tmsg::flag::C) {
             Some(self.read_zint()?)
@@ -343,14 +353,10 @@ impl ZBuf {
             None
         };
 
-        Some(TransportBody::Sync(Sync {
-            reliability,
-            sn,
-            count,
-        }))
+        Some(TransportBody::Sync(Sync { channel, sn, count }))
     }
 
-    fn read_ack_nack(&mut self, header: u8) -> Option<TransportBody> {
+    fn read_ack_nack(&mut self, header: u8, priority: Priority) -> Option<TransportBody> {
         let sn = self.read_zint()?;
         let mask = if imsg::has_flag(header, tmsg::flag::M) {
             Some(self.read_zint()?)
@@ -358,7 +364,7 @@ impl ZBuf {
             None
         };
 
-        Some(TransportBody::AckNack(AckNack { sn, mask }))
+        Some(TransportBody::AckNack(AckNack { priority, sn, mask }))
     }
 
     fn read_keep_alive(&mut self, header: u8) -> Option<TransportBody> {
@@ -403,7 +409,13 @@ impl ZBuf {
             })
         };
 
-        Some(ReplyContext { qid, replier })
+        let is_error = imsg::has_flag(header, zmsg::flag::E);
+
+        Some(ReplyContext {
+            qid,
+            replier,
+            is_error,
+        })
     }
 
     pub fn read_zenoh_message(&mut self, reliability: Reliability) -> Option<ZenohMessage> {
@@ -447,6 +459,7 @@ impl ZBuf {
                 PULL => break self.read_pull(header)?,
                 QUERY => break self.read_query(header)?,
                 LINK_STATE_LIST => break self.read_link_state_list(header)?,
+                QUERY_CANCEL => break self.read_query_cancel(header)?,
                 unknown => {
                     log::trace!("Zenoh message with unknown ID: {}", unknown);
                     return None;
@@ -588,6 +601,11 @@ impl ZBuf {
         }))
     }
 
+    fn read_query_cancel(&mut self, _header: u8) -> Option<ZenohBody> {
+        let qid = self.read_zint()?;
+        Some(ZenohBody::QueryCancel(QueryCancel { qid }))
+    }
+
     fn read_declare(&mut self, _header: u8) -> Option<ZenohBody> {
         let declarations = self.read_declarations()?;
         Some(ZenohBody::Declare(Declare { declarations }))
@@ -245,6 +245,10 @@ impl WBuf {
     }
 
     fn write_sync(&mut self, sync: &Sync) -> bool {
+        if sync.channel.priority != Priority::default() {
+            zcheck!(self.write_deco_priority(sync.channel.priority))
+        }
+
         zcheck!(self.write(sync.header()));
         zcheck!(self.write_zint(sync.sn));
         if let Some(c) = sync.count {
@@ -254,6 +258,10 @@ impl WBuf {
     }
 
     fn write_ack_nack(&mut self, ack_nack: &AckNack) -> bool {
+        if ack_nack.priority != Priority::default() {
+            zcheck!(self.write_deco_priority(ack_nack.priority))
+        }
+
         zcheck!(self.write(ack_nack.header()));
         zcheck!(self.write_zint(ack_nack.sn));
         if let Some(m) = ack_nack.mask {
@@ -307,6 +315,7 @@ impl WBuf {
             ZenohBody::LinkStateList(link_state_list) => {
                 self.write_link_state_list(link_state_list)
             }
+            ZenohBody::QueryCancel(query_cancel) => self.write_query_cancel(query_cancel),
         };
 
         #[cfg(feature = "stats")]
@@ -480,6 +489,11 @@ impl WBuf {
         self.write_consolidation(&query.consolidation)
     }
 
+    fn write_query_cancel(&mut self, query_cancel: &QueryCancel) -> bool {
+        zcheck!(self.write(query_cancel.header()));
+        self.write_zint(query_cancel.qid)
+    }
+
     fn write_link_state_list(&mut self, link_state_list: &LinkStateList) -> bool {
         zcheck!(self.write(link_state_list.header()));
         zcheck!(self.write_usize_as_zint(link_state_list.link_states.len()));
@@ -18,7 +18,7 @@ use super::protocol::core::{
 use super::protocol::io::ZBuf;
 use super::protocol::proto::{DataInfo, RoutingContext};
 use super::router::*;
-use super::transport::Primitives;
+use super::transport::{Primitives, TransportMulticast};
 use async_std::sync::Arc;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
@@ -30,6 +30,7 @@ pub struct FaceState {
     pub(super) whatami: WhatAmI,
     pub(super) primitives: Arc<dyn Primitives + Send + Sync>,
     pub(super) link_id: usize,
+    pub(super) mcast_group: Option<TransportMulticast>,
     pub(super) local_mappings: HashMap<ZInt, Arc<Resource>>,
     pub(super) remote_mappings: HashMap<ZInt, Arc<Resource>>,
     pub(super) local_subs: HashSet<Arc<Resource>>,
@@ -38,6 +39,7 @@ pub struct FaceState {
     pub(super) remote_qabls: HashSet<Arc<Resource>>,
     pub(super) next_qid: ZInt,
     pub(super) pending_queries: HashMap<ZInt, Arc<Query>>,
+    pub(super) matching_listeners: HashMap<usize, MatchingContext>,
 }
 
 impl FaceState {
@@ -47,6 +49,7 @@ impl FaceState {
         whatami: WhatAmI,
         primitives: Arc<dyn Primitives + Send + Sync>,
         link_id: usize,
+        mcast_group: Option<TransportMulticast>,
     ) -> Arc<FaceState> {
         Arc::new(FaceState {
             id,
@@ -54,6 +57,7 @@ impl FaceState {
             whatami,
             primitives,
             link_id,
+            mcast_group,
             local_mappings: HashMap::new(),
             remote_mappings: HashMap::new(),
             local_subs: HashSet::new(),
@@ -62,6 +66,7 @@ impl FaceState {
             remote_qabls: HashSet::new(),
             next_qid: 0,
             pending_queries: HashMap::new(),
+            matching_listeners: HashMap::new(),
         })
     }
 
@@ -74,6 +79,13 @@ impl FaceState {
         }
     }
 
+    /// Returns true if both faces belong to the same multicast group, in which case
+    /// messages received from one of them must not be sent back to the other.
+    #[inline]
+    pub(super) fn same_mcast_group(&self, other: &FaceState) -> bool {
+        self.mcast_group.is_some() && self.mcast_group == other.mcast_group
+    }
+
     pub(super) fn get_next_local_id(&self) -> ZInt {
         let mut id = 1;
         while self.local_mappings.get(&id).is_some() || self.remote_mappings.get(&id).is_some() {
@@ -464,6 +476,7 @@ impl Primitives for Face {
         let mut tables = zwrite!(self.tables);
         route_query(
             &mut tables,
+            &self.tables,
             &self.state,
             prefixid,
             suffix,
@@ -497,9 +510,36 @@ impl Primitives for Face {
         );
     }
 
-    fn send_reply_final(&self, qid: ZInt) {
+    fn send_reply_error(
+        &self,
+        qid: ZInt,
+        replier_kind: ZInt,
+        replier_id: PeerId,
+        reskey: ResKey,
+        info: Option<DataInfo>,
+        payload: ZBuf,
+    ) {
+        let mut tables = zwrite!(self.tables);
+        route_send_reply_error(
+            &mut tables,
+            &mut self.state.clone(),
+            qid,
+            replier_kind,
+            replier_id,
+            reskey,
+            info,
+            payload,
+        );
+    }
+
+    fn send_reply_final(&self, qid: ZInt, is_partial: bool) {
+        let mut tables = zwrite!(self.tables);
+        route_send_reply_final(&mut tables, &mut self.state.clone(), qid, is_partial);
+    }
+
+    fn send_query_cancel(&self, qid: ZInt) {
         let mut tables = zwrite!(self.tables);
-        route_send_reply_final(&mut tables, &mut self.state.clone(), qid);
+        route_query_cancel(&mut tables, &self.state, qid);
     }
 
     fn send_pull(
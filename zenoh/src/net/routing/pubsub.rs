@@ -68,6 +68,7 @@ fn propagate_simple_subscription(
 ) {
     for dst_face in &mut tables.faces.values_mut() {
         if src_face.id != dst_face.id
+            && dst_face.mcast_group.is_none()
             && !dst_face.local_subs.contains(res)
             && match tables.whatami {
                 whatami::ROUTER => dst_face.whatami == whatami::CLIENT,
@@ -82,6 +83,25 @@ fn propagate_simple_subscription(
     }
 }
 
+fn propagate_mcast_subscription(
+    tables: &mut Tables,
+    res: &Arc<Resource>,
+    sub_info: &SubInfo,
+    src_face: &Arc<FaceState>,
+) {
+    for mcast_group in &mut tables.mcast_groups {
+        if !src_face.same_mcast_group(mcast_group) && !mcast_group.local_subs.contains(res) {
+            get_mut_unchecked(mcast_group)
+                .local_subs
+                .insert(res.clone());
+            let reskey = Resource::decl_key(res, mcast_group);
+            mcast_group
+                .primitives
+                .decl_subscriber(&reskey, sub_info, None);
+        }
+    }
+}
+
 fn propagate_sourced_subscription(
     tables: &Tables,
     res: &Arc<Resource>,
@@ -294,6 +314,7 @@ pub fn declare_client_subscription(
                     propagate_simple_subscription(tables, &res, sub_info, face);
                 }
             }
+            propagate_mcast_subscription(tables, &res, sub_info, face);
 
             compute_matches_data_routes(tables, &mut res);
         }
@@ -375,6 +396,22 @@ fn propagate_forget_simple_subscription(tables: &mut Tables, res: &Arc<Resource>
     }
 }
 
+fn propagate_forget_mcast_subscription(tables: &mut Tables, res: &Arc<Resource>) {
+    for mcast_group in &mut tables.mcast_groups {
+        if mcast_group.local_subs.contains(res)
+            && !res
+                .session_ctxs
+                .values()
+                .any(|ctx| ctx.subs.is_some() && !ctx.face.same_mcast_group(mcast_group))
+        {
+            let reskey = Resource::get_best_key(res, "", mcast_group.id);
+            mcast_group.primitives.forget_subscriber(&reskey, None);
+
+            get_mut_unchecked(mcast_group).local_subs.remove(res);
+        }
+    }
+}
+
 fn propagate_forget_sourced_subscription(
     tables: &Tables,
     res: &Arc<Resource>,
@@ -560,6 +597,7 @@ pub(crate) fn undeclare_client_subscription(
             get_mut_unchecked(face).local_subs.remove(res);
         }
     }
+    propagate_forget_mcast_subscription(tables, res);
 
     compute_matches_data_routes(tables, res);
     Resource::clean(res)
@@ -588,7 +626,10 @@ pub(crate) fn pubsub_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
         mode: SubMode::Push,
         period: None,
     };
-    if face.whatami == whatami::CLIENT && tables.whatami != whatami::CLIENT {
+    if face.whatami == whatami::CLIENT
+        && face.mcast_group.is_none()
+        && tables.whatami != whatami::CLIENT
+    {
         for sub in &tables.router_subs {
             get_mut_unchecked(face).local_subs.insert(sub.clone());
             let reskey = Resource::decl_key(sub, face);
@@ -609,6 +650,50 @@ pub(crate) fn pubsub_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
     }
 }
 
+pub(crate) fn pubsub_new_mcast_group(tables: &mut Tables, mcast_group: &mut Arc<FaceState>) {
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable, // @TODO
+        mode: SubMode::Push,
+        period: None,
+    };
+    for face in tables.faces.values() {
+        if !face.same_mcast_group(mcast_group) {
+            for sub in &face.remote_subs {
+                if !mcast_group.local_subs.contains(sub) {
+                    get_mut_unchecked(mcast_group)
+                        .local_subs
+                        .insert(sub.clone());
+                    let reskey = Resource::decl_key(sub, mcast_group);
+                    mcast_group
+                        .primitives
+                        .decl_subscriber(&reskey, &sub_info, None);
+                }
+            }
+        }
+    }
+}
+
+/// Declares again the resources and the subscriptions of a multicast group, for the peers
+/// that joined the group after they were declared or that lost their declarations.
+pub(crate) fn pubsub_redeclare_mcast_group(mcast_group: &FaceState) {
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable, // @TODO
+        mode: SubMode::Push,
+        period: None,
+    };
+    for (rid, res) in mcast_group.local_mappings.iter() {
+        mcast_group
+            .primitives
+            .decl_resource(*rid, &res.name().into());
+    }
+    for sub in mcast_group.local_subs.iter() {
+        let reskey = Resource::get_best_key(sub, "", mcast_group.id);
+        mcast_group
+            .primitives
+            .decl_subscriber(&reskey, &sub_info, None);
+    }
+}
+
 pub(crate) fn pubsub_remove_node(tables: &mut Tables, node: &PeerId, net_type: whatami::Type) {
     match net_type {
         whatami::ROUTER => {
@@ -699,6 +784,7 @@ pub(crate) fn pubsub_tree_change(
 
     // recompute routes
     compute_data_routes_from(tables, &mut tables.root_res.clone());
+    update_matching_listeners(tables);
 }
 
 #[inline]
@@ -820,10 +906,29 @@ fn compute_data_route(
             for (sid, context) in &mres.session_ctxs {
                 if let Some(subinfo) = &context.subs {
                     if subinfo.mode == SubMode::Push {
-                        route.entry(*sid).or_insert_with(|| {
-                            let reskey = Resource::get_best_key(prefix, suffix, *sid);
-                            (context.face.clone(), reskey, None)
-                        });
+                        match &context.face.mcast_group {
+                            // Subscribers reached through a multicast group are
+                            // served by a single transmission on that group.
+                            Some(transport) => {
+                                if let Some(mcast_group) = tables
+                                    .mcast_groups
+                                    .iter()
+                                    .find(|group| group.mcast_group.as_ref() == Some(transport))
+                                {
+                                    route.entry(mcast_group.id).or_insert_with(|| {
+                                        let reskey =
+                                            Resource::get_best_key(prefix, suffix, mcast_group.id);
+                                        (mcast_group.clone(), reskey, None)
+                                    });
+                                }
+                            }
+                            None => {
+                                route.entry(*sid).or_insert_with(|| {
+                                    let reskey = Resource::get_best_key(prefix, suffix, *sid);
+                                    (context.face.clone(), reskey, None)
+                                });
+                            }
+                        }
                     }
                 }
             }
@@ -930,6 +1035,86 @@ pub(crate) fn compute_matches_data_routes(tables: &mut Tables, res: &mut Arc<Res
                 compute_data_routes(tables, &mut match_.upgrade().unwrap());
             }
         }
+
+        update_matching_listeners(tables);
+    }
+}
+
+/// The state of a listener of the matching status of the publications of a face.
+pub(crate) struct MatchingContext {
+    pub(crate) resname: String,
+    pub(crate) local: bool,
+    pub(crate) matching: bool,
+    pub(crate) handler: Box<dyn Fn(bool) + Send + Sync>,
+}
+
+/// Returns true if the data published by `face` on `resname` would reach at least one subscriber.
+/// If `local` is true, the subscribers of `face` itself are taken into account.
+pub(crate) fn is_matching(
+    tables: &Tables,
+    face: &Arc<FaceState>,
+    resname: &str,
+    local: bool,
+) -> bool {
+    let res = Resource::get_resource(&tables.root_res, resname);
+    let route = get_data_route(tables, face, &res, &tables.root_res, resname, None);
+    route
+        .values()
+        .any(|(outface, _, _)| local || outface.id != face.id)
+        || !get_matching_pulls(tables, &res, &tables.root_res, resname).is_empty()
+}
+
+/// Registers a listener of the matching status of the publications of `face` on `resname`.
+/// `handler` is called with the new status each time it changes.
+pub(crate) fn declare_matching_listener(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    id: usize,
+    resname: String,
+    local: bool,
+    handler: Box<dyn Fn(bool) + Send + Sync>,
+) {
+    log::debug!("Declare matching listener {} for {}", resname, face);
+    let matching = is_matching(tables, face, &resname, local);
+    get_mut_unchecked(face).matching_listeners.insert(
+        id,
+        MatchingContext {
+            resname,
+            local,
+            matching,
+            handler,
+        },
+    );
+}
+
+pub(crate) fn undeclare_matching_listener(face: &mut Arc<FaceState>, id: usize) {
+    if let Some(ctx) = get_mut_unchecked(face).matching_listeners.remove(&id) {
+        log::debug!("Undeclare matching listener {} for {}", ctx.resname, face);
+    }
+}
+
+fn update_matching_listeners(tables: &Tables) {
+    for face in tables.faces.values() {
+        let changes = face
+            .matching_listeners
+            .iter()
+            .filter_map(|(id, ctx)| {
+                let matching = is_matching(tables, face, &ctx.resname, ctx.local);
+                if matching != ctx.matching {
+                    Some((*id, matching))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<(usize, bool)>>();
+        for (id, matching) in changes {
+            let mut face = face.clone();
+            if let Some(ctx) = get_mut_unchecked(&mut face).matching_listeners.get_mut(&id) {
+                log::trace!("Matching status of {}: {}", ctx.resname, matching);
+                ctx.matching = matching;
+                (ctx.handler)(matching);
+            }
+        }
     }
 }
 
@@ -1074,7 +1259,7 @@ fn get_matching_pulls(
 macro_rules! send_to_first {
     ($route:expr, $srcface:expr, $payload:expr, $channel:expr, $cong_ctrl:expr, $data_info:expr) => {
         let (outface, reskey, context) = $route.values().next().unwrap();
-        if $srcface.id != outface.id {
+        if $srcface.id != outface.id && !$srcface.same_mcast_group(outface) {
             outface
                 .primitives
                 .send_data(
@@ -1092,7 +1277,7 @@ macro_rules! send_to_first {
 macro_rules! send_to_all {
     ($route:expr, $srcface:expr, $payload:expr, $channel:expr, $cong_ctrl:expr, $data_info:expr) => {
         for (outface, reskey, context) in $route.values() {
-            if $srcface.id != outface.id {
+            if $srcface.id != outface.id && !$srcface.same_mcast_group(outface) {
                 outface
                     .primitives
                     .send_data(
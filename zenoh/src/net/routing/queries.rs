@@ -11,17 +11,21 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
-use async_std::sync::Arc;
+use async_std::sync::{Arc, Weak};
 use petgraph::graph::NodeIndex;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
 use zenoh_util::sync::get_mut_unchecked;
 
 use super::protocol::core::{
-    queryable, whatami, PeerId, QueryConsolidation, QueryTarget, ResKey, ZInt,
+    queryable, rname, whatami, Channel, CongestionControl, PeerId, Priority, QueryConsolidation,
+    QueryTarget, Reliability, ResKey, ZInt,
 };
 use super::protocol::io::ZBuf;
-use super::protocol::proto::{DataInfo, RoutingContext};
+use super::protocol::proto::{data_kind, DataInfo, RoutingContext};
 
 use super::face::FaceState;
 use super::network::Network;
@@ -31,6 +35,8 @@ use super::router::Tables;
 pub(crate) struct Query {
     src_face: Arc<FaceState>,
     src_qid: ZInt,
+    // Set when the replies of a face the query was propagated to will not all be received
+    partial: AtomicBool,
 }
 
 fn local_router_qabl_kind(tables: &Tables, res: &Arc<Resource>) -> ZInt {
@@ -138,7 +144,9 @@ fn propagate_simple_queryable(
     for dst_face in &mut tables.faces.values_mut() {
         let kind = local_qabl_kind(whatami, &pid, res, dst_face);
         // let current_qabl = dst_face.local_qabls.get(res);
+        // Queries are not routed over multicast groups
         if (src_face.is_none() || src_face.as_ref().unwrap().id != dst_face.id)
+            && dst_face.mcast_group.is_none()
             // && (current_qabl.is_none() || *current_qabl.unwrap() != kind) // @TODO
             && match tables.whatami {
                 whatami::ROUTER => dst_face.whatami == whatami::CLIENT,
@@ -246,6 +254,7 @@ pub fn declare_router_queryable(
             register_router_queryable(tables, Some(face), &mut res, kind, router);
 
             compute_matches_query_routes(tables, &mut res);
+            update_liveliness(tables, &mut res);
         }
         None => log::error!("Declare router queryable for unknown rid {}!", prefixid),
     }
@@ -307,6 +316,7 @@ pub fn declare_peer_queryable(
             }
 
             compute_matches_query_routes(tables, &mut res);
+            update_liveliness(tables, &mut res);
         }
         None => log::error!("Declare router queryable for unknown rid {}!", prefixid),
     }
@@ -376,6 +386,7 @@ pub fn declare_client_queryable(
             }
 
             compute_matches_query_routes(tables, &mut res);
+            update_liveliness(tables, &mut res);
         }
         None => log::error!("Declare queryable for unknown rid {}!", prefixid),
     }
@@ -535,6 +546,7 @@ pub fn forget_router_queryable(
                 undeclare_router_queryable(tables, Some(face), &mut res, router);
 
                 compute_matches_query_routes(tables, &mut res);
+                update_liveliness(tables, &mut res);
                 Resource::clean(&mut res)
             }
             None => log::error!("Undeclare unknown router queryable!"),
@@ -588,6 +600,7 @@ pub fn forget_peer_queryable(
                 }
 
                 compute_matches_query_routes(tables, &mut res);
+                update_liveliness(tables, &mut res);
                 Resource::clean(&mut res)
             }
             None => log::error!("Undeclare unknown peer queryable!"),
@@ -650,6 +663,7 @@ pub(crate) fn undeclare_client_queryable(
     }
 
     compute_matches_query_routes(tables, res);
+    update_liveliness(tables, res);
     Resource::clean(res)
 }
 
@@ -671,7 +685,10 @@ pub fn forget_client_queryable(
 }
 
 pub(crate) fn queries_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
-    if face.whatami == whatami::CLIENT && tables.whatami != whatami::CLIENT {
+    if face.whatami == whatami::CLIENT
+        && face.mcast_group.is_none()
+        && tables.whatami != whatami::CLIENT
+    {
         for qabl in &tables.router_qabls {
             let kind = local_qabl_kind(tables.whatami, &tables.pid.clone(), qabl, face);
             get_mut_unchecked(face)
@@ -708,6 +725,7 @@ pub(crate) fn queries_remove_node(tables: &mut Tables, node: &PeerId, net_type:
                 unregister_router_queryable(tables, &mut res, node);
 
                 compute_matches_query_routes(tables, &mut res);
+                update_liveliness(tables, &mut res);
                 Resource::clean(&mut res)
             }
         }
@@ -733,6 +751,7 @@ pub(crate) fn queries_remove_node(tables: &mut Tables, node: &PeerId, net_type:
                 }
 
                 compute_matches_query_routes(tables, &mut res);
+                update_liveliness(tables, &mut res);
                 Resource::clean(&mut res)
             }
         }
@@ -996,6 +1015,68 @@ fn compute_query_routes_from(tables: &mut Tables, res: &mut Arc<Resource>) {
     }
 }
 
+#[inline]
+fn is_liveliness_token(kind: &ZInt) -> bool {
+    kind & queryable::LIVELINESS != 0
+}
+
+#[inline]
+fn has_liveliness_tokens(res: &Arc<Resource>) -> bool {
+    res.context().router_qabls.values().any(is_liveliness_token)
+        || res.context().peer_qabls.values().any(is_liveliness_token)
+        || res
+            .session_ctxs
+            .values()
+            .any(|ctx| ctx.qabl.as_ref().map(is_liveliness_token).unwrap_or(false))
+}
+
+// Notifies the subscribers of the clients when the first liveliness token of a resource
+// appears (with a PUT) or when the last one disappears (with a DELETE).
+// Each router or peer notifies its own clients, so the clients tables don't.
+fn update_liveliness(tables: &mut Tables, res: &mut Arc<Resource>) {
+    if tables.whatami == whatami::CLIENT || res.context.is_none() {
+        return;
+    }
+    let alive = has_liveliness_tokens(res);
+    if alive == res.context().liveliness {
+        return;
+    }
+    get_mut_unchecked(res).context_mut().liveliness = alive;
+    log::debug!(
+        "Liveliness token {} {}",
+        res.name(),
+        if alive { "appeared" } else { "disappeared" }
+    );
+
+    let mut info = DataInfo::new();
+    if !alive {
+        info.kind = Some(data_kind::DELETE);
+    }
+    info.timestamp = tables.hlc.as_ref().map(|hlc| hlc.new_timestamp());
+    let name = res.name();
+    for face in tables.faces.values() {
+        if face.whatami == whatami::CLIENT
+            && face
+                .remote_subs
+                .iter()
+                .any(|sub| rname::matches(&sub.name(), &name))
+        {
+            let reskey = Resource::get_best_key(res, "", face.id);
+            face.primitives.send_data(
+                &reskey,
+                ZBuf::new(),
+                Channel {
+                    priority: Priority::default(),
+                    reliability: Reliability::Reliable,
+                },
+                CongestionControl::Block,
+                Some(info.clone()),
+                None,
+            );
+        }
+    }
+}
+
 pub(crate) fn compute_matches_query_routes(tables: &mut Tables, res: &mut Arc<Resource>) {
     if res.context.is_some() {
         compute_query_routes(tables, res);
@@ -1012,6 +1093,7 @@ pub(crate) fn compute_matches_query_routes(tables: &mut Tables, res: &mut Arc<Re
 #[allow(clippy::too_many_arguments)]
 pub fn route_query(
     tables: &mut Tables,
+    tables_ref: &Arc<RwLock<Tables>>,
     face: &Arc<FaceState>,
     rid: ZInt,
     suffix: &str,
@@ -1147,11 +1229,12 @@ pub fn route_query(
                 || (route.len() == 1 && route.iter().next().unwrap().1 .0.id == face.id)
             {
                 log::debug!("Send final reply {}:{} (no matching queryables)", face, qid);
-                face.primitives.clone().send_reply_final(qid)
+                face.primitives.clone().send_reply_final(qid, false)
             } else {
                 let query = Arc::new(Query {
                     src_face: face.clone(),
                     src_qid: qid,
+                    partial: AtomicBool::new(false),
                 });
 
                 for (outface, reskey, context) in route.values() {
@@ -1164,6 +1247,13 @@ pub fn route_query(
 
                         log::trace!("Propagate query {}:{} to {}", query.src_face, qid, outface);
 
+                        schedule_query_timeout(
+                            tables_ref.clone(),
+                            tables.queries_default_timeout,
+                            &outface,
+                            qid,
+                        );
+
                         outface.primitives.send_query(
                             reskey,
                             predicate,
@@ -1178,11 +1268,49 @@ pub fn route_query(
         }
         None => {
             log::error!("Route query with unknown rid {}! Send final reply.", rid);
-            face.primitives.clone().send_reply_final(qid)
+            face.primitives.clone().send_reply_final(qid, false)
         }
     }
 }
 
+// Stops waiting for the replies of the query `qid` propagated to `face` after `timeout`,
+// cancelling it on `face` and sending a partial final reply to the query source if no other
+// face is still expected to reply (the queries do not carry the timeout of their issuer,
+// so the router's default applies)
+fn schedule_query_timeout(
+    tables_ref: Arc<RwLock<Tables>>,
+    timeout: Duration,
+    face: &Arc<FaceState>,
+    qid: ZInt,
+) {
+    let face = Arc::downgrade(face);
+    async_std::task::spawn(async move {
+        async_std::task::sleep(timeout).await;
+        let _tables = zwrite!(tables_ref);
+        if let Some(mut face) = Weak::upgrade(&face) {
+            if let Some(query) = get_mut_unchecked(&mut face).pending_queries.remove(&qid) {
+                log::warn!(
+                    "Query {}:{} timed out after {:?} waiting for replies from {}",
+                    query.src_face,
+                    qid,
+                    timeout,
+                    face
+                );
+                query.partial.store(true, Ordering::Relaxed);
+                face.primitives.clone().send_query_cancel(qid);
+                if Arc::strong_count(&query) == 1 {
+                    log::debug!("Propagate partial final reply {}:{}", query.src_face, qid);
+                    query
+                        .src_face
+                        .primitives
+                        .clone()
+                        .send_reply_final(query.src_qid, true);
+                }
+            }
+        }
+    });
+}
+
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn route_send_reply_data(
     _tables: &mut Tables,
@@ -1205,30 +1333,91 @@ pub(crate) fn route_send_reply_data(
                 payload,
             );
         }
-        None => log::error!("Route reply for unknown query!"),
+        None => log::debug!(
+            "Route reply for unknown query {} (timed out or cancelled)",
+            qid
+        ),
     }
 }
 
-pub(crate) fn route_send_reply_final(_tables: &mut Tables, face: &mut Arc<FaceState>, qid: ZInt) {
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn route_send_reply_error(
+    _tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    qid: ZInt,
+    replier_kind: ZInt,
+    replier_id: PeerId,
+    reskey: ResKey,
+    info: Option<DataInfo>,
+    payload: ZBuf,
+) {
+    match face.pending_queries.get(&qid) {
+        Some(query) => {
+            query.src_face.primitives.clone().send_reply_error(
+                query.src_qid,
+                replier_kind,
+                replier_id,
+                reskey,
+                info,
+                payload,
+            );
+        }
+        None => log::debug!(
+            "Route reply for unknown query {} (timed out or cancelled)",
+            qid
+        ),
+    }
+}
+
+pub(crate) fn route_send_reply_final(
+    _tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    qid: ZInt,
+    is_partial: bool,
+) {
     match face.pending_queries.get(&qid) {
         Some(query) => {
             log::debug!(
-                "Received final reply {}:{} from {}",
+                "Received final reply {}:{} from {} (partial: {})",
                 query.src_face,
                 qid,
-                face
+                face,
+                is_partial
             );
+            if is_partial {
+                query.partial.store(true, Ordering::Relaxed);
+            }
             if Arc::strong_count(query) == 1 {
                 log::debug!("Propagate final reply {}:{}", query.src_face, qid);
                 query
                     .src_face
                     .primitives
                     .clone()
-                    .send_reply_final(query.src_qid);
+                    .send_reply_final(query.src_qid, query.partial.load(Ordering::Relaxed));
             }
             get_mut_unchecked(face).pending_queries.remove(&qid);
         }
-        None => log::error!("Route reply for unknown query!"),
+        None => log::debug!(
+            "Route reply for unknown query {} (timed out or cancelled)",
+            qid
+        ),
+    }
+}
+
+pub(crate) fn route_query_cancel(tables: &mut Tables, face: &Arc<FaceState>, qid: ZInt) {
+    log::debug!("Cancel query {}:{}", face, qid);
+    for outface in tables.faces.values_mut() {
+        let out_qids: Vec<ZInt> = outface
+            .pending_queries
+            .iter()
+            .filter(|(_, query)| query.src_face.id == face.id && query.src_qid == qid)
+            .map(|(out_qid, _)| *out_qid)
+            .collect();
+        for out_qid in out_qids {
+            get_mut_unchecked(outface).pending_queries.remove(&out_qid);
+            log::trace!("Propagate query cancel {}:{} to {}", face, out_qid, outface);
+            outface.primitives.clone().send_query_cancel(out_qid);
+        }
     }
 }
 
@@ -1240,13 +1429,18 @@ pub(crate) fn finalize_pending_queries(_tables: &mut Tables, face: &mut Arc<Face
             query.src_qid,
             face
         );
+        query.partial.store(true, Ordering::Relaxed);
         if Arc::strong_count(query) == 1 {
-            log::debug!("Propagate final reply {}:{}", query.src_face, query.src_qid);
+            log::debug!(
+                "Propagate partial final reply {}:{}",
+                query.src_face,
+                query.src_qid
+            );
             query
                 .src_face
                 .primitives
                 .clone()
-                .send_reply_final(query.src_qid);
+                .send_reply_final(query.src_qid, true);
         }
     }
     get_mut_unchecked(face).pending_queries.clear();
@@ -48,6 +48,7 @@ pub(super) struct ResourceContext {
     pub(super) routers_query_routes: Vec<Arc<Route>>,
     pub(super) peers_query_routes: Vec<Arc<Route>>,
     pub(super) client_query_route: Option<Arc<Route>>,
+    pub(super) liveliness: bool,
 }
 
 impl ResourceContext {
@@ -65,6 +66,7 @@ impl ResourceContext {
             routers_query_routes: Vec::new(),
             peers_query_routes: Vec::new(),
             client_query_route: None,
+            liveliness: false,
         }
     }
 }
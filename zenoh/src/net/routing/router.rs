@@ -19,15 +19,19 @@ pub use super::pubsub::*;
 pub use super::queries::*;
 pub use super::resource::*;
 use super::runtime::Runtime;
-use super::transport::{DeMux, Mux, Primitives, TransportPeerEventHandler, TransportUnicast};
+use super::transport::{
+    DeMux, Mux, Primitives, TransportMulticast, TransportPeerEventHandler, TransportUnicast,
+};
 use crate::net::link::Link;
 use async_std::sync::{Arc, Weak};
 use async_std::task::JoinHandle;
 use std::any::Any;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Mutex, RwLock};
+use std::time::Duration;
 use uhlc::HLC;
 use zenoh_util::core::ZResult;
+use zenoh_util::properties::config::ZN_QUERIES_DEFAULT_TIMEOUT_DEFAULT;
 use zenoh_util::sync::get_mut_unchecked;
 use zenoh_util::zconfigurable;
 
@@ -40,10 +44,10 @@ pub struct Tables {
     pub(crate) pid: PeerId,
     pub(crate) whatami: whatami::Type,
     face_counter: usize,
-    #[allow(dead_code)]
     pub(crate) hlc: Option<Arc<HLC>>,
     pub(crate) root_res: Arc<Resource>,
     pub(crate) faces: HashMap<usize, Arc<FaceState>>,
+    pub(crate) mcast_groups: Vec<Arc<FaceState>>,
     pub(crate) pull_caches_lock: Mutex<()>,
     pub(crate) router_subs: HashSet<Arc<Resource>>,
     pub(crate) peer_subs: HashSet<Arc<Resource>>,
@@ -54,6 +58,7 @@ pub struct Tables {
     pub(crate) shared_nodes: Vec<PeerId>,
     pub(crate) routers_trees_task: Option<JoinHandle<()>>,
     pub(crate) peers_trees_task: Option<JoinHandle<()>>,
+    pub(crate) queries_default_timeout: Duration,
 }
 
 impl Tables {
@@ -65,6 +70,7 @@ impl Tables {
             hlc,
             root_res: Resource::root(),
             faces: HashMap::new(),
+            mcast_groups: vec![],
             pull_caches_lock: Mutex::new(()),
             router_subs: HashSet::new(),
             peer_subs: HashSet::new(),
@@ -75,6 +81,9 @@ impl Tables {
             shared_nodes: vec![],
             routers_trees_task: None,
             peers_trees_task: None,
+            queries_default_timeout: Duration::from_millis(
+                ZN_QUERIES_DEFAULT_TIMEOUT_DEFAULT.parse().unwrap(),
+            ),
         }
     }
 
@@ -120,13 +129,16 @@ impl Tables {
         whatami: WhatAmI,
         primitives: Arc<dyn Primitives + Send + Sync>,
         link_id: usize,
+        mcast_group: Option<TransportMulticast>,
     ) -> Weak<FaceState> {
         let fid = self.face_counter;
         self.face_counter += 1;
         let mut newface = self
             .faces
             .entry(fid)
-            .or_insert_with(|| FaceState::new(fid, pid, whatami, primitives.clone(), link_id))
+            .or_insert_with(|| {
+                FaceState::new(fid, pid, whatami, primitives.clone(), link_id, mcast_group)
+            })
             .clone();
         log::debug!("New {}", newface);
 
@@ -142,7 +154,7 @@ impl Tables {
         whatami: WhatAmI,
         primitives: Arc<dyn Primitives + Send + Sync>,
     ) -> Weak<FaceState> {
-        self.open_net_face(pid, whatami, primitives, 0)
+        self.open_net_face(pid, whatami, primitives, 0, None)
     }
 
     pub fn close_face(&mut self, face: &Weak<FaceState>) {
@@ -242,10 +254,17 @@ pub struct Router {
 }
 
 impl Router {
-    pub fn new(pid: PeerId, whatami: whatami::Type, hlc: Option<Arc<HLC>>) -> Self {
+    pub fn new(
+        pid: PeerId,
+        whatami: whatami::Type,
+        hlc: Option<Arc<HLC>>,
+        queries_default_timeout: Duration,
+    ) -> Self {
+        let mut tables = Tables::new(pid, whatami, hlc);
+        tables.queries_default_timeout = queries_default_timeout;
         Router {
             whatami,
-            tables: Arc::new(RwLock::new(Tables::new(pid, whatami, hlc))),
+            tables: Arc::new(RwLock::new(tables)),
         }
     }
 
@@ -333,6 +352,7 @@ impl Router {
                         whatami,
                         Arc::new(Mux::new(transport)),
                         link_id,
+                        None,
                     )
                     .upgrade()
                     .unwrap(),
@@ -352,6 +372,96 @@ impl Router {
         }
         Ok(handler)
     }
+
+    pub fn new_transport_multicast(&self, transport: TransportMulticast) {
+        let mut tables = zwrite!(self.tables);
+        let fid = tables.face_counter;
+        tables.face_counter += 1;
+        let mut mcast_group = FaceState::new(
+            fid,
+            tables.pid,
+            whatami::PEER,
+            Arc::new(Mux::new(transport.clone())),
+            0,
+            Some(transport.clone()),
+        );
+        log::debug!("New multicast group {}", mcast_group);
+
+        pubsub_new_mcast_group(&mut tables, &mut mcast_group);
+        tables.mcast_groups.push(mcast_group);
+        drop(tables);
+
+        // The peers drop the messages of the group until they receive its Join, and the
+        // declarations might be lost: declare them again after each Join
+        let join_interval = match transport.get_join_interval() {
+            Ok(join_interval) => join_interval,
+            Err(_) => return,
+        };
+        let tables_ref = Arc::downgrade(&self.tables);
+        async_std::task::spawn(async move {
+            loop {
+                async_std::task::sleep(join_interval).await;
+                let tables_ref = match tables_ref.upgrade() {
+                    Some(tables_ref) => tables_ref,
+                    None => break,
+                };
+                let tables = zread!(tables_ref);
+                match tables
+                    .mcast_groups
+                    .iter()
+                    .find(|group| group.mcast_group.as_ref() == Some(&transport))
+                {
+                    Some(mcast_group) => pubsub_redeclare_mcast_group(mcast_group),
+                    None => break,
+                }
+            }
+        });
+    }
+
+    pub fn new_peer_multicast(
+        &self,
+        transport: TransportMulticast,
+        pid: PeerId,
+    ) -> Arc<DeMux<Face>> {
+        let mut tables = zwrite!(self.tables);
+        // Multicast peers don't take part in the link state protocol:
+        // they are routed as clients and reached through their multicast group.
+        let state = tables
+            .open_net_face(
+                pid,
+                whatami::CLIENT,
+                Arc::new(Mux::new(transport.clone())),
+                0,
+                Some(transport),
+            )
+            .upgrade()
+            .unwrap();
+        Arc::new(DeMux::new(Face {
+            tables: self.tables.clone(),
+            state,
+        }))
+    }
+
+    pub fn close_transport_multicast(&self, transport: &TransportMulticast) {
+        let mut tables = zwrite!(self.tables);
+        let faces = tables
+            .faces
+            .values()
+            .filter(|face| face.mcast_group.as_ref() == Some(transport))
+            .map(Arc::downgrade)
+            .collect::<Vec<Weak<FaceState>>>();
+        for face in faces {
+            tables.close_face(&face);
+        }
+        if let Some(idx) = tables
+            .mcast_groups
+            .iter()
+            .position(|group| group.mcast_group.as_ref() == Some(transport))
+        {
+            let mcast_group = tables.mcast_groups.remove(idx);
+            tables.close_face(&Arc::downgrade(&mcast_group));
+        }
+    }
 }
 
 pub struct LinkStateInterceptor {
@@ -217,7 +217,7 @@ impl Primitives for AdminSpace {
                 );
             }
 
-            primitives.send_reply_final(qid);
+            primitives.send_reply_final(qid, false);
         });
     }
 
@@ -241,8 +241,32 @@ impl Primitives for AdminSpace {
         );
     }
 
-    fn send_reply_final(&self, qid: ZInt) {
-        trace!("recv ReplyFinal {:?}", qid);
+    fn send_reply_error(
+        &self,
+        qid: ZInt,
+        replier_kind: ZInt,
+        replier_id: PeerId,
+        reskey: ResKey,
+        info: Option<DataInfo>,
+        payload: ZBuf,
+    ) {
+        trace!(
+            "recv ReplyError {:?} {:?} {:?} {:?} {:?} {:?}",
+            qid,
+            replier_kind,
+            replier_id,
+            reskey,
+            info,
+            payload
+        );
+    }
+
+    fn send_reply_final(&self, qid: ZInt, is_partial: bool) {
+        trace!("recv ReplyFinal {:?} {:?}", qid, is_partial);
+    }
+
+    fn send_query_cancel(&self, qid: ZInt) {
+        trace!("recv QueryCancel {:?}", qid);
     }
 
     fn send_pull(
@@ -31,11 +31,12 @@ use super::transport::{
 pub use adminspace::AdminSpace;
 use async_std::sync::Arc;
 use std::any::Any;
+use std::time::Duration;
 use uhlc::HLC;
 use zenoh_util::core::{ZError, ZErrorKind, ZResult};
 use zenoh_util::properties::config::*;
 use zenoh_util::sync::get_mut_unchecked;
-use zenoh_util::{zerror, zerror2};
+use zenoh_util::{zerror, zerror2, zparse};
 
 pub struct RuntimeState {
     pub pid: PeerId,
@@ -98,7 +99,17 @@ impl Runtime {
             None
         };
 
-        let router = Arc::new(Router::new(pid, whatami, hlc.clone()));
+        let queries_default_timeout = Duration::from_millis(zparse!(config.get_or(
+            &ZN_QUERIES_DEFAULT_TIMEOUT_KEY,
+            ZN_QUERIES_DEFAULT_TIMEOUT_DEFAULT
+        ))?);
+
+        let router = Arc::new(Router::new(
+            pid,
+            whatami,
+            hlc.clone(),
+            queries_default_timeout,
+        ));
 
         let handler = Arc::new(RuntimeTransportEventHandler {
             runtime: std::sync::RwLock::new(None),
@@ -199,10 +210,46 @@ impl TransportEventHandler for RuntimeTransportEventHandler {
 
     fn new_multicast(
         &self,
-        _transport: TransportMulticast,
+        transport: TransportMulticast,
     ) -> ZResult<Arc<dyn TransportMulticastEventHandler>> {
-        // @TODO
-        unimplemented!();
+        match zread!(self.runtime).as_ref() {
+            Some(runtime) => {
+                runtime.router.new_transport_multicast(transport.clone());
+                Ok(Arc::new(RuntimeMulticastGroup {
+                    runtime: runtime.clone(),
+                    transport,
+                }))
+            }
+            None => zerror!(ZErrorKind::Other {
+                descr: "Runtime not yet ready!".to_string()
+            }),
+        }
+    }
+}
+
+pub(super) struct RuntimeMulticastGroup {
+    pub(super) runtime: Runtime,
+    pub(super) transport: TransportMulticast,
+}
+
+impl TransportMulticastEventHandler for RuntimeMulticastGroup {
+    fn new_peer(&self, peer: TransportPeer) -> ZResult<Arc<dyn TransportPeerEventHandler>> {
+        Ok(self
+            .runtime
+            .router
+            .new_peer_multicast(self.transport.clone(), peer.pid))
+    }
+
+    fn closing(&self) {
+        self.runtime
+            .router
+            .close_transport_multicast(&self.transport);
+    }
+
+    fn closed(&self) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
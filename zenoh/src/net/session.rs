@@ -13,10 +13,11 @@
 //
 use super::info::*;
 use super::routing::face::Face;
+use super::routing::pubsub;
 use super::*;
 use async_std::sync::Arc;
 use async_std::task;
-use flume::{bounded, Sender};
+use flume::{bounded, unbounded, Sender};
 use log::{error, trace, warn};
 use protocol::{
     core::{
@@ -29,7 +30,7 @@ use protocol::{
 use runtime::Runtime;
 use std::collections::HashMap;
 use std::fmt;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::RwLock;
 use std::time::Duration;
 use transport::Primitives;
@@ -58,9 +59,12 @@ pub(crate) struct SessionState {
     local_subscribers: HashMap<Id, Arc<SubscriberState>>,
     queryables: HashMap<Id, Arc<QueryableState>>,
     queries: HashMap<ZInt, QueryState>,
+    // cancellation flags of the queries received from the router (false) or from this session (true)
+    received_queries: HashMap<(bool, ZInt), Arc<AtomicBool>>,
     local_routing: bool,
     join_subscriptions: Vec<String>,
     join_publications: Vec<String>,
+    queries_default_timeout: Duration,
 }
 
 impl SessionState {
@@ -68,6 +72,7 @@ impl SessionState {
         local_routing: bool,
         join_subscriptions: Vec<String>,
         join_publications: Vec<String>,
+        queries_default_timeout: Duration,
     ) -> SessionState {
         SessionState {
             primitives: None,
@@ -81,9 +86,11 @@ impl SessionState {
             local_subscribers: HashMap::new(),
             queryables: HashMap::new(),
             queries: HashMap::new(),
+            received_queries: HashMap::new(),
             local_routing,
             join_subscriptions,
             join_publications,
+            queries_default_timeout,
         }
     }
 }
@@ -250,10 +257,12 @@ impl Session {
         join_publications: Vec<String>,
     ) -> impl ZFuture<Output = Session> {
         let router = runtime.router.clone();
+        let queries_default_timeout = zread!(router.tables).queries_default_timeout;
         let state = Arc::new(RwLock::new(SessionState::new(
             local_routing,
             join_subscriptions,
             join_publications,
+            queries_default_timeout,
         )));
         let session = Session {
             runtime,
@@ -549,6 +558,62 @@ impl Session {
         })
     }
 
+    pub(crate) fn is_matching(&self, reskey: &ResKey) -> impl ZFuture<Output = ZResult<bool>> {
+        trace!("is_matching({:?})", reskey);
+        let state = zread!(self.state);
+        zready(state.localkey_to_resname(reskey).map(|resname| {
+            let face = state.primitives.as_ref().unwrap().clone();
+            let local = state.local_routing;
+            drop(state);
+            let tables = zread!(face.tables);
+            pubsub::is_matching(&tables, &face.state, &resname, local)
+        }))
+    }
+
+    pub(crate) fn declare_matching_listener(
+        &self,
+        reskey: &ResKey,
+    ) -> impl ZFuture<Output = ZResult<MatchingListener<'_>>> {
+        trace!("declare_matching_listener({:?})", reskey);
+        let state = zread!(self.state);
+        let id = state.decl_id_counter.fetch_add(1, Ordering::SeqCst);
+        zready(state.localkey_to_resname(reskey).map(|resname| {
+            let face = state.primitives.as_ref().unwrap().clone();
+            let local = state.local_routing;
+            drop(state);
+            // The routing tables notify the changes while locked: use an unbounded channel to never block them.
+            let (sender, receiver) = unbounded();
+            let mut tables = zwrite!(face.tables);
+            pubsub::declare_matching_listener(
+                &mut tables,
+                &mut face.state.clone(),
+                id,
+                resname,
+                local,
+                Box::new(move |matching| {
+                    let _ = sender.send(MatchingStatus { matching });
+                }),
+            );
+            MatchingListener {
+                session: self,
+                id,
+                alive: true,
+                receiver: MatchingStatusReceiver::new(receiver),
+            }
+        }))
+    }
+
+    pub(crate) fn undeclare_matching_listener(
+        &self,
+        id: usize,
+    ) -> impl ZFuture<Output = ZResult<()>> {
+        trace!("undeclare_matching_listener({})", id);
+        let face = zread!(self.state).primitives.as_ref().unwrap().clone();
+        let _tables = zwrite!(face.tables);
+        pubsub::undeclare_matching_listener(&mut face.state.clone(), id);
+        zready(Ok(()))
+    }
+
     fn declare_any_subscriber(
         &self,
         reskey: &ResKey,
@@ -917,6 +982,138 @@ impl Session {
         })
     }
 
+    // Returns the resource name of the liveliness tokens declared on a resource key
+    fn liveliness_resname(&self, resource: &ResKey) -> ZResult<String> {
+        let resname = zread!(self.state).localkey_to_resname(resource)?;
+        Ok([rname::LIVELINESS_PREFIX, &resname].concat())
+    }
+
+    /// Declare a [LivelinessToken](LivelinessToken) for the given resource key,
+    /// signaling to the other applications that this one is alive.
+    ///
+    /// The token is undeclared when dropped, or when this session closes or loses its connectivity:
+    /// the routers and peers then notify its disappearance to the
+    /// [liveliness subscribers](Session::declare_liveliness_subscriber).
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource key of the token
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::net::*;
+    ///
+    /// let session = open(config::peer()).await.unwrap();
+    /// let token = session.declare_liveliness_token(&"/group/member1".into()).await.unwrap();
+    /// # })
+    /// ```
+    pub fn declare_liveliness_token(
+        &self,
+        resource: &ResKey,
+    ) -> impl ZFuture<Output = ZResult<LivelinessToken<'_>>> {
+        trace!("declare_liveliness_token({:?})", resource);
+        zready_try!({
+            let res_name = self.liveliness_resname(resource)?;
+            let mut queryable = self
+                .declare_queryable(&res_name.as_str().into(), queryable::LIVELINESS)
+                .wait()?;
+            // reply to the liveliness queries until the token is undeclared
+            let mut receiver = queryable.receiver().clone();
+            task::spawn(async move {
+                while let Some(query) = receiver.next().await {
+                    query
+                        .reply_async(Sample {
+                            res_name: res_name.clone(),
+                            payload: ZBuf::new(),
+                            data_info: None,
+                        })
+                        .await;
+                }
+            });
+            Ok(LivelinessToken { queryable })
+        })
+    }
+
+    /// Declare a [Subscriber](Subscriber) to the liveliness tokens matching the given resource key.
+    ///
+    /// The subscriber receives a `PUT` [Sample](Sample) when the first token on a resource key appears, and a
+    /// `DELETE` [Sample](Sample) when the last one disappears. The resource names of those samples are
+    /// the keys of the tokens, prefixed with [`LIVELINESS_PREFIX`](utils::resource_name::LIVELINESS_PREFIX).
+    /// The tokens declared before the subscriber can be retrieved with [query_liveliness](Session::query_liveliness).
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource key of the tokens to subscribe to
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # async_std::task::block_on(async {
+    /// use zenoh::net::*;
+    /// use futures::prelude::*;
+    ///
+    /// let session = open(config::peer()).await.unwrap();
+    /// let mut subscriber = session.declare_liveliness_subscriber(&"/group/*".into()).await.unwrap();
+    /// while let Some(sample) = subscriber.receiver().next().await {
+    ///     let alive = sample.data_info.and_then(|info| info.kind) != Some(data_kind::DELETE);
+    ///     println!("{} alive: {}", sample.res_name, alive);
+    /// }
+    /// # })
+    /// ```
+    pub fn declare_liveliness_subscriber(
+        &self,
+        resource: &ResKey,
+    ) -> impl ZFuture<Output = ZResult<Subscriber<'_>>> {
+        trace!("declare_liveliness_subscriber({:?})", resource);
+        zready_try!({
+            let res_name = self.liveliness_resname(resource)?;
+            self.declare_subscriber(&res_name.as_str().into(), &SubInfo::default())
+                .wait()
+        })
+    }
+
+    /// Query the liveliness tokens currently declared on the resource keys matching the given one.
+    ///
+    /// A reply is received for each such token, its resource name being the key of the token
+    /// prefixed with [`LIVELINESS_PREFIX`](utils::resource_name::LIVELINESS_PREFIX).
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource key of the tokens to query
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::net::*;
+    /// use futures::prelude::*;
+    ///
+    /// let session = open(config::peer()).await.unwrap();
+    /// let mut replies = session.query_liveliness(&"/group/*".into()).await.unwrap();
+    /// while let Some(reply) = replies.next().await {
+    ///     println!(">> Alive: {}", reply.data.unwrap().res_name);
+    /// }
+    /// # })
+    /// ```
+    pub fn query_liveliness(
+        &self,
+        resource: &ResKey,
+    ) -> impl ZFuture<Output = ZResult<ReplyReceiver>> {
+        trace!("query_liveliness({:?})", resource);
+        zready_try!({
+            let res_name = self.liveliness_resname(resource)?;
+            self.query(
+                &res_name.as_str().into(),
+                "",
+                QueryTarget {
+                    kind: queryable::LIVELINESS,
+                    target: Target::All,
+                },
+                QueryConsolidation::none(),
+            )
+            .wait()
+        })
+    }
+
     /// Write data.
     ///
     /// # Arguments
@@ -1123,6 +1320,9 @@ impl Session {
 
     /// Query data from the matching queryables in the system.
     ///
+    /// The query times out after the `"queries_default_timeout"` configured for the session
+    /// (see [`query_ext`](Session::query_ext)).
+    ///
     /// # Arguments
     ///
     /// * `resource` - The resource key to query
@@ -1154,17 +1354,75 @@ impl Session {
         predicate: &str,
         target: QueryTarget,
         consolidation: QueryConsolidation,
+    ) -> impl ZFuture<Output = ZResult<ReplyReceiver>> {
+        let timeout = zread!(self.state).queries_default_timeout;
+        self.query_ext(resource, predicate, target, consolidation, timeout)
+    }
+
+    /// Query data from the matching queryables in the system, with a timeout.
+    ///
+    /// If all the replies have not been received after `timeout`, the query is finalized:
+    /// the replies received so far are delivered, the [`ReplyReceiver`] is closed,
+    /// its [`is_partial()`](ReplyReceiver::is_partial) method returns `true`
+    /// and the queryables still processing the query are notified of its cancellation.
+    ///
+    /// The timeout is not transmitted with the query: each router on the path stops waiting
+    /// for the replies after its own `"queries_default_timeout"` and then sends a partial
+    /// final reply. The effective timeout of a routed query is thus the minimum of `timeout`
+    /// and of the `"queries_default_timeout"` of the traversed routers, and a query finalized
+    /// by a router is reported as partial as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource key to query
+    /// * `predicate` - An indication to matching queryables about the queried data
+    /// * `target` - The kind of queryables that should be target of this query
+    /// * `consolidation` - The kind of consolidation that should be applied on replies
+    /// * `timeout` - The delay after which the query is finalized
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::net::*;
+    /// use futures::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let session = open(config::peer()).await.unwrap();
+    /// let mut replies = session.query_ext(
+    ///     &"/resource/name".into(),
+    ///     "predicate",
+    ///     QueryTarget::default(),
+    ///     QueryConsolidation::default(),
+    ///     Duration::from_secs(1),
+    /// ).await.unwrap();
+    /// while let Some(reply) = replies.next().await {
+    ///     println!(">> Received {:?}", reply.data);
+    /// }
+    /// if replies.is_partial() {
+    ///     println!(">> Query timed out");
+    /// }
+    /// # })
+    /// ```
+    pub fn query_ext(
+        &self,
+        resource: &ResKey,
+        predicate: &str,
+        target: QueryTarget,
+        consolidation: QueryConsolidation,
+        timeout: Duration,
     ) -> impl ZFuture<Output = ZResult<ReplyReceiver>> {
         trace!(
-            "query({:?}, {:?}, {:?}, {:?})",
+            "query_ext({:?}, {:?}, {:?}, {:?}, {:?})",
             resource,
             predicate,
             target,
-            consolidation
+            consolidation,
+            timeout
         );
         let mut state = zwrite!(self.state);
         let qid = state.qid_counter.fetch_add(1, Ordering::SeqCst);
         let (rep_sender, rep_receiver) = bounded(*API_REPLY_RECEPTION_CHANNEL_SIZE);
+        let partial = Arc::new(AtomicBool::new(false));
         let nb_final = if state.local_routing { 2 } else { 1 };
         trace!("Register query {} (nb_final = {})", qid, nb_final);
         state.queries.insert(
@@ -1178,9 +1436,30 @@ impl Session {
                     None
                 },
                 rep_sender,
+                partial: partial.clone(),
             },
         );
 
+        let weak_state = Arc::downgrade(&self.state);
+        task::spawn(async move {
+            task::sleep(timeout).await;
+            if let Some(state) = weak_state.upgrade() {
+                let mut state = zwrite!(state);
+                if let Some(query) = state.queries.remove(&qid) {
+                    warn!("Query {} timed out after {:?}", qid, timeout);
+                    query.partial.store(true, Ordering::Relaxed);
+                    finalize_query(qid, query);
+                    if state.local_routing {
+                        cancel_received_query(&mut state, true, qid);
+                    }
+                    if let Some(primitives) = state.primitives.clone() {
+                        drop(state);
+                        primitives.send_query_cancel(qid);
+                    }
+                }
+            }
+        });
+
         let primitives = state.primitives.as_ref().unwrap().clone();
         let local_routing = state.local_routing;
         drop(state);
@@ -1196,7 +1475,11 @@ impl Session {
             self.handle_query(true, resource, predicate, qid, target, consolidation);
         }
 
-        zready(Ok(ReplyReceiver::new(rep_receiver)))
+        let handle = Arc::new(QueryHandle {
+            qid,
+            state: Arc::downgrade(&self.state),
+        });
+        zready(Ok(ReplyReceiver::new(partial, handle, rep_receiver)))
     }
 
     fn handle_query(
@@ -1249,6 +1532,10 @@ impl Session {
 
         let predicate = predicate.to_string();
         let (rep_sender, rep_receiver) = bounded(*API_REPLY_EMISSION_CHANNEL_SIZE);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        zwrite!(self.state)
+            .received_queries
+            .insert((local, qid), cancelled.clone());
 
         let pid = self.runtime.pid; // @TODO build/use prebuilt specific pid
 
@@ -1259,6 +1546,7 @@ impl Session {
                 replies_sender: RepliesSender {
                     kind,
                     sender: rep_sender.clone(),
+                    cancelled: cancelled.clone(),
                 },
             });
         }
@@ -1266,34 +1554,74 @@ impl Session {
 
         // router is not re-entrant
 
+        // the replies of a cancelled query are not forwarded anymore
         if local {
             let this = self.clone();
             task::spawn(async move {
-                while let Some((kind, sample)) = rep_receiver.stream().next().await {
-                    this.send_reply_data(
-                        qid,
-                        kind,
-                        pid,
-                        ResKey::RName(sample.res_name),
-                        sample.data_info,
-                        sample.payload,
-                    );
+                while let Some((kind, reply)) = rep_receiver.stream().next().await {
+                    if cancelled.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    match reply {
+                        Ok(sample) => this.send_reply_data(
+                            qid,
+                            kind,
+                            pid,
+                            ResKey::RName(sample.res_name),
+                            sample.data_info,
+                            sample.payload,
+                        ),
+                        Err(error) => this.send_reply_error(
+                            qid,
+                            kind,
+                            pid,
+                            ResKey::RName(error.res_name),
+                            error.data_info,
+                            error.payload,
+                        ),
+                    }
+                }
+                if zwrite!(this.state)
+                    .received_queries
+                    .remove(&(true, qid))
+                    .is_some()
+                {
+                    this.send_reply_final(qid, false);
                 }
-                this.send_reply_final(qid);
             });
         } else {
+            let state = self.state.clone();
             task::spawn(async move {
-                while let Some((kind, sample)) = rep_receiver.stream().next().await {
-                    primitives.send_reply_data(
-                        qid,
-                        kind,
-                        pid,
-                        ResKey::RName(sample.res_name),
-                        sample.data_info,
-                        sample.payload,
-                    );
+                while let Some((kind, reply)) = rep_receiver.stream().next().await {
+                    if cancelled.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    match reply {
+                        Ok(sample) => primitives.send_reply_data(
+                            qid,
+                            kind,
+                            pid,
+                            ResKey::RName(sample.res_name),
+                            sample.data_info,
+                            sample.payload,
+                        ),
+                        Err(error) => primitives.send_reply_error(
+                            qid,
+                            kind,
+                            pid,
+                            ResKey::RName(error.res_name),
+                            error.data_info,
+                            error.payload,
+                        ),
+                    }
+                }
+                if zwrite!(state)
+                    .received_queries
+                    .remove(&(false, qid))
+                    .is_some()
+                {
+                    primitives.send_reply_final(qid, false);
                 }
-                primitives.send_reply_final(qid);
             });
         }
     }
@@ -1429,11 +1757,11 @@ impl Primitives for Session {
         match state.queries.get_mut(&qid) {
             Some(query) => {
                 let new_reply = Reply {
-                    data: Sample {
-                        res_name,
+                    data: Ok(Sample {
+                        res_name: res_name.clone(),
                         payload,
                         data_info,
-                    },
+                    }),
                     replier_kind,
                     replier_id,
                 };
@@ -1442,19 +1770,14 @@ impl Primitives for Session {
                         let _ = query.rep_sender.send(new_reply);
                     }
                     ConsolidationMode::Lazy => {
-                        match query
-                            .replies
-                            .as_ref()
-                            .unwrap()
-                            .get(&new_reply.data.res_name)
-                        {
+                        match query.replies.as_ref().unwrap().get(&res_name) {
                             Some(reply) => {
-                                if new_reply.data.data_info > reply.data.data_info {
+                                if is_newer(&new_reply, reply) {
                                     query
                                         .replies
                                         .as_mut()
                                         .unwrap()
-                                        .insert(new_reply.data.res_name.clone(), new_reply.clone());
+                                        .insert(res_name.clone(), new_reply.clone());
                                     let _ = query.rep_sender.send(new_reply);
                                 }
                             }
@@ -1463,25 +1786,20 @@ impl Primitives for Session {
                                     .replies
                                     .as_mut()
                                     .unwrap()
-                                    .insert(new_reply.data.res_name.clone(), new_reply.clone());
+                                    .insert(res_name.clone(), new_reply.clone());
                                 let _ = query.rep_sender.send(new_reply);
                             }
                         }
                     }
                     ConsolidationMode::Full => {
-                        match query
-                            .replies
-                            .as_ref()
-                            .unwrap()
-                            .get(&new_reply.data.res_name)
-                        {
+                        match query.replies.as_ref().unwrap().get(&res_name) {
                             Some(reply) => {
-                                if new_reply.data.data_info > reply.data.data_info {
+                                if is_newer(&new_reply, reply) {
                                     query
                                         .replies
                                         .as_mut()
                                         .unwrap()
-                                        .insert(new_reply.data.res_name.clone(), new_reply.clone());
+                                        .insert(res_name.clone(), new_reply.clone());
                                 }
                             }
                             None => {
@@ -1489,7 +1807,7 @@ impl Primitives for Session {
                                     .replies
                                     .as_mut()
                                     .unwrap()
-                                    .insert(new_reply.data.res_name.clone(), new_reply.clone());
+                                    .insert(res_name.clone(), new_reply.clone());
                             }
                         };
                     }
@@ -1501,20 +1819,63 @@ impl Primitives for Session {
         }
     }
 
-    fn send_reply_final(&self, qid: ZInt) {
-        trace!("recv ReplyFinal {:?}", qid);
+    fn send_reply_error(
+        &self,
+        qid: ZInt,
+        replier_kind: ZInt,
+        replier_id: PeerId,
+        reskey: ResKey,
+        data_info: Option<DataInfo>,
+        payload: ZBuf,
+    ) {
+        trace!(
+            "recv ReplyError {:?} {:?} {:?} {:?} {:?} {:?}",
+            qid,
+            replier_kind,
+            replier_id,
+            reskey,
+            data_info,
+            payload
+        );
+        let state = zread!(self.state);
+        let res_name = match state.remotekey_to_resname(&reskey) {
+            Ok(name) => name,
+            Err(e) => {
+                error!("Received ReplyError for unkown reskey: {}", e);
+                return;
+            }
+        };
+        match state.queries.get(&qid) {
+            // errors are not consolidated: they are delivered as soon as they are received
+            Some(query) => {
+                let _ = query.rep_sender.send(Reply {
+                    data: Err(ReplyError {
+                        res_name,
+                        payload,
+                        data_info,
+                    }),
+                    replier_kind,
+                    replier_id,
+                });
+            }
+            None => {
+                warn!("Received ReplyError for unkown Query: {}", qid);
+            }
+        }
+    }
+
+    fn send_reply_final(&self, qid: ZInt, is_partial: bool) {
+        trace!("recv ReplyFinal {:?} {:?}", qid, is_partial);
         let mut state = zwrite!(self.state);
         match state.queries.get_mut(&qid) {
             Some(mut query) => {
+                if is_partial {
+                    query.partial.store(true, Ordering::Relaxed);
+                }
                 query.nb_final -= 1;
                 if query.nb_final == 0 {
                     let query = state.queries.remove(&qid).unwrap();
-                    if query.reception_mode == ConsolidationMode::Full {
-                        for (_, reply) in query.replies.unwrap().into_iter() {
-                            let _ = query.rep_sender.send(reply);
-                        }
-                    }
-                    trace!("Close query {}", qid);
+                    finalize_query(qid, query);
                 }
             }
             None => {
@@ -1523,6 +1884,12 @@ impl Primitives for Session {
         }
     }
 
+    fn send_query_cancel(&self, qid: ZInt) {
+        trace!("recv QueryCancel {:?}", qid);
+        let mut state = zwrite!(self.state);
+        cancel_received_query(&mut state, false, qid);
+    }
+
     fn send_pull(
         &self,
         _is_final: bool,
@@ -1544,6 +1911,45 @@ impl Primitives for Session {
     }
 }
 
+// Cancels a query issued by this session, notifying the queryables that received it
+pub(crate) fn cancel_query(state: &RwLock<SessionState>, qid: ZInt) {
+    let mut state = zwrite!(state);
+    if state.queries.remove(&qid).is_some() {
+        trace!("Cancel query {}", qid);
+        if state.local_routing {
+            cancel_received_query(&mut state, true, qid);
+        }
+        let primitives = state.primitives.as_ref().unwrap().clone();
+        drop(state);
+        primitives.send_query_cancel(qid);
+    }
+}
+
+// Flags a query received by the queryables of this session as cancelled
+fn cancel_received_query(state: &mut SessionState, local: bool, qid: ZInt) {
+    if let Some(cancelled) = state.received_queries.remove(&(local, qid)) {
+        cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+// Returns true if `new_reply` carries a more recent sample than `reply`
+fn is_newer(new_reply: &Reply, reply: &Reply) -> bool {
+    match (&new_reply.data, &reply.data) {
+        (Ok(new_sample), Ok(sample)) => new_sample.data_info > sample.data_info,
+        _ => false,
+    }
+}
+
+// Delivers the replies retained for a fully consolidated query and closes its channel
+fn finalize_query(qid: ZInt, query: QueryState) {
+    if query.reception_mode == ConsolidationMode::Full {
+        for (_, reply) in query.replies.unwrap().into_iter() {
+            let _ = query.rep_sender.send(reply);
+        }
+    }
+    trace!("Close query {}", qid);
+}
+
 impl Drop for Session {
     fn drop(&mut self) {
         if self.alive {
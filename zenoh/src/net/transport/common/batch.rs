@@ -382,7 +382,8 @@ impl SerializationBatch {
         }
     }
 
-    #[cfg(test)]
+    /// Get a `&[u8]` to access the serialized messages, i.e. without the eventual 2 bytes of length.
+    #[inline(always)]
     pub(crate) fn get_serialized_messages(&self) -> &[u8] {
         if self.is_streamed() {
             self.buffer.get_first_slice(LENGTH_BYTES.len()..)
@@ -0,0 +1,191 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use super::batch::SerializationBatch;
+use super::protocol::io::ZSlice;
+use zenoh_util::collections::{RecyclingObject, RecyclingObjectPool};
+use zenoh_util::core::{ZError, ZErrorKind, ZResult};
+use zenoh_util::zerror;
+
+/// The number of bytes prepended to each batch when compression is enabled on a transport.
+/// The header indicates whether the serialized messages following it are compressed or not.
+pub(crate) const BATCH_HEADER_LEN: usize = 1;
+
+mod header {
+    pub(super) const RAW: u8 = 0;
+    pub(super) const LZ4: u8 = 1;
+}
+
+/// Prepare the bytes of a [`SerializationBatch`][SerializationBatch] for being transmitted on a
+/// link of a transport with compression enabled.
+///
+/// The serialized messages are compressed with LZ4 when their length exceeds `threshold` and the
+/// compression actually reduces their size, otherwise they are sent as they are. In both cases
+/// they are preceded by a header byte indicating the encoding and, in case the batch is streamed,
+/// by the 16 bits little endian length of the resulting batch.
+///
+/// The returned slice points into `buffer`, which is reused across batches.
+pub(crate) fn compress_batch<'a>(
+    batch: &SerializationBatch,
+    threshold: usize,
+    buffer: &'a mut Vec<u8>,
+) -> &'a [u8] {
+    let payload = batch.get_serialized_messages();
+
+    buffer.clear();
+    if batch.is_streamed() {
+        buffer.extend_from_slice(&[0u8, 0u8]);
+    }
+    let start = buffer.len();
+
+    let mut is_compressed = false;
+    if payload.len() > threshold {
+        buffer.push(header::LZ4);
+        let offset = buffer.len();
+        buffer.resize(
+            offset + lz4_flex::block::get_maximum_output_size(payload.len()),
+            0,
+        );
+        match lz4_flex::block::compress_into(payload, &mut buffer[offset..]) {
+            Ok(n) if n < payload.len() => {
+                buffer.truncate(offset + n);
+                is_compressed = true;
+            }
+            _ => buffer.truncate(start),
+        }
+    }
+    if !is_compressed {
+        buffer.push(header::RAW);
+        buffer.extend_from_slice(payload);
+    }
+
+    if batch.is_streamed() {
+        let length = (buffer.len() - start) as u16;
+        buffer[..start].copy_from_slice(&length.to_le_bytes());
+    }
+
+    buffer
+}
+
+/// Turn the `n` bytes of a batch received on a link of a transport with compression enabled
+/// into a [`ZSlice`][ZSlice] containing the serialized messages.
+///
+/// Raw batches are sliced in place, while compressed batches are decompressed into a buffer
+/// taken from `pool`.
+pub(crate) fn decompress_batch<F>(
+    buffer: RecyclingObject<Box<[u8]>>,
+    n: usize,
+    pool: &RecyclingObjectPool<Box<[u8]>, F>,
+) -> ZResult<ZSlice>
+where
+    F: Fn() -> Box<[u8]>,
+{
+    if n < BATCH_HEADER_LEN {
+        let e = "Received a batch without compression header".to_string();
+        return zerror!(ZErrorKind::InvalidMessage { descr: e });
+    }
+
+    match buffer[0] {
+        header::RAW => Ok(ZSlice::new(buffer.into(), BATCH_HEADER_LEN, n)),
+        header::LZ4 => {
+            let mut output = pool.try_take().unwrap_or_else(|| pool.alloc());
+            let m = lz4_flex::block::decompress_into(&buffer[BATCH_HEADER_LEN..n], &mut output)
+                .map_err(|e| {
+                    let e = format!("Decompression of a batch failed: {}", e);
+                    zerror2!(ZErrorKind::InvalidMessage { descr: e })
+                })?;
+            Ok(ZSlice::new(output.into(), 0, m))
+        }
+        h => {
+            let e = format!("Received a batch with unknown compression header: {}", h);
+            zerror!(ZErrorKind::InvalidMessage { descr: e })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::protocol::core::{Channel, CongestionControl, Priority, Reliability, ResKey};
+    use crate::net::protocol::io::ZBuf;
+    use crate::net::protocol::proto::defaults::SEQ_NUM_RES;
+    use crate::net::protocol::proto::{Frame, FramePayload, TransportBody, ZenohMessage};
+    use crate::net::transport::common::seq_num::SeqNumGenerator;
+
+    fn compression_roundtrip(payload_size: usize, threshold: usize) {
+        let batch_size: u16 = 8_192;
+        for is_streamed in [false, true].iter() {
+            // Serialize a single message on the batch
+            let priority = Priority::default();
+            let mut sn_gen = SeqNumGenerator::new(0, SEQ_NUM_RES);
+            let mut batch = SerializationBatch::new(batch_size, *is_streamed);
+
+            let channel = Channel {
+                priority,
+                reliability: Reliability::Reliable,
+            };
+            let mut msg = ZenohMessage::make_data(
+                ResKey::RName("/test/compression".to_string()),
+                ZBuf::from(vec![0u8; payload_size]),
+                channel,
+                CongestionControl::default(),
+                None,
+                None,
+                None,
+                None,
+            );
+            assert!(batch.serialize_zenoh_message(&mut msg, priority, &mut sn_gen));
+
+            // Compress the batch and strip the eventual length
+            let mut buffer = vec![];
+            let mut bytes = compress_batch(&batch, threshold, &mut buffer).to_vec();
+            if *is_streamed {
+                let length = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+                bytes.drain(..2);
+                assert_eq!(length, bytes.len());
+            }
+            if payload_size > threshold {
+                assert!(bytes.len() < batch.len());
+            } else {
+                assert_eq!(bytes.len(), BATCH_HEADER_LEN + batch.len());
+            }
+
+            // Decompress the batch as it was received from the link
+            let pool =
+                RecyclingObjectPool::new(2, || vec![0u8; batch_size as usize].into_boxed_slice());
+            let mut received = pool.try_take().unwrap();
+            received[..bytes.len()].copy_from_slice(&bytes);
+            let zslice = decompress_batch(received, bytes.len(), &pool).unwrap();
+            assert_eq!(zslice.as_slice(), batch.get_serialized_messages());
+
+            // Verify that we deserialize the same message we have serialized
+            let mut zbuf = ZBuf::from(zslice);
+            let frame = zbuf.read_transport_message().unwrap();
+            match frame.body {
+                TransportBody::Frame(Frame {
+                    payload: FramePayload::Messages { messages },
+                    ..
+                }) => assert_eq!(messages, vec![msg]),
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn compression_batch() {
+        // Payload above the threshold, the batch is compressed
+        compression_roundtrip(4_096, 128);
+        // Payload below the threshold, the batch is sent raw
+        compression_roundtrip(64, 128);
+    }
+}
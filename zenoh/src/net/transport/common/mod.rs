@@ -12,6 +12,7 @@
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
 pub(crate) mod batch;
+pub(crate) mod compression;
 pub(crate) mod conduit;
 pub(crate) mod defragmentation;
 pub(crate) mod pipeline;
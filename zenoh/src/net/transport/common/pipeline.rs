@@ -28,7 +28,7 @@ use super::batch::SerializationBatch;
 use super::conduit::{TransportChannelTx, TransportConduitTx};
 use super::protocol::core::Priority;
 use super::protocol::io::WBuf;
-use super::protocol::proto::{TransportMessage, ZenohMessage};
+use super::protocol::proto::{Frame, FramePayload, TransportBody, TransportMessage, ZenohMessage};
 use async_std::task;
 use std::collections::VecDeque;
 use std::fmt;
@@ -75,7 +75,10 @@ macro_rules! zgetbatch {
                     return false;
                 }
 
+                // Drop the refill guard before locking the stage_in to respect the lock order
+                drop(refill_guard);
                 $stage_in = zlock!($self.stage_in[$priority]);
+                continue;
             }
 
             // Drain all the empty batches
@@ -301,20 +304,53 @@ impl TransmissionPipeline {
 
     #[inline]
     pub(crate) fn push_transport_message(
+        &self,
+        message: TransportMessage,
+        priority: Priority,
+    ) -> bool {
+        self.push_transport_message_inner(message, priority, false)
+    }
+
+    /// Push a transport message that is dropped if no batches are available, i.e. the link is congested.
+    #[inline]
+    pub(crate) fn try_push_transport_message(
+        &self,
+        message: TransportMessage,
+        priority: Priority,
+    ) -> bool {
+        self.push_transport_message_inner(message, priority, true)
+    }
+
+    fn push_transport_message_inner(
         &self,
         mut message: TransportMessage,
         priority: Priority,
+        is_droppable: bool,
     ) -> bool {
         // Check it is a valid conduit
         let priority = if self.is_qos() { priority as usize } else { 0 };
+        // A fragment takes up the remainder of a batch when deserialized
+        let is_fragment = matches!(
+            message.body,
+            TransportBody::Frame(Frame {
+                payload: FramePayload::Fragment { .. },
+                ..
+            })
+        );
         let mut in_guard = zlock!(self.stage_in[priority]);
 
         macro_rules! zserialize {
             () => {
                 // Get the current serialization batch
-                let batch = zgetbatch!(self, priority, in_guard, false);
+                let batch = zgetbatch!(self, priority, in_guard, is_droppable);
                 if batch.serialize_transport_message(&mut message) {
                     self.bytes_in[priority].store(batch.len(), Ordering::Release);
+                    if is_fragment {
+                        // Move the batch into the OUT pipeline so that nothing follows the fragment
+                        let batch = in_guard.try_pull().unwrap();
+                        let mut out_guard = zlock!(self.stage_out);
+                        out_guard[priority].push(batch);
+                    }
                     self.cond_canpull.notify_one();
                     return true;
                 }
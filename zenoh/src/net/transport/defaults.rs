@@ -28,4 +28,20 @@ zconfigurable! {
 
     // The default backoff time in nanoseconds to allow the batching to potentially progress
     pub static ref ZN_QUEUE_PULL_BACKOFF: u64 = 100;
+
+    // Parameters of the reliability over unreliable links
+    // - The window size is the maximum number of reliable frames per conduit that can be sent
+    //   without being acknowledged by the remote peer.
+    // - The sync interval in milliseconds is the period at which a Sync is sent for the frames
+    //   not yet acknowledged, triggering the retransmission of the lost ones.
+    // - The backlog size is the maximum number of reliable frames per conduit waiting for room
+    //   in the window. Beyond that, the droppable reliable messages are dropped, and the blocking
+    //   ones wait for the acknowledgments to make room in the backlog.
+    // - The backlog timeout in milliseconds is the maximum time a blocking message waits for room
+    //   in the backlog. Beyond that, it is added to the backlog anyway: its sender might hold a lock
+    //   required to process the acknowledgments (e.g. the routing tables).
+    pub static ref ZN_RELIABILITY_WINDOW_SIZE: usize = 256;
+    pub static ref ZN_RELIABILITY_BACKLOG_SIZE: usize = 8_192;
+    pub static ref ZN_RELIABILITY_BACKLOG_TIMEOUT: u64 = 10_000;
+    pub static ref ZN_RELIABILITY_SYNC_INTERVAL: u64 = 100;
 }
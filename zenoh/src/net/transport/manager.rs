@@ -29,7 +29,7 @@ use std::sync::RwLock;
 use zenoh_util::core::{ZError, ZErrorKind, ZResult};
 use zenoh_util::crypto::{BlockCipher, PseudoRng};
 use zenoh_util::properties::{config::*, Properties};
-use zenoh_util::zparse;
+use zenoh_util::{zerror, zparse};
 
 /// # Examples
 /// ```
@@ -91,6 +91,8 @@ pub struct TransportManagerConfig {
     pub batch_size: u16,
     pub defrag_buff_size: usize,
     pub link_rx_buff_size: usize,
+    pub is_compression: bool,
+    pub compression_threshold: usize,
     pub unicast: TransportManagerConfigUnicast,
     pub multicast: TransportManagerConfigMulticast,
     pub endpoint: HashMap<LocatorProtocol, Properties>,
@@ -111,6 +113,8 @@ pub struct TransportManagerConfigBuilder {
     batch_size: u16,
     defrag_buff_size: usize,
     link_rx_buff_size: usize,
+    is_compression: bool,
+    compression_threshold: usize,
     unicast: TransportManagerConfigUnicast,
     multicast: TransportManagerConfigMulticast,
     endpoint: HashMap<LocatorProtocol, Properties>,
@@ -152,6 +156,16 @@ impl TransportManagerConfigBuilder {
         self
     }
 
+    pub fn compression(mut self, is_compression: bool) -> Self {
+        self.is_compression = is_compression;
+        self
+    }
+
+    pub fn compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.compression_threshold = compression_threshold;
+        self
+    }
+
     pub fn endpoint(mut self, endpoint: HashMap<LocatorProtocol, Properties>) -> Self {
         self.endpoint = endpoint;
         self
@@ -176,6 +190,8 @@ impl TransportManagerConfigBuilder {
             batch_size: self.batch_size,
             defrag_buff_size: self.defrag_buff_size,
             link_rx_buff_size: self.link_rx_buff_size,
+            is_compression: self.is_compression,
+            compression_threshold: self.compression_threshold,
             unicast: self.unicast,
             multicast: self.multicast,
             endpoint: self.endpoint,
@@ -208,6 +224,12 @@ impl TransportManagerConfigBuilder {
         if let Some(v) = properties.get(&ZN_LINK_RX_BUFF_SIZE_KEY) {
             self = self.link_rx_buff_size(zparse!(v)?);
         }
+        if let Some(v) = properties.get(&ZN_COMPRESSION_KEY) {
+            self = self.compression(zparse!(v)?);
+        }
+        if let Some(v) = properties.get(&ZN_COMPRESSION_THRESHOLD_KEY) {
+            self = self.compression_threshold(zparse!(v)?);
+        }
 
         self = self.endpoint(LocatorConfig::from_config(properties)?);
         self = self.unicast(
@@ -216,6 +238,12 @@ impl TransportManagerConfigBuilder {
                 .await?
                 .build(),
         );
+        self = self.multicast(
+            TransportManagerConfigMulticast::builder()
+                .from_config(properties)
+                .await?
+                .build(),
+        );
 
         Ok(self)
     }
@@ -231,6 +259,8 @@ impl Default for TransportManagerConfigBuilder {
             batch_size: BATCH_SIZE,
             defrag_buff_size: zparse!(ZN_DEFRAG_BUFF_SIZE_DEFAULT).unwrap(),
             link_rx_buff_size: zparse!(ZN_LINK_RX_BUFF_SIZE_DEFAULT).unwrap(),
+            is_compression: zparse!(ZN_COMPRESSION_DEFAULT).unwrap(),
+            compression_threshold: zparse!(ZN_COMPRESSION_THRESHOLD_DEFAULT).unwrap(),
             endpoint: HashMap::new(),
             unicast: TransportManagerConfigUnicast::default(),
             multicast: TransportManagerConfigMulticast::default(),
@@ -289,8 +319,10 @@ impl TransportManager {
     /*************************************/
     pub async fn add_listener(&self, endpoint: EndPoint) -> ZResult<Locator> {
         if endpoint.locator.address.is_multicast() {
-            // @TODO: multicast
-            unimplemented!();
+            // Listening on a multicast locator means joining the multicast group
+            let locator = endpoint.locator.clone();
+            self.open_transport_multicast(endpoint).await?;
+            Ok(locator)
         } else {
             self.add_listener_unicast(endpoint).await
         }
@@ -298,8 +330,12 @@ impl TransportManager {
 
     pub async fn del_listener(&self, endpoint: &EndPoint) -> ZResult<()> {
         if endpoint.locator.address.is_multicast() {
-            // @TODO: multicast
-            unimplemented!();
+            match self.get_transport_multicast(&endpoint.locator) {
+                Some(transport) => transport.close().await,
+                None => zerror!(ZErrorKind::InvalidLocator {
+                    descr: format!("Can not delete the multicast listener: {}", endpoint)
+                }),
+            }
         } else {
             self.del_listener_unicast(endpoint).await
         }
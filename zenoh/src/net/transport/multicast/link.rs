@@ -11,9 +11,10 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use super::common::compression::{self, BATCH_HEADER_LEN};
 use super::common::{conduit::TransportConduitTx, pipeline::TransmissionPipeline};
 use super::protocol::io::{ZBuf, ZSlice};
-use super::protocol::proto::TransportMessage;
+use super::protocol::proto::{imsg, tmsg, TransportMessage};
 use super::transport::TransportMulticastInner;
 #[cfg(feature = "stats")]
 use super::transport::TransportMulticastStatsInner;
@@ -30,7 +31,7 @@ use std::time::{Duration, Instant};
 use zenoh_util::collections::RecyclingObjectPool;
 use zenoh_util::core::{ZError, ZErrorKind, ZResult};
 use zenoh_util::sync::Signal;
-use zenoh_util::zerror;
+use zenoh_util::{zerror, zread};
 
 pub(super) struct TransportLinkMulticastConfig {
     pub(super) version: u8,
@@ -41,6 +42,8 @@ pub(super) struct TransportLinkMulticastConfig {
     pub(super) join_interval: Duration,
     pub(super) sn_resolution: ZInt,
     pub(super) batch_size: u16,
+    // The compression threshold, if the batches are compressed
+    pub(super) compression: Option<usize>,
 }
 
 #[derive(Clone)]
@@ -100,12 +103,13 @@ impl TransportLinkMulticast {
             .collect();
 
         if self.handle_tx.is_none() {
+            // Leave room for the compression header, if any
+            let mut batch_size = config.batch_size.min(self.inner.get_mtu());
+            if config.compression.is_some() {
+                batch_size -= BATCH_HEADER_LEN as u16;
+            }
             // The pipeline
-            let pipeline = Arc::new(TransmissionPipeline::new(
-                config.batch_size.min(self.inner.get_mtu()),
-                false,
-                conduit_tx,
-            ));
+            let pipeline = Arc::new(TransmissionPipeline::new(batch_size, false, conduit_tx));
             self.pipeline = Some(pipeline.clone());
 
             // Spawn the TX task
@@ -232,6 +236,9 @@ async fn tx_task(
         Action::Join
     }
 
+    // The buffer to compress the batches onto
+    let mut buffer = vec![];
+
     let mut last_join = Instant::now() - config.join_interval;
     loop {
         match pull(&pipeline, config.keep_alive)
@@ -240,7 +247,10 @@ async fn tx_task(
         {
             Action::Pull((batch, priority)) => {
                 // Send the buffer on the link
-                let bytes = batch.as_bytes();
+                let bytes = match config.compression {
+                    Some(threshold) => compression::compress_batch(&batch, threshold, &mut buffer),
+                    None => batch.as_bytes(),
+                };
                 let _ = link.write_all(bytes).await?;
                 // Keep track of next SNs
                 if let Some(sn) = batch.sn.reliable {
@@ -273,9 +283,12 @@ async fn tx_task(
                     config.lease,
                     config.sn_resolution,
                     initial_sns,
+                    config.compression.is_some(),
                     attachment,
                 );
 
+                // The join is always sent raw, so that the receivers can decode it
+                // before knowing whether the batches of this peer are compressed
                 #[allow(unused_variables)] // Used when stats feature is enabled
                 let n = link.write_transport_message(&mut message).await?;
                 #[cfg(feature = "stats")]
@@ -296,8 +309,12 @@ async fn tx_task(
                 // Drain the transmission pipeline and write remaining bytes on the wire
                 let mut batches = pipeline.drain();
                 for (b, _) in batches.drain(..) {
+                    let bytes = match config.compression {
+                        Some(threshold) => compression::compress_batch(&b, threshold, &mut buffer),
+                        None => b.as_bytes(),
+                    };
                     let _ = link
-                        .write_all(b.as_bytes())
+                        .write_all(bytes)
                         .timeout(config.join_interval)
                         .await
                         .map_err(|_| {
@@ -312,7 +329,7 @@ async fn tx_task(
                     #[cfg(feature = "stats")]
                     {
                         stats.inc_tx_msgs(b.stats.t_msgs);
-                        stats.inc_tx_bytes(b.len());
+                        stats.inc_tx_bytes(bytes.len());
                     }
                 }
                 break;
@@ -370,8 +387,26 @@ async fn rx_task(
                 #[cfg(feature = "stats")]
                 transport.stats.inc_rx_bytes(n);
 
+                // The batches of a peer advertising compression in its joins are preceded by a
+                // compression header, except its joins: they are always sent raw and start with
+                // a header having the O flag, which is never set on a compression header
+                let is_compressed = !imsg::has_flag(buffer[0], tmsg::flag::O)
+                    && matches!(zread!(transport.peers).get(&loc), Some(p) if p.is_compression);
+
                 // Add the received bytes to the ZBuf for deserialization
-                zbuf.add_zslice(ZSlice::new(buffer.into(), 0, n));
+                let zslice = if is_compressed {
+                    match compression::decompress_batch(buffer, n, &pool) {
+                        Ok(zslice) => zslice,
+                        Err(e) => {
+                            // Drop the batch but keep on receiving from the other peers
+                            log::warn!("{}: batch from {} dropped: {}", link, loc, e);
+                            continue;
+                        }
+                    }
+                } else {
+                    ZSlice::new(buffer.into(), 0, n)
+                };
+                zbuf.add_zslice(zslice);
 
                 // Deserialize all the messages from the current ZBuf
                 while zbuf.can_read() {
@@ -383,8 +418,10 @@ async fn rx_task(
                             transport.receive_message(msg, &loc)?
                         }
                         None => {
-                            let e = format!("{}: decoding error", link);
-                            return zerror!(ZErrorKind::IoError { descr: e });
+                            // E.g. a compressed batch from a peer not joined yet: drop the
+                            // rest of the batch but keep on receiving from the other peers
+                            log::warn!("{}: batch from {} dropped: decoding error", link, loc);
+                            break;
                         }
                     }
                 }
@@ -27,6 +27,7 @@ use crate::net::transport::{TransportMulticastEventHandler, TransportPeer};
 pub use manager::*;
 use std::fmt;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 use transport::{TransportMulticastConfig, TransportMulticastInner};
 use zenoh_util::core::{ZError, ZErrorKind, ZResult};
 use zenoh_util::zerror2;
@@ -62,6 +63,12 @@ impl TransportMulticast {
         Ok(transport.get_sn_resolution())
     }
 
+    #[inline(always)]
+    pub fn get_join_interval(&self) -> ZResult<Duration> {
+        let transport = self.get_transport()?;
+        Ok(transport.get_join_interval())
+    }
+
     #[inline(always)]
     pub fn is_shm(&self) -> ZResult<bool> {
         let transport = self.get_transport()?;
@@ -100,6 +100,8 @@ pub(super) struct TransportMulticastPeer {
     pub(super) whatami: WhatAmI,
     pub(super) sn_resolution: ZInt,
     pub(super) lease: Duration,
+    // The batches of the peer are preceded by a compression header
+    pub(super) is_compression: bool,
     pub(super) whatchdog: Arc<AtomicBool>,
     pub(super) handle: TimedHandle,
     pub(super) conduit_rx: Box<[TransportConduitRx]>,
@@ -214,6 +216,10 @@ impl TransportMulticastInner {
         self.manager.config.sn_resolution
     }
 
+    pub(crate) fn get_join_interval(&self) -> Duration {
+        self.manager.config.multicast.join_interval
+    }
+
     pub(crate) fn is_qos(&self) -> bool {
         self.conduit_tx.len() > 1
     }
@@ -321,6 +327,11 @@ impl TransportMulticastInner {
                     join_interval: self.manager.config.multicast.join_interval,
                     sn_resolution: self.manager.config.sn_resolution,
                     batch_size,
+                    compression: if self.manager.config.is_compression {
+                        Some(self.manager.config.compression_threshold)
+                    } else {
+                        None
+                    },
                 };
                 l.start_tx(config, self.conduit_tx.clone());
                 Ok(())
@@ -451,6 +462,7 @@ impl TransportMulticastInner {
             whatami: peer.whatami,
             sn_resolution: join.sn_resolution,
             lease: join.lease,
+            is_compression: join.is_compression,
             whatchdog,
             handle,
             conduit_rx,
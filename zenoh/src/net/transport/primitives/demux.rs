@@ -12,7 +12,8 @@
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
 use super::protocol::proto::{
-    Data, Declaration, Declare, LinkStateList, Pull, Query, Unit, ZenohBody, ZenohMessage,
+    Data, Declaration, Declare, LinkStateList, Pull, Query, QueryCancel, Unit, ZenohBody,
+    ZenohMessage,
 };
 use super::Primitives;
 use crate::net::link::Link;
@@ -88,6 +89,16 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
                     );
                 }
                 Some(rep) => match rep.replier {
+                    Some(replier) if rep.is_error => {
+                        self.primitives.send_reply_error(
+                            rep.qid,
+                            replier.kind,
+                            replier.id,
+                            key,
+                            data_info,
+                            payload,
+                        );
+                    }
                     Some(replier) => {
                         self.primitives.send_reply_data(
                             rep.qid,
@@ -109,7 +120,7 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
             ZenohBody::Unit(Unit { reply_context, .. }) => {
                 if let Some(rep) = reply_context {
                     if rep.is_final() {
-                        self.primitives.send_reply_final(rep.qid);
+                        self.primitives.send_reply_final(rep.qid, rep.is_partial());
                     }
                 }
             }
@@ -132,6 +143,10 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
                 );
             }
 
+            ZenohBody::QueryCancel(QueryCancel { qid }) => {
+                self.primitives.send_query_cancel(qid);
+            }
+
             ZenohBody::Pull(Pull {
                 key,
                 pull_id,
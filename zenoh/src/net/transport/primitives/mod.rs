@@ -71,7 +71,19 @@ pub trait Primitives: Send + Sync {
         payload: ZBuf,
     );
 
-    fn send_reply_final(&self, qid: ZInt);
+    fn send_reply_error(
+        &self,
+        qid: ZInt,
+        replier_kind: ZInt,
+        replier_id: PeerId,
+        reskey: ResKey,
+        info: Option<DataInfo>,
+        payload: ZBuf,
+    );
+
+    fn send_reply_final(&self, qid: ZInt, is_partial: bool);
+
+    fn send_query_cancel(&self, qid: ZInt);
 
     fn send_pull(&self, is_final: bool, reskey: &ResKey, pull_id: ZInt, max_samples: &Option<ZInt>);
 
@@ -142,7 +154,18 @@ impl Primitives for DummyPrimitives {
         _payload: ZBuf,
     ) {
     }
-    fn send_reply_final(&self, _qid: ZInt) {}
+    fn send_reply_error(
+        &self,
+        _qid: ZInt,
+        _replier_kind: ZInt,
+        _replier_id: PeerId,
+        _reskey: ResKey,
+        _info: Option<DataInfo>,
+        _payload: ZBuf,
+    ) {
+    }
+    fn send_reply_final(&self, _qid: ZInt, _is_partial: bool) {}
+    fn send_query_cancel(&self, _qid: ZInt) {}
     fn send_pull(
         &self,
         _is_final: bool,
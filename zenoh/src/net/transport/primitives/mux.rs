@@ -11,7 +11,7 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
-use super::super::TransportUnicast;
+use super::super::{TransportMulticast, TransportUnicast};
 use super::protocol::core::{
     Channel, CongestionControl, PeerId, QueryConsolidation, QueryTarget, ResKey, SubInfo, ZInt,
 };
@@ -22,18 +22,38 @@ use super::protocol::proto::{
     Subscriber, ZenohMessage,
 };
 use super::Primitives;
+use zenoh_util::core::ZResult;
 
-pub struct Mux {
-    handler: TransportUnicast,
+/// A transport on which a [`Mux`] sends the zenoh messages.
+pub trait MuxTransport: Send + Sync {
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()>;
 }
 
-impl Mux {
-    pub(crate) fn new(handler: TransportUnicast) -> Mux {
+impl MuxTransport for TransportUnicast {
+    #[inline]
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
+        TransportUnicast::handle_message(self, message)
+    }
+}
+
+impl MuxTransport for TransportMulticast {
+    #[inline]
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
+        TransportMulticast::handle_message(self, message)
+    }
+}
+
+pub struct Mux<T: MuxTransport> {
+    handler: T,
+}
+
+impl<T: MuxTransport> Mux<T> {
+    pub(crate) fn new(handler: T) -> Mux<T> {
         Mux { handler }
     }
 }
 
-impl Primitives for Mux {
+impl<T: MuxTransport> Primitives for Mux<T> {
     fn decl_resource(&self, rid: ZInt, reskey: &ResKey) {
         let d = Declaration::Resource(Resource {
             rid,
@@ -193,15 +213,48 @@ impl Primitives for Mux {
         ));
     }
 
-    fn send_reply_final(&self, qid: ZInt) {
+    fn send_reply_error(
+        &self,
+        qid: ZInt,
+        replier_kind: ZInt,
+        replier_id: PeerId,
+        reskey: ResKey,
+        data_info: Option<DataInfo>,
+        payload: ZBuf,
+    ) {
+        let _ = self.handler.handle_message(ZenohMessage::make_data(
+            reskey,
+            payload,
+            zmsg::default_channel::REPLY,
+            zmsg::default_congestion_control::REPLY,
+            data_info,
+            None,
+            Some(ReplyContext::new_error(
+                qid,
+                ReplierInfo {
+                    kind: replier_kind,
+                    id: replier_id,
+                },
+            )),
+            None,
+        ));
+    }
+
+    fn send_reply_final(&self, qid: ZInt, is_partial: bool) {
         let _ = self.handler.handle_message(ZenohMessage::make_unit(
             zmsg::default_channel::REPLY,
             zmsg::default_congestion_control::REPLY,
-            Some(ReplyContext::new(qid, None)),
+            Some(ReplyContext::new_final(qid, is_partial)),
             None,
         ));
     }
 
+    fn send_query_cancel(&self, qid: ZInt) {
+        let _ = self
+            .handler
+            .handle_message(ZenohMessage::make_query_cancel(qid, None));
+    }
+
     fn send_pull(
         &self,
         is_final: bool,
@@ -65,6 +65,7 @@ struct Cookie {
     pid: PeerId,
     sn_resolution: ZInt,
     is_qos: bool,
+    is_compression: bool,
     nonce: ZInt,
 }
 
@@ -74,6 +75,7 @@ impl WBuf {
         zcheck!(self.write_peerid(&cookie.pid));
         zcheck!(self.write_zint(cookie.sn_resolution));
         zcheck!(self.write(if cookie.is_qos { 1 } else { 0 }));
+        zcheck!(self.write(if cookie.is_compression { 1 } else { 0 }));
         zcheck!(self.write_zint(cookie.nonce));
         true
     }
@@ -85,6 +87,7 @@ impl ZBuf {
         let pid = self.read_peerid()?;
         let sn_resolution = self.read_zint()?;
         let is_qos = self.read()? == 1;
+        let is_compression = self.read()? == 1;
         let nonce = self.read_zint()?;
 
         Some(Cookie {
@@ -92,6 +95,7 @@ impl ZBuf {
             pid,
             sn_resolution,
             is_qos,
+            is_compression,
             nonce,
         })
     }
@@ -148,6 +152,7 @@ async fn open_send_init_syn(
         manager.config.pid,
         manager.config.sn_resolution,
         manager.config.unicast.is_qos,
+        manager.config.is_compression,
         attachment_from_config(&auth.properties).ok(),
     );
     let _ = link
@@ -167,6 +172,7 @@ struct OpenInitAckOutput {
     whatami: WhatAmI,
     sn_resolution: ZInt,
     is_qos: bool,
+    is_compression: bool,
     initial_sn_tx: ZInt,
     cookie: ZSlice,
     open_syn_attachment: Option<Attachment>,
@@ -292,6 +298,8 @@ async fn open_recv_init_ack(
         whatami: init_ack.whatami,
         sn_resolution,
         is_qos: init_ack.is_qos,
+        // Batches are compressed only if both peers enable compression
+        is_compression: init_ack.is_compression && manager.config.is_compression,
         initial_sn_tx,
         cookie: init_ack.cookie,
         open_syn_attachment: attachment_from_config(&auth.properties).ok(),
@@ -306,6 +314,7 @@ struct OpenOpenSynOutput {
     sn_resolution: ZInt,
     initial_sn_tx: ZInt,
     is_qos: bool,
+    is_compression: bool,
     auth_transport: AuthenticatedPeerTransport,
 }
 async fn open_send_open_syn(
@@ -333,6 +342,7 @@ async fn open_send_open_syn(
         sn_resolution: input.sn_resolution,
         initial_sn_tx: input.initial_sn_tx,
         is_qos: input.is_qos,
+        is_compression: input.is_compression,
         auth_transport: input.auth_transport,
     };
     Ok(output)
@@ -343,6 +353,7 @@ struct OpenAckOutput {
     whatami: WhatAmI,
     sn_resolution: ZInt,
     is_qos: bool,
+    is_compression: bool,
     initial_sn_tx: ZInt,
     initial_sn_rx: ZInt,
     lease: Duration,
@@ -407,6 +418,7 @@ async fn open_recv_open_ack(
         whatami: input.whatami,
         sn_resolution: input.sn_resolution,
         is_qos: input.is_qos,
+        is_compression: input.is_compression,
         initial_sn_tx: input.initial_sn_tx,
         initial_sn_rx,
         lease,
@@ -453,6 +465,9 @@ pub(crate) async fn open_link(
         initial_sn_rx: info.initial_sn_rx,
         is_shm: info.auth_transport.is_shm,
         is_qos: info.is_qos,
+        is_compression: info.is_compression,
+        // Reliable frames are retransmitted when the link the transport is opened on is not reliable
+        is_retransmission: !link.is_reliable(),
     };
     let res = manager.init_transport_unicast(config);
     let transport = match res {
@@ -532,6 +547,7 @@ struct AcceptInitSynOutput {
     pid: PeerId,
     sn_resolution: ZInt,
     is_qos: bool,
+    is_compression: bool,
     init_ack_attachment: Option<Attachment>,
     auth_transport: AuthenticatedPeerTransport,
 }
@@ -622,6 +638,8 @@ async fn accept_recv_init_syn(
         pid: init_syn.pid,
         sn_resolution: init_syn.sn_resolution,
         is_qos: init_syn.is_qos,
+        // Batches are compressed only if both peers enable compression
+        is_compression: init_syn.is_compression && manager.config.is_compression,
         init_ack_attachment: attachment_from_config(&auth.properties).ok(),
         auth_transport: auth.transport,
     };
@@ -648,6 +666,7 @@ async fn accept_send_init_ack(
         pid: input.pid,
         sn_resolution: agreed_sn_resolution,
         is_qos: input.is_qos,
+        is_compression: input.is_compression,
         nonce: zasynclock!(manager.prng).gen_range(0..agreed_sn_resolution),
     };
     wbuf.write_cookie(&cookie);
@@ -678,6 +697,7 @@ async fn accept_send_init_ack(
         apid,
         sn_resolution,
         input.is_qos,
+        input.is_compression,
         cookie,
         input.init_ack_attachment,
     );
@@ -891,6 +911,9 @@ async fn accept_init_transport(
         initial_sn_rx: input.initial_sn,
         is_shm: input.auth_transport.is_shm,
         is_qos: input.cookie.is_qos,
+        is_compression: input.cookie.is_compression,
+        // Reliable frames are retransmitted when the link the transport is opened on is not reliable
+        is_retransmission: !link.is_reliable(),
     };
     let transport = manager
         .init_transport_unicast(config)
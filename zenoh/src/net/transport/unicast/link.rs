@@ -11,6 +11,7 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use super::common::compression::{self, BATCH_HEADER_LEN};
 use super::common::{conduit::TransportConduitTx, pipeline::TransmissionPipeline};
 use super::protocol::core::Priority;
 use super::protocol::io::{ZBuf, ZSlice};
@@ -77,9 +78,20 @@ impl TransportLinkUnicast {
         conduit_tx: Arc<[TransportConduitTx]>,
     ) {
         if self.handle_tx.is_none() {
+            // The compression threshold, if the batches are compressed
+            let compression = if self.transport.is_compression() {
+                Some(self.transport.manager.config.compression_threshold)
+            } else {
+                None
+            };
+            // Leave room for the compression header, if any
+            let mut batch_size = batch_size.min(self.inner.get_mtu());
+            if compression.is_some() {
+                batch_size -= BATCH_HEADER_LEN as u16;
+            }
             // The pipeline
             let pipeline = Arc::new(TransmissionPipeline::new(
-                batch_size.min(self.inner.get_mtu()),
+                batch_size,
                 self.inner.is_streamed(),
                 conduit_tx,
             ));
@@ -93,6 +105,7 @@ impl TransportLinkUnicast {
                     pipeline,
                     c_link.clone(),
                     keep_alive,
+                    compression,
                     #[cfg(feature = "stats")]
                     c_transport.stats.clone(),
                 )
@@ -179,14 +192,22 @@ async fn tx_task(
     pipeline: Arc<TransmissionPipeline>,
     link: LinkUnicast,
     keep_alive: Duration,
+    compression: Option<usize>,
     #[cfg(feature = "stats")] stats: TransportUnicastStatsInner,
 ) -> ZResult<()> {
+    // The buffer to compress the batches onto
+    let mut buffer = vec![];
     loop {
         match pipeline.pull().timeout(keep_alive).await {
             Ok(res) => match res {
                 Some((batch, priority)) => {
                     // Send the buffer on the link
-                    let bytes = batch.as_bytes();
+                    let bytes = match compression {
+                        Some(threshold) => {
+                            compression::compress_batch(&batch, threshold, &mut buffer)
+                        }
+                        None => batch.as_bytes(),
+                    };
                     let _ = link.write_all(bytes).await?;
 
                     #[cfg(feature = "stats")]
@@ -212,8 +233,12 @@ async fn tx_task(
     // Drain the transmission pipeline and write remaining bytes on the wire
     let mut batches = pipeline.drain();
     for (b, _) in batches.drain(..) {
+        let bytes = match compression {
+            Some(threshold) => compression::compress_batch(&b, threshold, &mut buffer),
+            None => b.as_bytes(),
+        };
         let _ = link
-            .write_all(b.as_bytes())
+            .write_all(bytes)
             .timeout(keep_alive)
             .await
             .map_err(|_| {
@@ -224,7 +249,7 @@ async fn tx_task(
         #[cfg(feature = "stats")]
         {
             stats.inc_tx_msgs(b.stats.t_msgs);
-            stats.inc_tx_bytes(b.len());
+            stats.inc_tx_bytes(bytes.len());
         }
     }
 
@@ -258,6 +283,8 @@ async fn rx_task_stream(
         Ok(Action::Stop)
     }
 
+    // The batches received on the link are compressed
+    let is_compression = transport.is_compression();
     // The ZBuf to read a message batch onto
     let mut zbuf = ZBuf::new();
     // The pool of buffers
@@ -282,7 +309,12 @@ async fn rx_task_stream(
             })??;
         match action {
             Action::Read(n) => {
-                zbuf.add_zslice(ZSlice::new(buffer.into(), 0, n));
+                let zslice = if is_compression {
+                    compression::decompress_batch(buffer, n, &pool)?
+                } else {
+                    ZSlice::new(buffer.into(), 0, n)
+                };
+                zbuf.add_zslice(zslice);
 
                 #[cfg(feature = "stats")]
                 transport.stats.inc_rx_bytes(2 + n); // Account for the batch len encoding (16 bits)
@@ -331,6 +363,8 @@ async fn rx_task_dgram(
         Ok(Action::Stop)
     }
 
+    // The batches received on the link are compressed
+    let is_compression = transport.is_compression();
     // The ZBuf to read a message batch onto
     let mut zbuf = ZBuf::new();
     // The pool of buffers
@@ -364,7 +398,12 @@ async fn rx_task_dgram(
                 transport.stats.inc_rx_bytes(n);
 
                 // Add the received bytes to the ZBuf for deserialization
-                zbuf.add_zslice(ZSlice::new(buffer.into(), 0, n));
+                let zslice = if is_compression {
+                    compression::decompress_batch(buffer, n, &pool)?
+                } else {
+                    ZSlice::new(buffer.into(), 0, n)
+                };
+                zbuf.add_zslice(zslice);
 
                 // Deserialize all the messages from the current ZBuf
                 while zbuf.can_read() {
@@ -356,6 +356,15 @@ impl TransportManager {
                 return zerror!(ZErrorKind::Other { descr: e });
             }
 
+            if transport.is_compression != config.is_compression {
+                let e = format!(
+                    "Transport with peer {} already exist. Invalid is_compression: {}. Execpted: {}.",
+                    config.peer, config.is_compression, transport.is_compression
+                );
+                log::trace!("{}", e);
+                return zerror!(ZErrorKind::Other { descr: e });
+            }
+
             return Ok(transport.into());
         }
 
@@ -379,6 +388,8 @@ impl TransportManager {
             initial_sn_rx: config.initial_sn_rx,
             is_shm: config.is_shm,
             is_qos: config.is_qos,
+            is_compression: config.is_compression,
+            is_retransmission: config.is_retransmission,
         };
         let a_st = Arc::new(TransportUnicastInner::new(stc));
 
@@ -388,14 +399,16 @@ impl TransportManager {
         guard.insert(config.peer, a_st);
 
         log::debug!(
-            "New transport opened with {}: whatami {}, sn resolution {}, initial sn tx {:?}, initial sn rx {:?}, shm: {}, qos: {}",
+            "New transport opened with {}: whatami {}, sn resolution {}, initial sn tx {:?}, initial sn rx {:?}, shm: {}, qos: {}, compression: {}, retransmission: {}",
             config.peer,
             config.whatami,
             config.sn_resolution,
             config.initial_sn_tx,
             config.initial_sn_rx,
             config.is_shm,
-            config.is_qos
+            config.is_qos,
+            config.is_compression,
+            config.is_retransmission
         );
 
         Ok(transport)
@@ -15,6 +15,7 @@ pub mod authenticator;
 pub(crate) mod establishment;
 pub(crate) mod link;
 pub(crate) mod manager;
+pub(crate) mod reliability;
 pub(crate) mod rx;
 pub(crate) mod transport;
 pub(crate) mod tx;
@@ -53,6 +54,8 @@ pub(crate) struct TransportConfigUnicast {
     pub(crate) initial_sn_rx: ZInt,
     pub(crate) is_shm: bool,
     pub(crate) is_qos: bool,
+    pub(crate) is_compression: bool,
+    pub(crate) is_retransmission: bool,
 }
 
 /// [`TransportUnicast`] is the transport handler returned
@@ -100,6 +103,18 @@ impl TransportUnicast {
         Ok(transport.is_qos())
     }
 
+    #[inline(always)]
+    pub fn is_compression(&self) -> ZResult<bool> {
+        let transport = self.get_transport()?;
+        Ok(transport.is_compression())
+    }
+
+    #[inline(always)]
+    pub fn is_retransmission(&self) -> ZResult<bool> {
+        let transport = self.get_transport()?;
+        Ok(transport.is_retransmission())
+    }
+
     #[inline(always)]
     pub fn get_callback(&self) -> ZResult<Option<Arc<dyn TransportPeerEventHandler>>> {
         let transport = self.get_transport()?;
@@ -208,6 +223,8 @@ impl fmt::Debug for TransportUnicast {
                 .field("sn_resolution", &transport.get_sn_resolution())
                 .field("is_qos", &transport.is_qos())
                 .field("is_shm", &transport.is_shm())
+                .field("is_compression", &transport.is_compression())
+                .field("is_retransmission", &transport.is_retransmission())
                 .finish(),
             Err(e) => {
                 write!(f, "{}", e)
@@ -11,15 +11,116 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use std::collections::VecDeque;
 use std::convert::TryInto;
 use std::fmt;
+use std::sync::{Condvar, Mutex};
 
 use super::common::seq_num::SeqNum;
-use super::core::ZInt;
+use super::protocol::core::{Priority, ZInt};
+use super::protocol::proto::FramePayload;
 
 use zenoh_util::core::{ZError, ZErrorKind, ZResult};
 use zenoh_util::zerror;
 
+// The number of bits of a mask, i.e. the maximum number of frames a mask can refer to
+pub(super) const MASK_BITS: usize = 8 * std::mem::size_of::<ZInt>();
+
+/// The state providing the reliability of the reliable channel of a conduit
+/// when the links of the transport are not reliable.
+///
+/// The sender keeps the frames until they are acknowledged by an AckNack, and retransmits
+/// the ones marked as missing. The frames not fitting in the window are kept in a backlog,
+/// and are sent as soon as enough frames are acknowledged. The blocking messages not fitting
+/// in a full backlog wait for the acknowledgments to make room. The receiver reorders the frames
+/// before delivering them, and acknowledges them periodically, when a frame is missing,
+/// or when it receives a Sync.
+pub(super) struct ConduitReliability {
+    pub(super) priority: Priority,
+    // The frames sent and not yet acknowledged
+    pub(super) tx: Mutex<ReliabilityQueue<FramePayload>>,
+    // The frames waiting for room in the window, always locked after tx
+    pub(super) backlog: Mutex<VecDeque<FramePayload>>,
+    // Notified when frames of the backlog are moved to the window
+    pub(super) backlog_room: Condvar,
+    // The frames received and not yet delivered
+    pub(super) rx: Mutex<ReliabilityRx>,
+}
+
+impl ConduitReliability {
+    pub(super) fn new(
+        priority: Priority,
+        window_size: usize,
+        initial_sn_tx: ZInt,
+        initial_sn_rx: ZInt,
+        sn_resolution: ZInt,
+    ) -> ConduitReliability {
+        ConduitReliability {
+            priority,
+            tx: Mutex::new(ReliabilityQueue::new(
+                window_size,
+                initial_sn_tx,
+                sn_resolution,
+            )),
+            backlog: Mutex::new(VecDeque::new()),
+            backlog_room: Condvar::new(),
+            rx: Mutex::new(ReliabilityRx::new(
+                window_size,
+                initial_sn_rx,
+                sn_resolution,
+            )),
+        }
+    }
+}
+
+pub(super) struct ReliabilityRx {
+    // The base of the queue is the sequence number of the next frame to deliver
+    pub(super) queue: ReliabilityQueue<FramePayload>,
+    // The number of frames delivered since the last acknowledgment
+    pub(super) unacked: usize,
+    // The number of positions from the base whose missing frames have already been notified
+    nacked: usize,
+}
+
+impl ReliabilityRx {
+    fn new(capacity: usize, initial_sn: ZInt, sn_resolution: ZInt) -> ReliabilityRx {
+        ReliabilityRx {
+            queue: ReliabilityQueue::new(capacity, initial_sn, sn_resolution),
+            unacked: 0,
+            nacked: 0,
+        }
+    }
+
+    /// Pulls the next frame in sequence, if already received.
+    pub(super) fn pull(&mut self) -> Option<FramePayload> {
+        let payload = self.queue.pull()?;
+        self.unacked += 1;
+        self.nacked = self.nacked.saturating_sub(1);
+        Some(payload)
+    }
+
+    /// Returns a bitmask of the missing frames that have not been notified to the sender yet.
+    /// The returned frames are then considered as notified.
+    pub(super) fn get_nack_mask(&mut self) -> ZInt {
+        let mask = self.queue.get_mask();
+        let new = if self.nacked < MASK_BITS {
+            mask & (ZInt::MAX << self.nacked)
+        } else {
+            0
+        };
+        self.nacked = self.nacked.max(MASK_BITS - mask.leading_zeros() as usize);
+        new
+    }
+
+    /// Returns a bitmask of all the frames missing before the sequence number `sn`.
+    /// The returned frames are then considered as notified.
+    pub(super) fn get_sync_mask(&mut self, sn: ZInt) -> ZResult<ZInt> {
+        let mask = self.queue.get_mask_before(sn)?;
+        self.nacked = self.nacked.max(MASK_BITS - mask.leading_zeros() as usize);
+        Ok(mask)
+    }
+}
+
 pub(super) struct ReliabilityQueue<T> {
     sn: SeqNum,
     index: usize,
@@ -61,6 +162,7 @@ impl<T> ReliabilityQueue<T> {
         self.len() == 0
     }
 
+    #[allow(dead_code)]
     #[inline]
     pub(super) fn is_full(&self) -> bool {
         self.len() == self.capacity()
@@ -71,6 +173,12 @@ impl<T> ReliabilityQueue<T> {
         self.sn.get()
     }
 
+    /// Returns the distance between the base of the queue and the sequence number `sn`.
+    #[inline]
+    pub(super) fn gap(&self, sn: ZInt) -> ZResult<ZInt> {
+        self.sn.gap(sn)
+    }
+
     pub(super) fn set_base(&mut self, sn: ZInt) -> ZResult<()> {
         let gap: usize = match self.sn.gap(sn) {
             Ok(gap) => match gap.try_into() {
@@ -128,13 +236,15 @@ impl<T> ReliabilityQueue<T> {
             return zerror!(ZErrorKind::Other { descr: e });
         }
 
-        self.len += 1;
         let index = (self.index + gap) % self.capacity();
-        self.inner[index] = Some(t);
+        if self.inner[index].replace(t).is_none() {
+            self.len += 1;
+        }
 
         Ok(())
     }
 
+    #[allow(dead_code)]
     pub(super) fn remove(&mut self, sn: ZInt) -> ZResult<T> {
         let gap: usize = match self.sn.gap(sn) {
             Ok(gap) => match gap.try_into() {
@@ -187,11 +297,12 @@ impl<T> ReliabilityQueue<T> {
     /// Returns a bitmask of surely missed messages.
     /// A bit is set to 1 iff the position in the queue is empty and
     /// there is at least one message with a higher sequence number.
+    /// Only the first messages fitting in the bitmask are considered.
     pub(super) fn get_mask(&self) -> ZInt {
         let mut mask: ZInt = 0;
         let mut count = 0;
         let mut i = 0;
-        while count < self.len() {
+        while count < self.len() && i < MASK_BITS {
             let index = (self.index + i) % self.capacity();
             if self.inner[index].is_none() {
                 mask |= 1 << i;
@@ -202,6 +313,21 @@ impl<T> ReliabilityQueue<T> {
         }
         mask
     }
+
+    /// Returns a bitmask of the messages missing before the sequence number `sn`.
+    /// A bit is set to 1 iff the position in the queue is empty and precedes `sn`.
+    /// Only the first messages fitting in the bitmask are considered.
+    pub(super) fn get_mask_before(&self, sn: ZInt) -> ZResult<ZInt> {
+        let gap: usize = self.sn.gap(sn)?.try_into().unwrap_or(usize::MAX);
+        let mut mask: ZInt = 0;
+        for i in 0..gap.min(self.capacity()).min(MASK_BITS) {
+            let index = (self.index + i) % self.capacity();
+            if self.inner[index].is_none() {
+                mask |= 1 << i;
+            }
+        }
+        Ok(mask)
+    }
 }
 
 impl<T: Clone> ReliabilityQueue<T> {
@@ -269,7 +395,7 @@ mod tests {
         assert_eq!(res, Some(0));
 
         // Add the second element
-        sn = sn + 1;
+        sn += 1;
         let res = queue.insert(1, sn);
         assert!(res.is_ok());
         let res = queue.pull();
@@ -391,7 +517,7 @@ mod tests {
         while sn < size as ZInt {
             let res = queue.insert(sn, sn);
             assert!(res.is_ok());
-            sn = sn + 2;
+            sn += 2;
         }
 
         // Verify that the mask is correct
@@ -403,7 +529,7 @@ mod tests {
         while sn < size as ZInt {
             let res = queue.insert(sn, sn);
             assert!(res.is_ok());
-            sn = sn + 2;
+            sn += 2;
         }
 
         // Verify that the mask is correct
@@ -411,7 +537,7 @@ mod tests {
         assert_eq!(queue.get_mask(), mask);
 
         // Drain the queue
-        while let Some(_) = queue.pull() {}
+        while queue.pull().is_some() {}
         // Verify that the queue is empty
         assert!(queue.is_empty());
     }
@@ -431,7 +557,7 @@ mod tests {
         let mut tail = 0;
         let mut mask: ZInt = 0;
         let mut rng = thread_rng();
-        while sequence.len() > 0 {
+        while !sequence.is_empty() {
             // Get random sequence number
             let index = rng.gen_range(0..sequence.len());
             let sn = sequence.remove(index);
@@ -443,9 +569,9 @@ mod tests {
             let res = queue.insert(sn, sn);
             assert!(res.is_ok());
             // Locally compute the mask
-            mask = mask | (1 << sn);
+            mask |= 1 << sn;
             let shift: u32 = tail.wrapping_sub(head) as u32;
-            let window = !ZInt::max_value().wrapping_shl(shift);
+            let window = !ZInt::MAX.wrapping_shl(shift);
             // Verify that the mask is correct
             assert_eq!(queue.get_mask(), !mask & window);
         }
@@ -453,10 +579,10 @@ mod tests {
         // Verify that we have filled the queue
         assert!(queue.is_full());
         // Verify that no elements are marked for retransmission
-        assert_eq!(queue.get_mask(), !ZInt::max_value());
+        assert_eq!(queue.get_mask(), !ZInt::MAX);
 
         // Drain the queue
-        while let Some(_) = queue.pull() {}
+        while queue.pull().is_some() {}
         // Verify that the queue is empty
         assert!(queue.is_empty());
 
@@ -604,4 +730,90 @@ mod tests {
         // Check that the base is 0
         assert_eq!(queue.get_base(), 0);
     }
+
+    #[test]
+    fn reliability_queue_duplicate() {
+        let size = 4;
+        let mut queue: ReliabilityQueue<ZInt> = ReliabilityQueue::new(size, 0, 8);
+
+        // Inserting twice the same SN does not change the length
+        assert!(queue.insert(1, 1).is_ok());
+        assert!(queue.insert(1, 1).is_ok());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.get_mask(), 0b1);
+
+        // Fill the hole and drain the queue
+        assert!(queue.insert(0, 0).is_ok());
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pull(), Some(0));
+        assert_eq!(queue.pull(), Some(1));
+        assert_eq!(queue.pull(), None);
+        assert_eq!(queue.get_base(), 2);
+    }
+
+    #[test]
+    fn reliability_queue_mask_before() {
+        let size = 8;
+        let mut queue: ReliabilityQueue<ZInt> = ReliabilityQueue::new(size, 6, 16);
+
+        // Nothing has been received: all the SNs before the sync are missing
+        assert_eq!(queue.get_mask_before(6).unwrap(), 0);
+        assert_eq!(queue.get_mask_before(9).unwrap(), 0b111);
+
+        // Receive 7 and 9: 6 and 8 are missing, 10 is not considered
+        assert!(queue.insert(7, 7).is_ok());
+        assert!(queue.insert(9, 9).is_ok());
+        assert_eq!(queue.gap(9).unwrap(), 3);
+        assert_eq!(queue.get_mask_before(10).unwrap(), 0b0101);
+        assert_eq!(queue.get_mask_before(11).unwrap(), 0b10101);
+
+        // The mask does not go beyond the capacity of the queue
+        assert_eq!(queue.get_mask_before(2).unwrap(), 0b1111_0101);
+
+        // The SN is out of resolution
+        assert!(queue.get_mask_before(16).is_err());
+    }
+
+    #[test]
+    fn reliability_rx_nack_mask() {
+        let size = 8;
+        let mut rx = ReliabilityRx::new(size, 0, 16);
+        let payload = FramePayload::Messages { messages: vec![] };
+
+        // Receive 2: 0 and 1 are missing
+        assert!(rx.queue.insert(payload.clone(), 2).is_ok());
+        assert_eq!(rx.get_nack_mask(), 0b011);
+        // Receive 4: only 3 is notified, 0 and 1 have already been
+        assert!(rx.queue.insert(payload.clone(), 4).is_ok());
+        assert_eq!(rx.get_nack_mask(), 0b01000);
+        assert_eq!(rx.get_nack_mask(), 0);
+
+        // Receive 0: the mask is now relative to 1
+        assert!(rx.queue.insert(payload.clone(), 0).is_ok());
+        assert!(rx.pull().is_some());
+        assert!(rx.pull().is_none());
+        assert_eq!(rx.unacked, 1);
+        assert_eq!(rx.queue.get_base(), 1);
+        assert_eq!(rx.get_nack_mask(), 0);
+
+        // A Sync notifies again all the missing frames
+        assert_eq!(rx.get_sync_mask(6).unwrap(), 0b10101);
+        assert_eq!(rx.get_nack_mask(), 0);
+
+        // Receive 7: only 6 is notified, 1, 3, and 5 have been notified by the Sync
+        assert!(rx.queue.insert(payload, 7).is_ok());
+        assert_eq!(rx.get_nack_mask(), 0b100000);
+    }
+
+    #[test]
+    fn reliability_queue_mask_large() {
+        let size = 2 * MASK_BITS;
+        let mut queue: ReliabilityQueue<ZInt> = ReliabilityQueue::new(size, 0, 4 * size as ZInt);
+
+        // The mask only refers to the first SNs fitting in it
+        let last = size as ZInt - 1;
+        assert!(queue.insert(last, last).is_ok());
+        assert_eq!(queue.get_mask(), ZInt::MAX);
+        assert_eq!(queue.get_mask_before(last).unwrap(), ZInt::MAX);
+    }
 }
@@ -11,11 +11,13 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
-use super::common::conduit::TransportChannelRx;
+use super::common::conduit::{TransportChannelRx, TransportConduitRx};
 use super::protocol::core::{PeerId, Priority, Reliability, ZInt};
 use super::protocol::proto::{
-    Close, Frame, FramePayload, KeepAlive, TransportBody, TransportMessage, ZenohMessage,
+    AckNack, Close, Frame, FramePayload, KeepAlive, Sync, TransportBody, TransportMessage,
+    ZenohMessage,
 };
+use super::reliability::ConduitReliability;
 use super::transport::TransportUnicastInner;
 use crate::net::link::LinkUnicast;
 use async_std::task;
@@ -138,6 +140,128 @@ impl TransportUnicastInner {
         }
     }
 
+    /// Reorder the reliable frames received on unreliable links and deliver them in sequence.
+    fn handle_reliable_frame(
+        &self,
+        reliability: &ConduitReliability,
+        conduit: &TransportConduitRx,
+        sn: ZInt,
+        payload: FramePayload,
+    ) -> ZResult<()> {
+        let mut rx = zlock!(reliability.rx);
+        let gap = rx.queue.gap(sn)?;
+        if gap >= rx.queue.capacity() as ZInt {
+            // The frame has already been delivered: the remote peer has missed our acknowledgment
+            log::trace!(
+                "Transport: {}. Duplicated frame dropped: {}. Expected: {}.",
+                self.pid,
+                sn,
+                rx.queue.get_base()
+            );
+            return Ok(());
+        }
+
+        let was_empty = rx.queue.is_empty();
+        let _ = rx.queue.insert(payload, sn);
+
+        // Deliver all the frames received in sequence
+        let mut res = Ok(());
+        loop {
+            let sn = rx.queue.get_base();
+            match rx.pull() {
+                Some(payload) => {
+                    res = self.handle_frame(sn, payload, zlock!(conduit.reliable));
+                    if res.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        // Ask for the retransmission of the frames detected as missing, and acknowledge
+        // the delivered frames periodically and once the missing frames are recovered
+        let mask = rx.get_nack_mask();
+        let is_recovered = !was_empty && rx.queue.is_empty();
+        if mask != 0 || is_recovered || rx.unacked >= rx.queue.capacity() / 4 {
+            rx.unacked = 0;
+            let msg = TransportMessage::make_ack_nack(
+                reliability.priority,
+                rx.queue.get_base(),
+                if mask != 0 { Some(mask) } else { None },
+                None,
+            );
+            drop(rx);
+            self.schedule_transport_message(msg, reliability.priority, true);
+        }
+
+        res
+    }
+
+    fn handle_sync(&self, reliability: &ConduitReliability, sn: ZInt) -> ZResult<()> {
+        // Acknowledge the frames received so far and ask for the missing ones
+        let msg = {
+            let mut rx = zlock!(reliability.rx);
+            rx.unacked = 0;
+            let mask = rx.get_sync_mask(sn)?;
+            TransportMessage::make_ack_nack(
+                reliability.priority,
+                rx.queue.get_base(),
+                if mask != 0 { Some(mask) } else { None },
+                None,
+            )
+        };
+        self.schedule_transport_message(msg, reliability.priority, true);
+        Ok(())
+    }
+
+    fn handle_ack_nack(
+        &self,
+        reliability: &ConduitReliability,
+        sn: ZInt,
+        mask: Option<ZInt>,
+    ) -> ZResult<()> {
+        let is_acked = {
+            let mut tx = zlock!(reliability.tx);
+            let gap = tx.gap(sn)?;
+            if gap > tx.len() as ZInt {
+                // The AckNack refers to frames already acknowledged
+                log::trace!(
+                    "Transport: {}. Outdated AckNack dropped: {}. Expected: {}.",
+                    self.pid,
+                    sn,
+                    tx.get_base()
+                );
+                return Ok(());
+            }
+            if gap > 0 {
+                // All the frames preceding sn have been received
+                tx.set_base(sn)?;
+            }
+            gap > 0
+        };
+        if is_acked {
+            // Some room has been made in the window for the frames in the backlog
+            self.flush_backlog(reliability);
+        }
+
+        if let Some(mask) = mask {
+            self.retransmit(reliability, sn, mask);
+        }
+        Ok(())
+    }
+
+    fn conduit_index(&self, priority: Priority) -> ZResult<usize> {
+        if self.is_qos() {
+            Ok(priority as usize)
+        } else if priority == Priority::default() {
+            Ok(0)
+        } else {
+            let e = format!("Transport: {}. Unknown conduit: {:?}.", self.pid, priority);
+            zerror!(ZErrorKind::InvalidMessage { descr: e })
+        }
+    }
+
     pub(super) fn receive_message(&self, msg: TransportMessage, link: &LinkUnicast) -> ZResult<()> {
         log::trace!("Received: {:?}", msg);
         // Process the received message
@@ -147,25 +271,33 @@ impl TransportUnicastInner {
                 sn,
                 payload,
             }) => {
-                let c = if self.is_qos() {
-                    &self.conduit_rx[channel.priority as usize]
-                } else if channel.priority == Priority::default() {
-                    &self.conduit_rx[0]
-                } else {
-                    let e = format!(
-                        "Transport: {}. Unknown conduit: {:?}.",
-                        self.pid, channel.priority
-                    );
-                    return zerror!(ZErrorKind::InvalidMessage { descr: e });
-                };
+                let index = self.conduit_index(channel.priority)?;
+                let c = &self.conduit_rx[index];
 
                 match channel.reliability {
-                    Reliability::Reliable => self.handle_frame(sn, payload, zlock!(c.reliable)),
+                    Reliability::Reliable => match self.reliability.as_ref() {
+                        Some(r) => self.handle_reliable_frame(&r[index], c, sn, payload),
+                        None => self.handle_frame(sn, payload, zlock!(c.reliable)),
+                    },
                     Reliability::BestEffort => {
                         self.handle_frame(sn, payload, zlock!(c.best_effort))
                     }
                 }
             }
+            TransportBody::Sync(Sync { channel, sn, .. })
+                if channel.reliability == Reliability::Reliable && self.reliability.is_some() =>
+            {
+                let index = self.conduit_index(channel.priority)?;
+                let reliability = self.reliability.as_ref().unwrap();
+                self.handle_sync(&reliability[index], sn)
+            }
+            TransportBody::AckNack(AckNack { priority, sn, mask })
+                if self.reliability.is_some() =>
+            {
+                let index = self.conduit_index(priority)?;
+                let reliability = self.reliability.as_ref().unwrap();
+                self.handle_ack_nack(&reliability[index], sn, mask)
+            }
             TransportBody::Close(Close {
                 pid,
                 reason,
@@ -11,6 +11,7 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use super::super::defaults::ZN_RELIABILITY_WINDOW_SIZE;
 use super::super::{TransportManager, TransportPeerEventHandler};
 use super::common::{
     conduit::{TransportConduitRx, TransportConduitTx},
@@ -19,6 +20,7 @@ use super::common::{
 use super::link::TransportLinkUnicast;
 use super::protocol::core::{ConduitSn, PeerId, Priority, WhatAmI, ZInt};
 use super::protocol::proto::{TransportMessage, ZenohMessage};
+use super::reliability::ConduitReliability;
 use crate::net::link::{Link, LinkUnicast};
 use async_std::sync::{Arc as AsyncArc, Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard};
 use std::convert::TryInto;
@@ -129,8 +131,12 @@ pub(crate) struct TransportUnicastInner {
     pub(super) callback: Arc<RwLock<Option<Arc<dyn TransportPeerEventHandler>>>>,
     // Mutex for notification
     pub(super) alive: AsyncArc<AsyncMutex<bool>>,
+    // The reliability state of each conduit, if reliable frames are retransmitted
+    pub(super) reliability: Option<Arc<[ConduitReliability]>>,
     // The transport can do shm
     pub(super) is_shm: bool,
+    // The batches sent on the links of the transport are compressed
+    pub(super) is_compression: bool,
     // Transport statistics
     #[cfg(feature = "stats")]
     pub(super) stats: TransportUnicastStatsInner,
@@ -145,6 +151,8 @@ pub(crate) struct TransportUnicastConfig {
     pub(crate) initial_sn_rx: ZInt,
     pub(crate) is_shm: bool,
     pub(crate) is_qos: bool,
+    pub(crate) is_compression: bool,
+    pub(crate) is_retransmission: bool,
 }
 
 impl TransportUnicastInner {
@@ -194,6 +202,24 @@ impl TransportUnicastInner {
             ));
         }
 
+        let reliability = if config.is_retransmission {
+            let reliability: Vec<ConduitReliability> = conduit_tx
+                .iter()
+                .map(|c| {
+                    ConduitReliability::new(
+                        c.id,
+                        *ZN_RELIABILITY_WINDOW_SIZE,
+                        config.initial_sn_tx,
+                        config.initial_sn_rx,
+                        config.sn_resolution,
+                    )
+                })
+                .collect();
+            Some(reliability.into_boxed_slice().into())
+        } else {
+            None
+        };
+
         TransportUnicastInner {
             manager: config.manager,
             pid: config.pid,
@@ -204,7 +230,9 @@ impl TransportUnicastInner {
             links: Arc::new(RwLock::new(vec![].into_boxed_slice())),
             callback: Arc::new(RwLock::new(None)),
             alive: AsyncArc::new(AsyncMutex::new(true)),
+            reliability,
             is_shm: config.is_shm,
+            is_compression: config.is_compression,
             #[cfg(feature = "stats")]
             stats: TransportUnicastStatsInner::default(),
         }
@@ -278,6 +306,11 @@ impl TransportUnicastInner {
             });
         }
 
+        // Start retransmitting the unacknowledged frames along with the first link
+        if guard.is_empty() {
+            self.start_sync();
+        }
+
         // Create a channel link from a link
         let link = TransportLinkUnicast::new(self.clone(), link);
 
@@ -421,6 +454,14 @@ impl TransportUnicastInner {
         self.conduit_tx.len() > 1
     }
 
+    pub(crate) fn is_compression(&self) -> bool {
+        self.is_compression
+    }
+
+    pub(crate) fn is_retransmission(&self) -> bool {
+        self.reliability.is_some()
+    }
+
     pub(crate) fn get_callback(&self) -> Option<Arc<dyn TransportPeerEventHandler>> {
         zread!(self.callback).clone()
     }
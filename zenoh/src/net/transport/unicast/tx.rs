@@ -11,13 +11,33 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
-use super::protocol::proto::ZenohMessage;
+use super::super::defaults::{
+    ZN_RELIABILITY_BACKLOG_SIZE, ZN_RELIABILITY_BACKLOG_TIMEOUT, ZN_RELIABILITY_SYNC_INTERVAL,
+};
+use super::protocol::core::{Channel, Priority, Reliability, ZInt};
+use super::protocol::io::{WBuf, ZSlice};
+use super::protocol::proto::{FramePayload, TransportMessage, ZenohMessage};
+use super::reliability::{ConduitReliability, ReliabilityQueue, MASK_BITS};
 use super::transport::TransportUnicastInner;
-use zenoh_util::zread;
+use async_std::task;
+use std::time::{Duration, Instant};
+use zenoh_util::{zlock, zread};
+
+// The maximum size of a frame header: the priority decorator,
+// the header byte, and the sequence number encoded as a ZInt
+const FRAME_HEADER_MAX_SIZE: usize = 12;
 
 impl TransportUnicastInner {
     #[inline(always)]
     pub(super) fn schedule_first_fit(&self, msg: ZenohMessage) {
+        // Reliable messages sent on unreliable links are retransmitted by the transport
+        if msg.is_reliable() {
+            if let Some(reliability) = self.reliability.as_ref() {
+                self.schedule_reliable(reliability, msg);
+                return;
+            }
+        }
+
         macro_rules! zpush {
             ($guard:expr, $pipeline:expr, $msg:expr) => {
                 // Drop the guard before the push_zenoh_message since
@@ -58,3 +78,289 @@ impl TransportUnicastInner {
         );
     }
 }
+
+/*************************************/
+/*       RELIABILITY TX              */
+/*************************************/
+impl TransportUnicastInner {
+    /// Schedule a transport message on the first available link, preferring reliable links.
+    /// A droppable message is dropped instead of waiting for a congested link.
+    pub(super) fn schedule_transport_message(
+        &self,
+        msg: TransportMessage,
+        priority: Priority,
+        is_droppable: bool,
+    ) {
+        let pipeline = {
+            let guard = zread!(self.links);
+            guard
+                .iter()
+                .filter(|l| l.get_link().is_reliable())
+                .chain(guard.iter())
+                .find_map(|l| l.get_pipeline())
+        };
+        match pipeline {
+            Some(pipeline) => {
+                if is_droppable {
+                    pipeline.try_push_transport_message(msg, priority);
+                } else {
+                    pipeline.push_transport_message(msg, priority);
+                }
+            }
+            None => log::trace!(
+                "Message dropped because the transport has no links: {:?}",
+                msg
+            ),
+        }
+    }
+
+    /// Schedule a reliable message when the links are not reliable. The message is serialized
+    /// in frames that are kept in the retransmission queue of the conduit until acknowledged.
+    ///
+    /// When the window is full, the frames are pushed back in the backlog of the conduit, flushed
+    /// upon acknowledgment. When the backlog is full as well, a droppable message is dropped, while
+    /// a blocking one waits for the acknowledgments to make room. This wait is bounded since the
+    /// caller may hold locks (e.g. the routing tables) needed to process the incoming AckNacks.
+    fn schedule_reliable(&self, reliability: &[ConduitReliability], mut msg: ZenohMessage) {
+        let index = if self.is_qos() {
+            msg.channel.priority as usize
+        } else {
+            msg.channel.priority = Priority::default();
+            0
+        };
+        let conduit = &reliability[index];
+        let is_droppable = msg.is_droppable();
+
+        let payloads = match self.make_frame_payloads(msg) {
+            Some(payloads) => payloads,
+            None => {
+                log::trace!("Message dropped because the transport has no links");
+                return;
+            }
+        };
+
+        // The locks are not held while pushing on the pipeline since the link could be congested,
+        // the frames are anyway reordered by the remote peer
+        let deadline = Instant::now() + Duration::from_millis(*ZN_RELIABILITY_BACKLOG_TIMEOUT);
+        let frames: Vec<TransportMessage> = loop {
+            let mut tx = zlock!(conduit.tx);
+            if payloads.len() > tx.capacity() {
+                log::warn!(
+                    "Transport: {}. Message dropped because it exceeds the reliability window: {} frames",
+                    self.pid,
+                    payloads.len()
+                );
+                return;
+            }
+
+            // The frames already in the backlog are sent first to preserve the order of the messages
+            let mut backlog = zlock!(conduit.backlog);
+            if backlog.is_empty() && tx.capacity() - tx.len() >= payloads.len() {
+                drop(backlog);
+                break self.make_reliable_frames(conduit, &mut tx, payloads);
+            }
+
+            let is_full = !backlog.is_empty()
+                && backlog.len() + payloads.len() > *ZN_RELIABILITY_BACKLOG_SIZE;
+            if is_full {
+                if is_droppable {
+                    log::trace!(
+                        "Transport: {}. Message dropped because the reliability backlog is full",
+                        self.pid
+                    );
+                    return;
+                }
+
+                let now = Instant::now();
+                if now < deadline {
+                    // Wait for the acknowledgments to make room, without holding the window
+                    drop(tx);
+                    if zread!(self.links).is_empty() {
+                        log::trace!("Message dropped because the transport has no links");
+                        return;
+                    }
+                    let timeout =
+                        (deadline - now).min(Duration::from_millis(*ZN_RELIABILITY_SYNC_INTERVAL));
+                    let _ = conduit.backlog_room.wait_timeout(backlog, timeout);
+                    continue;
+                }
+                log::warn!(
+                    "Transport: {}. No room made in the reliability backlog after {} ms: message added anyway",
+                    self.pid,
+                    *ZN_RELIABILITY_BACKLOG_TIMEOUT
+                );
+            }
+
+            let is_first = backlog.is_empty();
+            backlog.extend(payloads);
+            if is_first {
+                // Ask the remote peer to acknowledge the frames received so far
+                let sync = make_sync(conduit, &tx, self.sn_resolution);
+                drop(backlog);
+                drop(tx);
+                self.schedule_transport_message(sync, conduit.priority, true);
+            }
+            return;
+        };
+
+        for frame in frames.into_iter() {
+            self.schedule_transport_message(frame, conduit.priority, false);
+        }
+    }
+
+    /// Send the frames of the backlog fitting in the window, after some frames have been acknowledged.
+    pub(super) fn flush_backlog(&self, conduit: &ConduitReliability) {
+        let frames: Vec<TransportMessage> = {
+            let mut tx = zlock!(conduit.tx);
+            let mut backlog = zlock!(conduit.backlog);
+            let count = (tx.capacity() - tx.len()).min(backlog.len());
+            if count == 0 {
+                return;
+            }
+            let payloads: Vec<FramePayload> = backlog.drain(..count).collect();
+            drop(backlog);
+            conduit.backlog_room.notify_all();
+            self.make_reliable_frames(conduit, &mut tx, payloads)
+        };
+
+        // The frames are kept until acknowledged: the ones dropped because the link
+        // is congested will be retransmitted
+        for frame in frames.into_iter() {
+            self.schedule_transport_message(frame, conduit.priority, true);
+        }
+    }
+
+    /// Assign the SNs to the payloads and keep them in the window until acknowledged.
+    /// The window must have room for all the payloads.
+    fn make_reliable_frames(
+        &self,
+        conduit: &ConduitReliability,
+        tx: &mut ReliabilityQueue<FramePayload>,
+        payloads: Vec<FramePayload>,
+    ) -> Vec<TransportMessage> {
+        let index = if self.is_qos() {
+            conduit.priority as usize
+        } else {
+            0
+        };
+        let channel = Channel {
+            priority: conduit.priority,
+            reliability: Reliability::Reliable,
+        };
+        let mut ch_guard = zlock!(self.conduit_tx[index].reliable);
+        payloads
+            .into_iter()
+            .map(|payload| {
+                let sn = ch_guard.sn.get();
+                // The insertion can not fail since there is room in the window
+                let _ = tx.insert(payload.clone(), sn);
+                TransportMessage::make_frame(channel, sn, payload, None)
+            })
+            .collect()
+    }
+
+    /// Serialize a Zenoh message in frame payloads fitting in the batches of the links.
+    fn make_frame_payloads(&self, mut msg: ZenohMessage) -> Option<Vec<FramePayload>> {
+        let mtu = zread!(self.links)
+            .iter()
+            .filter(|l| l.get_pipeline().is_some())
+            .map(|l| l.get_link().get_mtu())
+            .min()?;
+        let max_len = mtu.min(self.manager.config.batch_size) as usize - FRAME_HEADER_MAX_SIZE;
+
+        let mut wbuf = WBuf::new(max_len, false);
+        wbuf.write_zenoh_message(&mut msg);
+        if wbuf.len() <= max_len {
+            return Some(vec![FramePayload::Messages {
+                messages: vec![msg],
+            }]);
+        }
+
+        // The message is too large for a single frame, fragment it
+        let mut bytes = vec![0u8; wbuf.len()];
+        wbuf.copy_into_slice(&mut bytes);
+        let buffer = ZSlice::from(bytes);
+        let mut payloads = vec![];
+        let mut start = 0;
+        while start < buffer.len() {
+            let end = (start + max_len).min(buffer.len());
+            payloads.push(FramePayload::Fragment {
+                buffer: buffer.new_sub_slice(start, end),
+                is_final: end == buffer.len(),
+            });
+            start = end;
+        }
+        Some(payloads)
+    }
+
+    /// Retransmit the frames marked as missing in the mask of an AckNack.
+    pub(super) fn retransmit(&self, conduit: &ConduitReliability, sn: ZInt, mask: ZInt) {
+        let channel = Channel {
+            priority: conduit.priority,
+            reliability: Reliability::Reliable,
+        };
+        let frames: Vec<TransportMessage> = {
+            let mut tx = zlock!(conduit.tx);
+            (0..MASK_BITS)
+                .filter(|i| mask & (1 << i) != 0)
+                .filter_map(|i| {
+                    let sn = (sn + i as ZInt) % self.sn_resolution;
+                    tx.get(sn)
+                        .ok()
+                        .map(|payload| TransportMessage::make_frame(channel, sn, payload, None))
+                })
+                .collect()
+        };
+        for frame in frames.into_iter() {
+            log::trace!("Transport: {}. Retransmitting: {:?}", self.pid, frame);
+            self.schedule_transport_message(frame, conduit.priority, true);
+        }
+    }
+
+    /// Spawn the task periodically sending a Sync for the frames not yet acknowledged.
+    /// The task stops once the transport has no links left.
+    pub(super) fn start_sync(&self) {
+        if self.reliability.is_none() {
+            return;
+        }
+
+        let c_transport = self.clone();
+        task::spawn(async move {
+            let period = Duration::from_millis(*ZN_RELIABILITY_SYNC_INTERVAL);
+            loop {
+                task::sleep(period).await;
+                if zread!(c_transport.links).is_empty() {
+                    break;
+                }
+
+                if let Some(reliability) = c_transport.reliability.as_ref() {
+                    for conduit in reliability.iter() {
+                        let sync = {
+                            let tx = zlock!(conduit.tx);
+                            if tx.is_empty() {
+                                continue;
+                            }
+                            make_sync(conduit, &tx, c_transport.sn_resolution)
+                        };
+                        c_transport.schedule_transport_message(sync, conduit.priority, true);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Make a Sync carrying the SN of the next frame to be sent and the number of
+/// frames not yet acknowledged.
+fn make_sync(
+    conduit: &ConduitReliability,
+    tx: &ReliabilityQueue<FramePayload>,
+    sn_resolution: ZInt,
+) -> TransportMessage {
+    let channel = Channel {
+        priority: conduit.priority,
+        reliability: Reliability::Reliable,
+    };
+    let sn = (tx.get_base() + tx.len() as ZInt) % sn_resolution;
+    TransportMessage::make_sync(channel, sn, Some(tx.len() as ZInt), None)
+}
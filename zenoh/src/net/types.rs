@@ -11,13 +11,15 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use super::session::{cancel_query, SessionState};
 use crate::net::Session;
 use crate::utils::new_reception_timestamp;
-use async_std::sync::Arc;
+use async_std::sync::{Arc, Weak};
 use flume::*;
 use std::collections::HashMap;
 use std::fmt;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::RwLock;
 use std::task::{Context, Poll};
 use uhlc::Timestamp;
@@ -157,6 +159,28 @@ impl Sample {
     }
 }
 
+/// An error replied by a [Queryable](Queryable) that failed to answer a query.
+///
+/// The `payload` describes the error (typically an UTF-8 message) and `res_name` is the
+/// resource the error relates to.
+#[derive(Debug, Clone)]
+pub struct ReplyError {
+    pub res_name: String,
+    pub payload: ZBuf,
+    pub data_info: Option<DataInfo>,
+}
+
+impl fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.res_name,
+            String::from_utf8_lossy(self.payload.contiguous().as_slice())
+        )
+    }
+}
+
 /// The callback that will be called on each data for a [CallbackSubscriber](CallbackSubscriber).
 pub type DataHandler = dyn FnMut(Sample) + Send + Sync + 'static;
 
@@ -182,6 +206,19 @@ impl Query {
     pub async fn reply_async(&'_ self, msg: Sample) {
         self.replies_sender.send_async(msg).await
     }
+
+    /// Replies an error to the querier, e.g. if the queryable failed to answer this query.
+    #[inline(always)]
+    pub fn reply_error(&'_ self, error: ReplyError) {
+        self.replies_sender.send_error(error)
+    }
+
+    /// Returns `true` if the querier cancelled this query: the replies are no longer expected
+    /// and the queryable can stop working on it.
+    #[inline(always)]
+    pub fn is_cancelled(&self) -> bool {
+        self.replies_sender.is_cancelled()
+    }
 }
 
 impl fmt::Debug for Query {
@@ -195,9 +232,11 @@ impl fmt::Debug for Query {
 }
 
 /// Structs returned by a [query](Session::query).
+///
+/// `data` is an `Err` if the replier failed to answer the query.
 #[derive(Clone, Debug)]
 pub struct Reply {
-    pub data: Sample,
+    pub data: Result<Sample, ReplyError>,
     pub replier_kind: ZInt,
     pub replier_id: PeerId,
 }
@@ -208,6 +247,7 @@ pub(crate) struct QueryState {
     pub(crate) reception_mode: ConsolidationMode,
     pub(crate) replies: Option<HashMap<String, Reply>>,
     pub(crate) rep_sender: Sender<Reply>,
+    pub(crate) partial: Arc<AtomicBool>,
 }
 
 pub(crate) type Id = usize;
@@ -227,7 +267,48 @@ pub struct Publisher<'a> {
     pub(crate) alive: bool,
 }
 
-impl Publisher<'_> {
+impl<'a> Publisher<'a> {
+    /// Returns true if at least one subscriber currently matches the resource of this [Publisher](Publisher).
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::net::*;
+    ///
+    /// let session = open(config::peer()).await.unwrap();
+    /// let publisher = session.declare_publisher(&"/resource/name".into()).await.unwrap();
+    /// if publisher.is_matching().await.unwrap() {
+    ///     session.write(&"/resource/name".into(), "value".as_bytes().into()).await.unwrap();
+    /// }
+    /// # })
+    /// ```
+    pub fn is_matching(&self) -> impl ZFuture<Output = ZResult<bool>> {
+        self.session.is_matching(&self.state.reskey)
+    }
+
+    /// Declare a [MatchingListener](MatchingListener) notified each time the matching status of this
+    /// [Publisher](Publisher) changes, i.e. when the first subscriber appears or the last one disappears.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::net::*;
+    /// use futures::prelude::*;
+    ///
+    /// let session = open(config::peer()).await.unwrap();
+    /// let publisher = session.declare_publisher(&"/resource/name".into()).await.unwrap();
+    /// let mut listener = publisher.declare_matching_listener().await.unwrap();
+    /// async_std::task::spawn(listener.receiver().clone().for_each(
+    ///     move |status| async move { println!("Matching subscribers: {}", status.matching); }
+    /// ));
+    /// # })
+    /// ```
+    pub fn declare_matching_listener(
+        &self,
+    ) -> impl ZFuture<Output = ZResult<MatchingListener<'a>>> {
+        self.session.declare_matching_listener(&self.state.reskey)
+    }
+
     /// Undeclare a [Publisher](Publisher) previously declared with [declare_publisher](Session::declare_publisher).
     ///
     /// Publishers are automatically undeclared when dropped, but you may want to use this function to handle errors or
@@ -264,6 +345,74 @@ impl fmt::Debug for Publisher<'_> {
     }
 }
 
+/// The matching status of a [Publisher](Publisher).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchingStatus {
+    /// True if at least one subscriber matches the resource of the [Publisher](Publisher).
+    pub matching: bool,
+}
+
+zreceiver! {
+    /// The changes of the matching status of a [Publisher](Publisher).
+    #[derive(Clone)]
+    pub struct MatchingStatusReceiver : Receiver<MatchingStatus> {}
+}
+
+/// A listener of the changes of the matching status of a [Publisher](Publisher).
+///
+/// MatchingListeners are automatically undeclared when dropped.
+pub struct MatchingListener<'a> {
+    pub(crate) session: &'a Session,
+    pub(crate) id: Id,
+    pub(crate) alive: bool,
+    pub(crate) receiver: MatchingStatusReceiver,
+}
+
+impl MatchingListener<'_> {
+    pub fn receiver(&mut self) -> &mut MatchingStatusReceiver {
+        &mut self.receiver
+    }
+
+    /// Undeclare a [MatchingListener](MatchingListener) previously declared with
+    /// [declare_matching_listener](Publisher::declare_matching_listener).
+    ///
+    /// MatchingListeners are automatically undeclared when dropped, but you may want to use this function to handle errors or
+    /// undeclare the MatchingListener asynchronously.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::net::*;
+    ///
+    /// let session = open(config::peer()).await.unwrap();
+    /// let publisher = session.declare_publisher(&"/resource/name".into()).await.unwrap();
+    /// let listener = publisher.declare_matching_listener().await.unwrap();
+    /// listener.undeclare().await.unwrap();
+    /// # })
+    /// ```
+    #[inline]
+    pub fn undeclare(mut self) -> impl ZFuture<Output = ZResult<()>> {
+        self.alive = false;
+        self.session.undeclare_matching_listener(self.id)
+    }
+}
+
+impl Drop for MatchingListener<'_> {
+    fn drop(&mut self) {
+        if self.alive {
+            let _ = self.session.undeclare_matching_listener(self.id).wait();
+        }
+    }
+}
+
+impl fmt::Debug for MatchingListener<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MatchingListener")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
 pub(crate) enum SubscriberInvoker {
     Sender(Sender<Sample>),
     Handler(Arc<RwLock<DataHandler>>),
@@ -447,9 +596,49 @@ impl fmt::Debug for CallbackSubscriber<'_> {
     }
 }
 
+// Cancels a query when dropped, i.e. when the last clone of its ReplyReceiver is dropped
+pub(crate) struct QueryHandle {
+    pub(crate) qid: ZInt,
+    pub(crate) state: Weak<RwLock<SessionState>>,
+}
+
+impl QueryHandle {
+    fn cancel(&self) {
+        if let Some(state) = self.state.upgrade() {
+            cancel_query(&state, self.qid);
+        }
+    }
+}
+
+impl Drop for QueryHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
 zreceiver! {
+    /// The replies of a [query](Session::query).
+    ///
+    /// The query is cancelled when all the clones of its ReplyReceiver are dropped.
     #[derive(Clone)]
-    pub struct ReplyReceiver : Receiver<Reply> {}
+    pub struct ReplyReceiver : Receiver<Reply> {
+        pub(crate) partial: Arc<AtomicBool>,
+        pub(crate) handle: Arc<QueryHandle>,
+    }
+}
+
+impl ReplyReceiver {
+    /// Returns `true` if the query timed out before all the matching queryables replied,
+    /// meaning that the received replies may be incomplete.
+    pub fn is_partial(&self) -> bool {
+        self.partial.load(Ordering::Relaxed)
+    }
+
+    /// Cancels the query: the matching queryables are notified (see [`Query::is_cancelled()`])
+    /// and the replies that were not received yet are discarded.
+    pub fn cancel(&self) {
+        self.handle.cancel()
+    }
 }
 
 pub(crate) struct QueryableState {
@@ -522,27 +711,67 @@ impl fmt::Debug for Queryable<'_> {
     }
 }
 
+/// A token signaling the liveliness of an application on a resource key
+/// (see [declare_liveliness_token](Session::declare_liveliness_token)).
+///
+/// Liveliness tokens are automatically undeclared when dropped.
+pub struct LivelinessToken<'a> {
+    pub(crate) queryable: Queryable<'a>,
+}
+
+impl LivelinessToken<'_> {
+    /// Undeclare a [LivelinessToken](LivelinessToken) previously declared with
+    /// [declare_liveliness_token](Session::declare_liveliness_token).
+    #[inline]
+    pub fn undeclare(self) -> impl ZFuture<Output = ZResult<()>> {
+        self.queryable.undeclare()
+    }
+}
+
+impl fmt::Debug for LivelinessToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.queryable.fmt(f)
+    }
+}
+
 /// Struct used by a [Queryable](Queryable) to send replies to queries.
 #[derive(Clone)]
 pub struct RepliesSender {
     pub(crate) kind: ZInt,
-    pub(crate) sender: Sender<(ZInt, Sample)>,
+    pub(crate) sender: Sender<(ZInt, Result<Sample, ReplyError>)>,
+    pub(crate) cancelled: Arc<AtomicBool>,
 }
 
 impl RepliesSender {
+    /// Returns `true` if the querier cancelled the query.
+    #[inline(always)]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
     #[inline(always)]
     pub fn send(&'_ self, msg: Sample) {
-        if let Err(e) = self.sender.send((self.kind, msg)) {
+        if let Err(e) = self.sender.send((self.kind, Ok(msg))) {
             log::error!("Error sending reply: {}", e);
         }
     }
 
+    #[inline(always)]
+    pub fn send_error(&'_ self, error: ReplyError) {
+        if let Err(e) = self.sender.send((self.kind, Err(error))) {
+            log::error!("Error sending reply error: {}", e);
+        }
+    }
+
     #[inline(always)]
     pub fn try_send(&self, msg: Sample) -> Result<(), TrySendError<Sample>> {
-        match self.sender.try_send((self.kind, msg)) {
+        match self.sender.try_send((self.kind, Ok(msg))) {
             Ok(()) => Ok(()),
-            Err(TrySendError::Full(sample)) => Err(TrySendError::Full(sample.1)),
-            Err(TrySendError::Disconnected(sample)) => Err(TrySendError::Disconnected(sample.1)),
+            Err(TrySendError::Full((_, Ok(sample)))) => Err(TrySendError::Full(sample)),
+            Err(TrySendError::Disconnected((_, Ok(sample)))) => {
+                Err(TrySendError::Disconnected(sample))
+            }
+            Err(_) => unreachable!(),
         }
     }
 
@@ -568,7 +797,7 @@ impl RepliesSender {
 
     #[inline(always)]
     pub async fn send_async(&self, msg: Sample) {
-        if let Err(e) = self.sender.send_async((self.kind, msg)).await {
+        if let Err(e) = self.sender.send_async((self.kind, Ok(msg))).await {
             log::error!("Error sending reply: {}", e);
         }
     }
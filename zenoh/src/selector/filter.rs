@@ -0,0 +1,384 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use crate::net::Sample;
+use crate::Value;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use zenoh_util::core::{ZError, ZErrorKind, ZResult};
+use zenoh_util::zerror;
+
+/// The field name to be used in a [`Filter`] predicate to designate the value itself
+/// when it's not a structured value (i.e. a [`Value::Integer`], a [`Value::Float`],
+/// a [`Value::StringUtf8`] or a [`Value::Json`] that is not an object).
+pub const SCALAR_FIELD: &str = "value";
+
+/// A comparison operator used in a [`Predicate`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    /// `<`
+    Lt,
+    /// `>`
+    Gt,
+    /// `<=`
+    Le,
+    /// `>=`
+    Ge,
+    /// `=` (or `==`)
+    Eq,
+    /// `!=`
+    Ne,
+}
+
+impl Operator {
+    fn eval(self, ord: Ordering) -> bool {
+        match self {
+            Operator::Lt => ord == Ordering::Less,
+            Operator::Gt => ord == Ordering::Greater,
+            Operator::Le => ord != Ordering::Greater,
+            Operator::Ge => ord != Ordering::Less,
+            Operator::Eq => ord == Ordering::Equal,
+            Operator::Ne => ord != Ordering::Equal,
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Operator::Lt => "<",
+            Operator::Gt => ">",
+            Operator::Le => "<=",
+            Operator::Ge => ">=",
+            Operator::Eq => "=",
+            Operator::Ne => "!=",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A predicate of a [`Filter`], with the form "`field`-`operator`-`value`".
+///
+/// The `field` can designate a nested field in a JSON object, using `'.'` as a separator (e.g. `a.b.c`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Predicate {
+    pub field: String,
+    pub op: Operator,
+    pub value: String,
+}
+
+impl Predicate {
+    /// Returns true if the field designated by this Predicate exists in `value`
+    /// and satisfies the comparison.
+    pub fn matches(&self, value: &Value) -> bool {
+        match value {
+            Value::Json(s) => match serde_json::from_str::<serde_json::Value>(s) {
+                Ok(json) => {
+                    let field = if json.is_object() {
+                        self.field
+                            .split('.')
+                            .try_fold(&json, |json, name| json.get(name))
+                    } else if self.field == SCALAR_FIELD {
+                        Some(&json)
+                    } else {
+                        None
+                    };
+                    match field {
+                        Some(field) => self.matches_json(field),
+                        None => false,
+                    }
+                }
+                Err(_) => false,
+            },
+            Value::Properties(props) => match props.get(&self.field) {
+                Some(v) => self.matches_str(v),
+                None => false,
+            },
+            Value::Integer(i) if self.field == SCALAR_FIELD => self.matches_number(*i as f64),
+            Value::Float(f) if self.field == SCALAR_FIELD => self.matches_number(*f),
+            Value::StringUtf8(s) if self.field == SCALAR_FIELD => self.matches_str(s),
+            _ => false,
+        }
+    }
+
+    fn matches_json(&self, json: &serde_json::Value) -> bool {
+        use serde_json::Value::*;
+        match json {
+            Number(n) => match n.as_f64() {
+                Some(n) => self.matches_number(n),
+                None => false,
+            },
+            String(s) => self.matches_string(s),
+            Bool(b) => match self.value.as_str() {
+                "true" => self.op.eval(b.cmp(&true)),
+                "false" => self.op.eval(b.cmp(&false)),
+                _ => false,
+            },
+            Null => match self.op {
+                Operator::Eq => self.value == "null",
+                Operator::Ne => self.value != "null",
+                _ => false,
+            },
+            Array(_) | Object(_) => false,
+        }
+    }
+
+    // Compares a string that could represent a number (e.g. in Properties)
+    fn matches_str(&self, s: &str) -> bool {
+        match s.parse::<f64>() {
+            Ok(n) if self.value.parse::<f64>().is_ok() => self.matches_number(n),
+            _ => self.matches_string(s),
+        }
+    }
+
+    fn matches_number(&self, n: f64) -> bool {
+        match self.value.parse::<f64>() {
+            Ok(v) => match n.partial_cmp(&v) {
+                Some(ord) => self.op.eval(ord),
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    fn matches_string(&self, s: &str) -> bool {
+        self.op.eval(s.cmp(unquote(&self.value)))
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.field, self.op, self.value)
+    }
+}
+
+impl TryFrom<&str> for Predicate {
+    type Error = ZError;
+    fn try_from(s: &str) -> ZResult<Predicate> {
+        let invalid = || {
+            zerror!(ZErrorKind::InvalidSelector {
+                selector: format!("invalid predicate in filter: '{}'", s)
+            })
+        };
+        let i = match s.find(&['<', '>', '=', '!'][..]) {
+            Some(i) if i > 0 => i,
+            _ => return invalid(),
+        };
+        let (field, rem) = s.split_at(i);
+        let (op, value) = if let Some(v) = rem.strip_prefix("<=") {
+            (Operator::Le, v)
+        } else if let Some(v) = rem.strip_prefix(">=") {
+            (Operator::Ge, v)
+        } else if let Some(v) = rem.strip_prefix("!=") {
+            (Operator::Ne, v)
+        } else if let Some(v) = rem.strip_prefix("==") {
+            (Operator::Eq, v)
+        } else if let Some(v) = rem.strip_prefix('<') {
+            (Operator::Lt, v)
+        } else if let Some(v) = rem.strip_prefix('>') {
+            (Operator::Gt, v)
+        } else if let Some(v) = rem.strip_prefix('=') {
+            (Operator::Eq, v)
+        } else {
+            return invalid();
+        };
+        if value.is_empty() {
+            return invalid();
+        }
+        Ok(Predicate {
+            field: field.trim().to_string(),
+            op,
+            value: value.trim().to_string(),
+        })
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    if s.len() >= 2
+        && ((s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')))
+    {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
+
+/// A filter on values, as specified in the filter part of a [`Selector`](super::Selector)
+/// (i.e. `x>1&y<2&...&z=4`).
+///
+/// A value matches the filter if it matches all its [`Predicate`]s.
+/// The predicates apply to the fields of [`Value::Json`] objects and of [`Value::Properties`].
+/// For [`Value::Integer`], [`Value::Float`], [`Value::StringUtf8`] and non-object [`Value::Json`],
+/// the [`SCALAR_FIELD`] name (i.e. `"value"`) designates the value itself.
+///
+/// The comparison is numerical if both the field's value and the predicate's value are numbers.
+/// Otherwise, it's a lexicographic comparison of strings (the predicate's value can be quoted).
+///
+/// # Examples
+/// ```
+/// use zenoh::selector::Filter;
+/// use zenoh::Value;
+/// use std::convert::TryFrom;
+///
+/// let filter = Filter::try_from("temp>20&room.floor=2&room.name!='kitchen'").unwrap();
+/// assert!(filter.matches(&Value::Json(
+///     r#"{"temp": 21.5, "room": {"floor": 2, "name": "office"}}"#.into()
+/// )));
+/// assert!(!filter.matches(&Value::Json(
+///     r#"{"temp": 19, "room": {"floor": 2, "name": "office"}}"#.into()
+/// )));
+///
+/// let filter = Filter::try_from("value>=10").unwrap();
+/// assert!(filter.matches(&Value::Integer(10)));
+/// assert!(!filter.matches(&Value::Float(9.5)));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub predicates: Vec<Predicate>,
+}
+
+impl Filter {
+    /// Returns true if `value` matches all the predicates of this Filter.
+    pub fn matches(&self, value: &Value) -> bool {
+        self.predicates.iter().all(|p| p.matches(value))
+    }
+
+    /// Returns true if the payload of `sample` matches all the predicates of this Filter.
+    /// A Sample that can't be decoded as a [`Value`], or that corresponds to a deletion, never matches.
+    pub fn matches_sample(&self, sample: &Sample) -> bool {
+        match Value::from_sample(sample, true) {
+            Ok(Some(value)) => self.matches(&value),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, p) in self.predicates.iter().enumerate() {
+            if i > 0 {
+                write!(f, "&")?;
+            }
+            write!(f, "{}", p)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&str> for Filter {
+    type Error = ZError;
+    fn try_from(s: &str) -> ZResult<Filter> {
+        let predicates = s
+            .split('&')
+            .filter(|p| !p.trim().is_empty())
+            .map(Predicate::try_from)
+            .collect::<ZResult<Vec<Predicate>>>()?;
+        Ok(Filter { predicates })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Properties;
+
+    #[test]
+    fn test_filter_parsing() {
+        let filter = Filter::try_from("x>1&y<=2&z!=abc&t==4").unwrap();
+        assert_eq!(
+            filter.predicates,
+            vec![
+                Predicate {
+                    field: "x".into(),
+                    op: Operator::Gt,
+                    value: "1".into()
+                },
+                Predicate {
+                    field: "y".into(),
+                    op: Operator::Le,
+                    value: "2".into()
+                },
+                Predicate {
+                    field: "z".into(),
+                    op: Operator::Ne,
+                    value: "abc".into()
+                },
+                Predicate {
+                    field: "t".into(),
+                    op: Operator::Eq,
+                    value: "4".into()
+                },
+            ]
+        );
+        assert_eq!(filter.to_string(), "x>1&y<=2&z!=abc&t=4");
+
+        assert!(Filter::try_from("x").is_err());
+        assert!(Filter::try_from(">1").is_err());
+        assert!(Filter::try_from("x>").is_err());
+        assert!(Filter::try_from("x=!1").is_ok());
+    }
+
+    #[test]
+    fn test_filter_json() {
+        let value = Value::Json(
+            r#"{"n": 3, "s": "abc", "b": true, "o": {"x": 1.5}, "z": null}"#.to_string(),
+        );
+        let check = |f: &str| Filter::try_from(f).unwrap().matches(&value);
+        assert!(check("n>2"));
+        assert!(check("n>=3&n<=3"));
+        assert!(!check("n<3"));
+        assert!(check("s=abc"));
+        assert!(check("s='abc'"));
+        assert!(check("s<abd"));
+        assert!(check("b=true"));
+        assert!(!check("b!=true"));
+        assert!(check("o.x>1"));
+        assert!(!check("o.y>1"));
+        assert!(check("z=null"));
+        assert!(!check("missing=1"));
+        assert!(!check("o>1"));
+
+        let value = Value::Json("42".to_string());
+        assert!(Filter::try_from("value=42").unwrap().matches(&value));
+        assert!(!Filter::try_from("x=42").unwrap().matches(&value));
+    }
+
+    #[test]
+    fn test_filter_properties_and_scalars() {
+        let value = Value::Properties(Properties::from("a=10;b=hello"));
+        let check = |f: &str| Filter::try_from(f).unwrap().matches(&value);
+        assert!(check("a>9"));
+        // numerical, not lexicographic comparison
+        assert!(check("a>9.5&a<100"));
+        assert!(check("b=hello"));
+        assert!(check("b>hallo"));
+        assert!(!check("c=1"));
+
+        assert!(Filter::try_from("value<0")
+            .unwrap()
+            .matches(&Value::Integer(-3)));
+        assert!(Filter::try_from("value!=1.5")
+            .unwrap()
+            .matches(&Value::Float(2.0)));
+        assert!(!Filter::try_from("x<0")
+            .unwrap()
+            .matches(&Value::Integer(-3)));
+        assert!(Filter::try_from("value=abc")
+            .unwrap()
+            .matches(&Value::StringUtf8("abc".into())));
+        assert!(!Filter::try_from("value=abc")
+            .unwrap()
+            .matches(&Value::Raw(0, vec![0x61, 0x62, 0x63].into())));
+    }
+}
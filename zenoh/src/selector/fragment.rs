@@ -0,0 +1,214 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use crate::net::Sample;
+use crate::{Properties, Value};
+use serde_json::map::Map;
+use std::convert::TryFrom;
+use std::fmt;
+use zenoh_util::core::{ZError, ZErrorKind, ZResult};
+use zenoh_util::zerror;
+
+/// A fragment, as specified in the fragment part of a [`Selector`](super::Selector)
+/// (i.e. `[a;b;x;y;...;z]`), allowing to select only some fields of structured values.
+///
+/// The projection applies to [`Value::Json`] objects and to [`Value::Properties`].
+/// A field can designate a nested field in a JSON object, using `'.'` as a separator (e.g. `a.b.c`).
+/// The fields that don't exist in a value are ignored.
+/// Other kinds of values are left unchanged.
+///
+/// # Examples
+/// ```
+/// use zenoh::selector::Fragment;
+/// use zenoh::Value;
+/// use std::convert::TryFrom;
+///
+/// let fragment = Fragment::try_from("temp;room.name").unwrap();
+/// let value = Value::Json(
+///     r#"{"temp": 21.5, "hum": 40, "room": {"floor": 2, "name": "office"}}"#.into()
+/// );
+/// match fragment.project(value) {
+///     Value::Json(s) => assert_eq!(s, r#"{"room":{"name":"office"},"temp":21.5}"#),
+///     _ => panic!(),
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fragment {
+    pub fields: Vec<String>,
+}
+
+impl Fragment {
+    /// Returns a new [`Value`] with only the fields selected by this Fragment.
+    pub fn project(&self, value: Value) -> Value {
+        match value {
+            Value::Json(s) => match serde_json::from_str::<serde_json::Value>(&s) {
+                Ok(serde_json::Value::Object(map)) => {
+                    let mut result = Map::new();
+                    for field in self.fields.iter() {
+                        let path: Vec<&str> = field.split('.').collect();
+                        if let Some(v) = get_path(&map, &path) {
+                            insert_path(&mut result, &path, v.clone());
+                        }
+                    }
+                    Value::Json(serde_json::Value::Object(result).to_string())
+                }
+                _ => Value::Json(s),
+            },
+            Value::Properties(props) => Value::Properties(Properties(
+                props
+                    .0
+                    .into_iter()
+                    .filter(|(k, _)| self.fields.contains(k))
+                    .collect(),
+            )),
+            value => value,
+        }
+    }
+
+    /// Returns a new [`Sample`] with a payload containing only the fields selected by this Fragment.
+    /// If the payload can't be decoded as a [`Value`], the Sample is returned unchanged.
+    pub fn project_sample(&self, sample: Sample) -> Sample {
+        match Value::from_sample(&sample, true) {
+            Ok(Some(value @ Value::Json(_))) | Ok(Some(value @ Value::Properties(_))) => {
+                let (encoding, payload) = self.project(value).encode();
+                let mut info = sample.data_info.unwrap_or_default();
+                info.encoding = Some(encoding);
+                Sample {
+                    res_name: sample.res_name,
+                    payload,
+                    data_info: Some(info),
+                }
+            }
+            _ => sample,
+        }
+    }
+}
+
+fn get_path<'a>(
+    map: &'a Map<String, serde_json::Value>,
+    path: &[&str],
+) -> Option<&'a serde_json::Value> {
+    let v = map.get(path[0])?;
+    if path.len() == 1 {
+        Some(v)
+    } else {
+        get_path(v.as_object()?, &path[1..])
+    }
+}
+
+fn insert_path(map: &mut Map<String, serde_json::Value>, path: &[&str], v: serde_json::Value) {
+    if path.len() == 1 {
+        map.insert(path[0].to_string(), v);
+    } else if let serde_json::Value::Object(sub) = map
+        .entry(path[0])
+        .or_insert_with(|| serde_json::Value::Object(Map::new()))
+    {
+        insert_path(sub, &path[1..], v);
+    }
+}
+
+impl fmt::Display for Fragment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.fields.join(";"))
+    }
+}
+
+impl TryFrom<&str> for Fragment {
+    type Error = ZError;
+    fn try_from(s: &str) -> ZResult<Fragment> {
+        let fields: Vec<String> = s
+            .split(';')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(String::from)
+            .collect();
+        if fields.is_empty() || fields.iter().any(|f| f.split('.').any(str::is_empty)) {
+            return zerror!(ZErrorKind::InvalidSelector {
+                selector: format!("invalid fragment: '[{}]'", s)
+            });
+        }
+        Ok(Fragment { fields })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::encoding;
+
+    fn json(s: &str) -> serde_json::Value {
+        serde_json::from_str(s).unwrap()
+    }
+
+    fn project_json(fragment: &str, value: &str) -> serde_json::Value {
+        match Fragment::try_from(fragment)
+            .unwrap()
+            .project(Value::Json(value.into()))
+        {
+            Value::Json(s) => json(&s),
+            v => panic!("Unexpected value: {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_fragment_parsing() {
+        assert_eq!(
+            Fragment::try_from("a; b.c ;d;").unwrap().fields,
+            vec!["a".to_string(), "b.c".to_string(), "d".to_string()]
+        );
+        assert!(Fragment::try_from("").is_err());
+        assert!(Fragment::try_from(";").is_err());
+        assert!(Fragment::try_from("a..b").is_err());
+    }
+
+    #[test]
+    fn test_fragment_json() {
+        let value = r#"{"a": 1, "b": {"c": 2, "d": [3]}, "e": "x"}"#;
+        assert_eq!(project_json("a;e", value), json(r#"{"a": 1, "e": "x"}"#));
+        assert_eq!(project_json("b.d", value), json(r#"{"b": {"d": [3]}}"#));
+        assert_eq!(
+            project_json("b;b.c", value),
+            json(r#"{"b": {"c": 2, "d": [3]}}"#)
+        );
+        assert_eq!(project_json("z;a.z;b.c.z", value), json("{}"));
+        // non-objects are unchanged
+        assert_eq!(project_json("a", "[1, 2]"), json("[1, 2]"));
+    }
+
+    #[test]
+    fn test_fragment_properties_and_samples() {
+        let fragment = Fragment::try_from("a;c").unwrap();
+        match fragment.project(Value::Properties(Properties::from("a=1;b=2;c=3"))) {
+            Value::Properties(p) => assert_eq!(p, Properties::from("a=1;c=3")),
+            v => panic!("Unexpected value: {:?}", v),
+        }
+        assert!(matches!(
+            fragment.project(Value::Integer(1)),
+            Value::Integer(1)
+        ));
+
+        let (encoding, payload) = Value::Properties(Properties::from("a=1;b=2")).encode();
+        let mut info = crate::net::DataInfo::new();
+        info.encoding = Some(encoding);
+        let sample = fragment.project_sample(Sample {
+            res_name: "/a/b".into(),
+            payload,
+            data_info: Some(info),
+        });
+        assert_eq!(
+            sample.data_info.as_ref().unwrap().encoding,
+            Some(encoding::APP_PROPERTIES)
+        );
+        assert_eq!(sample.payload.to_vec(), b"a=1".to_vec());
+    }
+}
@@ -12,12 +12,18 @@
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
 use crate::net::Query;
-use crate::{Path, PathExpr, Properties};
+use crate::{Path, PathExpr, Properties, Timestamp};
 use regex::Regex;
 use std::convert::TryFrom;
 use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use zenoh_util::core::{ZError, ZErrorKind, ZResult};
-use zenoh_util::zerror;
+use zenoh_util::{zerror, zerror2};
+
+mod filter;
+mod fragment;
+pub use filter::*;
+pub use fragment::*;
 
 /// The "starttime" property key for time-range selection
 pub const PROP_STARTTIME: &str = "starttime";
@@ -46,7 +52,8 @@ pub const PROP_STOPTIME: &str = "stoptime";
 ///    It allows to select only some fields within the structure. A new structure with only the selected fields
 ///    will be used in place of the original value.
 ///
-/// _**NOTE**_: _the filters and fragments are not yet supported in current zenoh version._
+/// The filter can be evaluated on values using the [`Filter`] returned by [`Selector::value_filter()`],
+/// and the fragment can be applied to values using the [`Fragment`] returned by [`Selector::value_fragment()`].
 pub struct Selector {
     /// the path expression part of this Selector (before `?` character).
     pub path_expr: PathExpr,
@@ -137,6 +144,142 @@ impl Selector {
     pub fn has_time_range(&self) -> bool {
         self.properties.contains_key(PROP_STARTTIME) || self.properties.contains_key(PROP_STOPTIME)
     }
+
+    /// Returns the [`Filter`] corresponding to the filter part of this Selector, if any.
+    /// Returns `Err(`[`ZError`]`)` if the filter part is not valid.
+    pub fn value_filter(&self) -> ZResult<Option<Filter>> {
+        self.filter.as_deref().map(Filter::try_from).transpose()
+    }
+
+    /// Returns the [`Fragment`] corresponding to the fragment part of this Selector, if any.
+    /// Returns `Err(`[`ZError`]`)` if the fragment part is not valid.
+    pub fn value_fragment(&self) -> ZResult<Option<Fragment>> {
+        self.fragment.as_deref().map(Fragment::try_from).transpose()
+    }
+
+    /// Returns the time-range specified in the Selector's properties
+    /// (i.e. using `"starttime"` and/or `"stoptime"`), or `None` if there is no such properties.
+    /// Returns `Err(`[`ZError`]`)` if one of those properties is not a valid time expression
+    /// (see [`parse_time_expr()`]).
+    pub fn time_range(&self) -> ZResult<Option<TimeRange>> {
+        if !self.has_time_range() {
+            return Ok(None);
+        }
+        let start = self
+            .properties
+            .get(PROP_STARTTIME)
+            .map(|s| parse_time_expr(s))
+            .transpose()?;
+        let stop = self
+            .properties
+            .get(PROP_STOPTIME)
+            .map(|s| parse_time_expr(s))
+            .transpose()?;
+        Ok(Some(TimeRange { start, stop }))
+    }
+}
+
+/// A time-range, as specified in a [`Selector`] with the `"starttime"` and `"stoptime"` properties.
+/// Both bounds are inclusive. A `None` bound means the range is unbounded on this side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeRange {
+    pub start: Option<SystemTime>,
+    pub stop: Option<SystemTime>,
+}
+
+impl TimeRange {
+    /// Returns true if `time` is within this TimeRange.
+    pub fn contains(&self, time: SystemTime) -> bool {
+        match (self.start, self.stop) {
+            (Some(start), _) if time < start => false,
+            (_, Some(stop)) if time > stop => false,
+            _ => true,
+        }
+    }
+
+    /// Returns true if the time of `timestamp` is within this TimeRange.
+    pub fn contains_timestamp(&self, timestamp: &Timestamp) -> bool {
+        self.contains(timestamp.get_time().to_system_time())
+    }
+}
+
+/// Parses a time expression, as used for the `"starttime"` and `"stoptime"` properties of a [`Selector`].
+///
+/// The accepted formats are:
+///  * `now()` for the current time, optionally followed by `+` or `-` and a duration
+///    (e.g. `now()-1h`, `now()-2d12h`, `now()+30s`, `now()-500ms`)
+///  * an RFC3339 date (e.g. `2021-09-15T14:00:00Z`)
+///  * a number of seconds since the UNIX Epoch (e.g. `0` or `1631714400.5`)
+///
+/// # Examples
+/// ```
+/// # use zenoh::parse_time_expr;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// assert_eq!(parse_time_expr("10.5").unwrap(), UNIX_EPOCH + Duration::from_millis(10_500));
+/// assert_eq!(
+///     parse_time_expr("1970-01-01T00:01:00Z").unwrap(),
+///     UNIX_EPOCH + Duration::from_secs(60)
+/// );
+/// assert!(parse_time_expr("now()-1h").is_ok());
+/// assert!(parse_time_expr("yesterday").is_err());
+/// ```
+pub fn parse_time_expr(s: &str) -> ZResult<SystemTime> {
+    let s = s.trim();
+    if let Some(offset) = s.strip_prefix("now()") {
+        let offset = offset.trim();
+        if offset.is_empty() {
+            return Ok(SystemTime::now());
+        }
+        let (is_add, duration) = if let Some(duration) = offset.strip_prefix('+') {
+            (true, duration)
+        } else if let Some(duration) = offset.strip_prefix('-') {
+            (false, duration)
+        } else {
+            return zerror!(ZErrorKind::Other {
+                descr: format!(
+                    "Invalid time expression '{}': 'now()' must be followed by '+' or '-'",
+                    s
+                )
+            });
+        };
+        let duration = humantime::parse_duration(duration.trim()).map_err(|e| {
+            zerror2!(ZErrorKind::Other {
+                descr: format!("Invalid duration in time expression '{}': {}", s, e)
+            })
+        })?;
+        let now = SystemTime::now();
+        let time = if is_add {
+            now.checked_add(duration)
+        } else {
+            now.checked_sub(duration)
+        };
+        time.ok_or_else(|| {
+            zerror2!(ZErrorKind::Other {
+                descr: format!("Invalid time expression '{}': time out of range", s)
+            })
+        })
+    } else if let Ok(secs) = s.parse::<f64>() {
+        if secs < 0.0 {
+            return zerror!(ZErrorKind::Other {
+                descr: format!("Invalid time expression '{}': negative time", s)
+            });
+        }
+        Duration::try_from_secs_f64(secs)
+            .ok()
+            .and_then(|d| UNIX_EPOCH.checked_add(d))
+            .ok_or_else(|| {
+                zerror2!(ZErrorKind::Other {
+                    descr: format!("Invalid time expression '{}': time out of range", s)
+                })
+            })
+    } else {
+        humantime::parse_rfc3339_weak(s).map_err(|e| {
+            zerror2!(ZErrorKind::Other {
+                descr: format!("Invalid time expression '{}': {}", s, e)
+            })
+        })
+    }
 }
 
 impl fmt::Display for Selector {
@@ -322,4 +465,60 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_time_range() {
+        assert_eq!(
+            Selector::try_from("/path/**")
+                .unwrap()
+                .time_range()
+                .unwrap(),
+            None
+        );
+
+        let range = Selector::try_from("/path/**?(starttime=10;stoptime=20)")
+            .unwrap()
+            .time_range()
+            .unwrap()
+            .unwrap();
+        assert_eq!(range.start, Some(UNIX_EPOCH + Duration::from_secs(10)));
+        assert_eq!(range.stop, Some(UNIX_EPOCH + Duration::from_secs(20)));
+        assert!(!range.contains(UNIX_EPOCH + Duration::from_secs(9)));
+        assert!(range.contains(UNIX_EPOCH + Duration::from_secs(10)));
+        assert!(range.contains(UNIX_EPOCH + Duration::from_secs(20)));
+        assert!(!range.contains(UNIX_EPOCH + Duration::from_secs(21)));
+
+        let range = Selector::try_from("/path/**?(starttime=now()-1h)")
+            .unwrap()
+            .time_range()
+            .unwrap()
+            .unwrap();
+        assert_eq!(range.stop, None);
+        assert!(range.contains(SystemTime::now() - Duration::from_secs(1800)));
+        assert!(!range.contains(SystemTime::now() - Duration::from_secs(7200)));
+        assert!(range.contains(SystemTime::now() + Duration::from_secs(7200)));
+
+        assert!(Selector::try_from("/path/**?(stoptime=now()*1h)")
+            .unwrap()
+            .time_range()
+            .is_err());
+        assert!(Selector::try_from("/path/**?(starttime=-1)")
+            .unwrap()
+            .time_range()
+            .is_err());
+
+        // Invalid or out of range expressions are errors, not panics
+        assert!(parse_time_expr("now()\u{e9}1h").is_err());
+        assert!(parse_time_expr("now()-1000000000y").is_ok());
+        assert!(parse_time_expr("now()-400000000000y").is_err());
+        assert!(parse_time_expr("now()+400000000000y").is_err());
+        assert!(parse_time_expr("1e30").is_err());
+        assert!(parse_time_expr("NaN").is_err());
+        assert!(
+            Selector::try_from("/path/**?(starttime=now()-400000000000y)")
+                .unwrap()
+                .time_range()
+                .is_err()
+        );
+    }
 }
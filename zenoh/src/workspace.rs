@@ -15,8 +15,8 @@ use crate::net::queryable::EVAL;
 use crate::net::{
     data_kind, encoding, CallbackSubscriber, CongestionControl, DataInfo, Query,
     QueryConsolidation, QueryTarget, Queryable, Receiver, RecvError, RecvTimeoutError, Reliability,
-    RepliesSender, Reply, ReplyReceiver, ResKey, Sample, SampleReceiver, Session, SubInfo, SubMode,
-    Subscriber, TryRecvError, ZBuf, ZFuture, ZInt,
+    RepliesSender, Reply, ReplyError, ReplyReceiver, ResKey, Sample, SampleReceiver, Session,
+    SubInfo, SubMode, Subscriber, TryRecvError, ZBuf, ZFuture, ZInt,
 };
 use crate::utils::new_reception_timestamp;
 use crate::{
@@ -192,7 +192,9 @@ impl Workspace<'_> {
     }
 
     /// Get a selection of [`Path`]/[`Value`] from zenoh.  
-    /// The selection is returned as a [`async_std::stream::Stream`] of [`Data`].
+    /// The selection is returned as a [`async_std::stream::Stream`] of [`DataResult`]:
+    /// each item is either a [`Data`], or an error replied by a storage or an evaluation function
+    /// that failed to answer (with a [`ZErrorKind::ReplyError`] kind).
     /// Note that the [`Selector`] can be absolute or relative to this Workspace.
     ///
     /// # Examples
@@ -206,9 +208,12 @@ impl Workspace<'_> {
     /// let workspace = zenoh.workspace(None).await.unwrap();
     /// let mut data_stream = workspace.get(&"/demo/example/**".try_into().unwrap()).await.unwrap();
     /// while let Some(data) = data_stream.next().await {
-    ///     println!(">> {} : {:?} at {}",
-    ///         data.path, data.value, data.timestamp
-    ///     )
+    ///     match data {
+    ///         Ok(data) => println!(">> {} : {:?} at {}",
+    ///             data.path, data.value, data.timestamp
+    ///         ),
+    ///         Err(e) => println!(">> Error: {}", e),
+    ///     }
     /// }
     /// # })
     /// ```
@@ -367,6 +372,8 @@ impl Workspace<'_> {
     /// allowing the implementation to send a reply as a result of the evaluation function via [`GetRequest::reply()`].
     /// This Stream will never end unless it's dropped or explicitly closed via [`GetRequestStream::close()`].
     /// Note that the [`PathExpr`] can be absolute or relative to this Workspace.
+    /// If the requested selector contains a fragment, it can be applied to the replied values
+    /// via [`GetRequest::project()`].
     ///
     /// # Examples
     /// ```no_run
@@ -423,24 +430,52 @@ pub struct Data {
     pub timestamp: Timestamp,
 }
 
+/// An item of a [`DataReceiver`]: a [`Data`], or the error replied by a storage or
+/// an evaluation function (see [`GetRequest::reply_error()`]).
+pub type DataResult = ZResult<Data>;
+
 ztranscoder! {
-    /// A [`Stream`] of [`Data`] returned as a result of the [`Workspace::get()`] operation.
+    /// A [`Stream`] of [`DataResult`] returned as a result of the [`Workspace::get()`] operation.
     ///
     /// [`Stream`]: async_std::stream::Stream
     #[derive(Clone)]
-    pub DataReceiver: Receiver<Data> <- ReplyReceiver: Receiver<Reply>
+    pub DataReceiver: Receiver<DataResult> <- ReplyReceiver: Receiver<Reply>
     with
-        DataIter: Iterator<Data>,
-        DataTryIter: Iterator<Data>,
+        DataIter: Iterator<DataResult>,
+        DataTryIter: Iterator<DataResult>,
     {
         decode_value: bool,
     }
 }
 
 impl DataReceiver {
-    fn transcode(&self, reply: Reply) -> ZResult<Data> {
-        let path: Path = reply.data.res_name.try_into().unwrap();
-        let (encoding, timestamp) = if let Some(info) = reply.data.data_info {
+    /// Returns `true` if the [`Workspace::get()`] operation timed out before all the replies
+    /// were received, meaning that the received [`Data`] may be incomplete.
+    pub fn is_partial(&self) -> bool {
+        self.receiver.is_partial()
+    }
+
+    /// Cancels the [`Workspace::get()`] operation, notifying the evaluation functions that received it
+    /// (see [`GetRequest::is_cancelled()`]). The [`Data`] that were not received yet are discarded.
+    ///
+    /// The operation is also cancelled when all the clones of this DataReceiver are dropped.
+    pub fn cancel(&self) {
+        self.receiver.cancel()
+    }
+
+    fn transcode(&self, reply: Reply) -> ZResult<DataResult> {
+        let sample = match reply.data {
+            Ok(sample) => sample,
+            Err(error) => {
+                return Ok(zerror!(ZErrorKind::ReplyError {
+                    path: error.res_name,
+                    descr: String::from_utf8_lossy(error.payload.contiguous().as_slice())
+                        .into_owned(),
+                }))
+            }
+        };
+        let path: Path = sample.res_name.try_into().unwrap();
+        let (encoding, timestamp) = if let Some(info) = sample.data_info {
             (
                 info.encoding.unwrap_or(encoding::APP_OCTET_STREAM),
                 info.timestamp.unwrap_or_else(new_reception_timestamp),
@@ -449,15 +484,15 @@ impl DataReceiver {
             (encoding::APP_OCTET_STREAM, new_reception_timestamp())
         };
         let value = if self.decode_value {
-            Value::decode(encoding, reply.data.payload)?
+            Value::decode(encoding, sample.payload)?
         } else {
-            Value::Raw(encoding, reply.data.payload)
+            Value::Raw(encoding, sample.payload)
         };
-        Ok(Data {
+        Ok(Ok(Data {
             path,
             value,
             timestamp,
-        })
+        }))
     }
 }
 
@@ -631,6 +666,17 @@ pub struct GetRequest {
 }
 
 impl GetRequest {
+    /// Returns a [`Value`] with only the fields selected by the fragment of this request's selector
+    /// (see [`Fragment::project()`](crate::selector::Fragment::project)).
+    /// If the selector has no fragment, the Value is returned unchanged.
+    /// Returns `Err(`[`ZError`]`)` if the fragment is not valid.
+    pub fn project(&self, value: Value) -> ZResult<Value> {
+        match self.selector.value_fragment()? {
+            Some(fragment) => Ok(fragment.project(value)),
+            None => Ok(value),
+        }
+    }
+
     /// Send a [`Path`]/[`Value`] as a reply to the requester.
     #[inline(always)]
     pub fn reply(&self, path: Path, value: Value) {
@@ -644,6 +690,24 @@ impl GetRequest {
             .send_async(path_value_to_sample(path, value))
             .await
     }
+
+    /// Send an error as a reply to the requester, if the evaluation function failed to answer
+    /// this request for `path`. The requester receives it as a [`ZErrorKind::ReplyError`].
+    #[inline(always)]
+    pub fn reply_error(&self, path: Path, descr: &str) {
+        self.replies_sender.send_error(ReplyError {
+            res_name: path.to_string(),
+            payload: descr.as_bytes().to_vec().into(),
+            data_info: None,
+        })
+    }
+
+    /// Returns `true` if the requester cancelled this request: the evaluation function
+    /// can stop working on it, its replies being discarded.
+    #[inline(always)]
+    pub fn is_cancelled(&self) -> bool {
+        self.replies_sender.is_cancelled()
+    }
 }
 
 fn query_to_get(query: Query) -> ZResult<GetRequest> {
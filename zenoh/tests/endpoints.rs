@@ -243,6 +243,48 @@ fn endpoint_unix() {
     let _ = std::fs::remove_file("zenoh-test-unix-socket-1.sock.lock");
 }
 
+#[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+#[test]
+fn endpoint_unix_seqpacket() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Remove the files if they still exists
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-11.sock");
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-12.sock");
+    // Define the locators
+    let endpoints: Vec<EndPoint> = vec![
+        "unixsock-seqpacket/zenoh-test-unix-socket-11.sock"
+            .parse()
+            .unwrap(),
+        "unixsock-seqpacket/zenoh-test-unix-socket-12.sock"
+            .parse()
+            .unwrap(),
+    ];
+    task::block_on(run(&endpoints));
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-11.sock");
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-12.sock");
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-11.sock.lock");
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-12.sock.lock");
+}
+
+#[cfg(feature = "transport_ws")]
+#[test]
+fn endpoint_ws() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Define the locators
+    let endpoints: Vec<EndPoint> = vec![
+        "ws/127.0.0.1:9460".parse().unwrap(),
+        "ws/[::1]:9460".parse().unwrap(),
+        "ws/localhost:9461".parse().unwrap(),
+    ];
+    task::block_on(run(&endpoints));
+}
+
 #[cfg(all(feature = "transport_tcp", feature = "transport_udp"))]
 #[test]
 fn endpoint_tcp_udp() {
@@ -73,15 +73,19 @@ fn gen_routing_context() -> RoutingContext {
 
 fn gen_reply_context(is_final: bool) -> ReplyContext {
     let qid = gen!(ZInt);
-    let replier = if !is_final {
-        Some(ReplierInfo {
+    if !is_final {
+        let replier = ReplierInfo {
             kind: thread_rng().gen_range(0..4),
             id: gen_pid(),
-        })
+        };
+        if gen_bool!() {
+            ReplyContext::new_error(qid, replier)
+        } else {
+            ReplyContext::new(qid, Some(replier))
+        }
     } else {
-        None
-    };
-    ReplyContext::new(qid, replier)
+        ReplyContext::new_final(qid, gen_bool!())
+    }
 }
 
 fn gen_attachment() -> Attachment {
@@ -309,23 +313,27 @@ fn codec_hello() {
 fn codec_init() {
     for _ in 0..NUM_ITER {
         let is_qos = [true, false];
+        let is_compression = [true, false];
         let wami = [whatami::ROUTER, whatami::CLIENT];
         let sn_resolution = [SEQ_NUM_RES, gen!(ZInt)];
         let attachment = [None, Some(gen_attachment())];
 
         for q in is_qos.iter() {
-            for w in wami.iter() {
-                for s in sn_resolution.iter() {
-                    for a in attachment.iter() {
-                        let msg = TransportMessage::make_init_syn(
-                            gen!(u8),
-                            *w,
-                            gen_pid(),
-                            *s,
-                            *q,
-                            a.clone(),
-                        );
-                        test_write_read_transport_message(msg);
+            for c in is_compression.iter() {
+                for w in wami.iter() {
+                    for s in sn_resolution.iter() {
+                        for a in attachment.iter() {
+                            let msg = TransportMessage::make_init_syn(
+                                gen!(u8),
+                                *w,
+                                gen_pid(),
+                                *s,
+                                *q,
+                                *c,
+                                a.clone(),
+                            );
+                            test_write_read_transport_message(msg);
+                        }
                     }
                 }
             }
@@ -333,18 +341,21 @@ fn codec_init() {
 
         let sn_resolution = [None, Some(gen!(ZInt))];
         for q in is_qos.iter() {
-            for w in wami.iter() {
-                for s in sn_resolution.iter() {
-                    for a in attachment.iter() {
-                        let msg = TransportMessage::make_init_ack(
-                            *w,
-                            gen_pid(),
-                            *s,
-                            *q,
-                            gen_buffer(64).into(),
-                            a.clone(),
-                        );
-                        test_write_read_transport_message(msg);
+            for c in is_compression.iter() {
+                for w in wami.iter() {
+                    for s in sn_resolution.iter() {
+                        for a in attachment.iter() {
+                            let msg = TransportMessage::make_init_ack(
+                                *w,
+                                gen_pid(),
+                                *s,
+                                *q,
+                                *c,
+                                gen_buffer(64).into(),
+                                a.clone(),
+                            );
+                            test_write_read_transport_message(msg);
+                        }
                     }
                 }
             }
@@ -389,23 +400,27 @@ fn codec_join() {
             ConduitSnList::Plain(gen_initial_sn()),
             ConduitSnList::QoS(Box::new([gen_initial_sn(); Priority::NUM])),
         ];
+        let is_compression = [false, true];
         let attachment = [None, Some(gen_attachment())];
 
         for l in lease.iter() {
             for w in wami.iter() {
                 for s in sn_resolution.iter() {
                     for i in initial_sns.iter() {
-                        for a in attachment.iter() {
-                            let msg = TransportMessage::make_join(
-                                gen!(u8),
-                                *w,
-                                gen_pid(),
-                                *l,
-                                *s,
-                                i.clone(),
-                                a.clone(),
-                            );
-                            test_write_read_transport_message(msg);
+                        for c in is_compression.iter() {
+                            for a in attachment.iter() {
+                                let msg = TransportMessage::make_join(
+                                    gen!(u8),
+                                    *w,
+                                    gen_pid(),
+                                    *l,
+                                    *s,
+                                    i.clone(),
+                                    *c,
+                                    a.clone(),
+                                );
+                                test_write_read_transport_message(msg);
+                            }
                         }
                     }
                 }
@@ -435,7 +450,20 @@ fn codec_close() {
 #[test]
 fn codec_sync() {
     for _ in 0..NUM_ITER {
-        let ch = [Reliability::Reliable, Reliability::BestEffort];
+        let ch = [
+            Channel {
+                priority: Priority::default(),
+                reliability: Reliability::Reliable,
+            },
+            Channel {
+                priority: Priority::default(),
+                reliability: Reliability::BestEffort,
+            },
+            Channel {
+                priority: Priority::RealTime,
+                reliability: Reliability::Reliable,
+            },
+        ];
         let count = [None, Some(gen!(ZInt))];
         let attachment = [None, Some(gen_attachment())];
 
@@ -453,13 +481,16 @@ fn codec_sync() {
 #[test]
 fn codec_ack_nack() {
     for _ in 0..NUM_ITER {
+        let priority = [Priority::default(), Priority::RealTime];
         let mask = [None, Some(gen!(ZInt))];
         let attachment = [None, Some(gen_attachment())];
 
-        for m in mask.iter() {
-            for a in attachment.iter() {
-                let msg = TransportMessage::make_ack_nack(gen!(ZInt), *m, a.clone());
-                test_write_read_transport_message(msg);
+        for p in priority.iter() {
+            for m in mask.iter() {
+                for a in attachment.iter() {
+                    let msg = TransportMessage::make_ack_nack(*p, gen!(ZInt), *m, a.clone());
+                    test_write_read_transport_message(msg);
+                }
             }
         }
     }
@@ -837,3 +868,15 @@ fn codec_query() {
         }
     }
 }
+
+#[test]
+fn codec_query_cancel() {
+    for _ in 0..NUM_ITER {
+        let attachment = [None, Some(gen_attachment())];
+
+        for a in attachment.iter() {
+            let msg = ZenohMessage::make_query_cancel(gen!(ZInt), a.clone());
+            test_write_read_zenoh_message(msg);
+        }
+    }
+}
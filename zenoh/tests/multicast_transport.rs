@@ -130,6 +130,7 @@ mod tests {
 
     async fn open_transport(
         endpoint: &EndPoint,
+        is_compression: (bool, bool),
     ) -> (TransportMulticastPeer, TransportMulticastPeer) {
         // Define peer01 and peer02 IDs
         let peer01_id = PeerId::new(1, [0u8; PeerId::MAX_SIZE]);
@@ -140,6 +141,7 @@ mod tests {
         let config = TransportManagerConfig::builder()
             .pid(peer01_id)
             .whatami(whatami::PEER)
+            .compression(is_compression.0)
             .build(peer01_handler.clone());
         let peer01_manager = TransportManager::new(config);
 
@@ -148,6 +150,7 @@ mod tests {
         let config = TransportManagerConfig::builder()
             .whatami(whatami::PEER)
             .pid(peer02_id)
+            .compression(is_compression.1)
             .build(peer02_handler.clone());
         let peer02_manager = TransportManager::new(config);
 
@@ -299,11 +302,16 @@ mod tests {
         task::sleep(SLEEP).await;
     }
 
-    async fn run(endpoints: &[EndPoint], channel: &[Channel], msg_size: &[usize]) {
+    async fn run(
+        endpoints: &[EndPoint],
+        channel: &[Channel],
+        msg_size: &[usize],
+        is_compression: (bool, bool),
+    ) {
         for e in endpoints.iter() {
             for ch in channel.iter() {
                 for ms in msg_size.iter() {
-                    let (peer01, peer02) = open_transport(e).await;
+                    let (peer01, peer02) = open_transport(e, is_compression).await;
                     single_run(&peer01, &peer02, *ch, *ms).await;
 
                     #[cfg(feature = "stats")]
@@ -352,6 +360,24 @@ mod tests {
             },
         ];
         // Run
-        task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG));
+        task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG, (false, false)));
+    }
+
+    #[cfg(feature = "transport_udp")]
+    #[test]
+    fn transport_multicast_udp_compression() {
+        task::block_on(async {
+            zasync_executor_init!();
+        });
+
+        // Define the locator: not the default one, to not interfere with the other tests
+        let endpoints: Vec<EndPoint> = vec!["udp/224.0.0.224:7448".parse().unwrap()];
+        // Define the reliability and congestion control
+        let channel = [Channel {
+            priority: Priority::default(),
+            reliability: Reliability::BestEffort,
+        }];
+        // Run: only the sender compresses its batches, the receiver decompresses them anyway
+        task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG, (true, false)));
     }
 }
@@ -0,0 +1,520 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::prelude::*;
+use async_std::task;
+use std::time::Duration;
+use zenoh::net::queryable::EVAL;
+use zenoh::net::*;
+use zenoh_util::properties::config::*;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[test]
+fn session_query_timeout() {
+    task::block_on(async {
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_QUERIES_DEFAULT_TIMEOUT_KEY, "100".to_string());
+        let session = open(config).await.unwrap();
+
+        let mut queryable = session
+            .declare_queryable(&"/test/timeout".into(), EVAL)
+            .await
+            .unwrap();
+        let receiver = queryable.receiver().clone();
+        let handle = task::spawn(async move {
+            let mut receiver = receiver;
+            let mut pending = vec![];
+            while let Some(query) = receiver.next().await {
+                query
+                    .reply_async(Sample {
+                        res_name: query.res_name.clone(),
+                        payload: vec![1].into(),
+                        data_info: None,
+                    })
+                    .await;
+                // Replies once but never sends the final reply to the 2nd query
+                if query.predicate == "hang" {
+                    pending.push(query);
+                } else {
+                    drop(query);
+                }
+            }
+        });
+
+        // The query completes before the timeout
+        let mut replies = session
+            .query_ext(
+                &"/test/timeout".into(),
+                "",
+                QueryTarget::default(),
+                QueryConsolidation::none(),
+                QUERY_TIMEOUT,
+            )
+            .await
+            .unwrap();
+        let count = replies.clone().count().timeout(TIMEOUT).await.unwrap();
+        assert_eq!(count, 1);
+        assert!(!replies.is_partial());
+        assert!(replies.next().await.is_none());
+
+        // The query is finalized after the timeout, with the replies received so far
+        let replies = session
+            .query_ext(
+                &"/test/timeout".into(),
+                "hang",
+                QueryTarget::default(),
+                QueryConsolidation::default(),
+                QUERY_TIMEOUT,
+            )
+            .await
+            .unwrap();
+        let count = replies.clone().count().timeout(TIMEOUT).await.unwrap();
+        assert_eq!(count, 1);
+        assert!(replies.is_partial());
+
+        // The session's default timeout applies to queries without explicit timeout
+        let replies = session
+            .query(
+                &"/test/timeout".into(),
+                "hang",
+                QueryTarget::default(),
+                QueryConsolidation::none(),
+            )
+            .await
+            .unwrap();
+        let count = replies.clone().count().timeout(TIMEOUT).await.unwrap();
+        assert_eq!(count, 1);
+        assert!(replies.is_partial());
+
+        queryable.undeclare().await.unwrap();
+        handle.cancel().await;
+        session.close().await.unwrap();
+    });
+}
+
+#[cfg(feature = "transport_tcp")]
+#[test]
+fn session_query_timeout_routed() {
+    task::block_on(async {
+        let locator = "tcp/127.0.0.1:17451".to_string();
+        let mut config = config::empty();
+        config.insert(ZN_MODE_KEY, "router".to_string());
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_LISTENER_KEY, locator.clone());
+        config.insert(ZN_QUERIES_DEFAULT_TIMEOUT_KEY, "1000".to_string());
+        let router = open(config).await.unwrap();
+
+        let mut config = config::client(Some(locator.clone()));
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        let server = open(config).await.unwrap();
+        let mut config = config::client(Some(locator));
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        let client = open(config).await.unwrap();
+
+        let mut queryable = server
+            .declare_queryable(&"/test/routed".into(), EVAL)
+            .await
+            .unwrap();
+        let receiver = queryable.receiver().clone();
+        let (cancelled_sender, cancelled_receiver) = flume::unbounded();
+        let handle = task::spawn(async move {
+            let mut receiver = receiver;
+            while let Some(query) = receiver.next().await {
+                query
+                    .reply_async(Sample {
+                        res_name: query.res_name.clone(),
+                        payload: vec![1].into(),
+                        data_info: None,
+                    })
+                    .await;
+                // Never sends the final reply, until the query is cancelled
+                let cancelled_sender = cancelled_sender.clone();
+                task::spawn(async move {
+                    while !query.is_cancelled() {
+                        task::sleep(Duration::from_millis(10)).await;
+                    }
+                    cancelled_sender.send(query.predicate.clone()).unwrap();
+                });
+            }
+        });
+        // Wait for the queryable declaration to reach the router
+        task::sleep(Duration::from_secs(1)).await;
+
+        // The query's timeout applies when it is shorter than the router's one
+        let replies = client
+            .query_ext(
+                &"/test/routed".into(),
+                "session",
+                QueryTarget::default(),
+                QueryConsolidation::none(),
+                QUERY_TIMEOUT,
+            )
+            .await
+            .unwrap();
+        let count = replies.clone().count().timeout(TIMEOUT).await.unwrap();
+        assert_eq!(count, 1);
+        assert!(replies.is_partial());
+        // The timed out query is cancelled on the queryable
+        let cancelled = cancelled_receiver.recv_async().timeout(TIMEOUT).await;
+        assert_eq!(cancelled.unwrap().unwrap(), "session");
+
+        // Otherwise the router finalizes the query after its own default timeout
+        let start = std::time::Instant::now();
+        let replies = client
+            .query_ext(
+                &"/test/routed".into(),
+                "router",
+                QueryTarget::default(),
+                QueryConsolidation::none(),
+                TIMEOUT,
+            )
+            .await
+            .unwrap();
+        let count = replies.clone().count().timeout(TIMEOUT).await.unwrap();
+        assert_eq!(count, 1);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(replies.is_partial());
+        let cancelled = cancelled_receiver.recv_async().timeout(TIMEOUT).await;
+        assert_eq!(cancelled.unwrap().unwrap(), "router");
+
+        queryable.undeclare().await.unwrap();
+        handle.cancel().await;
+        client.close().await.unwrap();
+        server.close().await.unwrap();
+        router.close().await.unwrap();
+    });
+}
+
+#[test]
+fn session_query_cancel() {
+    task::block_on(async {
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        let session = open(config).await.unwrap();
+
+        let mut queryable = session
+            .declare_queryable(&"/test/cancel".into(), EVAL)
+            .await
+            .unwrap();
+        let receiver = queryable.receiver().clone();
+        let (cancelled_sender, cancelled_receiver) = flume::unbounded();
+        let handle = task::spawn(async move {
+            let mut receiver = receiver;
+            while let Some(query) = receiver.next().await {
+                let cancelled_sender = cancelled_sender.clone();
+                // An expensive eval, replying until the query is cancelled
+                task::spawn(async move {
+                    while !query.is_cancelled() {
+                        query
+                            .reply_async(Sample {
+                                res_name: query.res_name.clone(),
+                                payload: vec![1].into(),
+                                data_info: None,
+                            })
+                            .await;
+                        task::sleep(Duration::from_millis(10)).await;
+                    }
+                    cancelled_sender.send(query.predicate.clone()).unwrap();
+                });
+            }
+        });
+
+        // Explicit cancel
+        let mut replies = session
+            .query(
+                &"/test/cancel".into(),
+                "cancel",
+                QueryTarget::default(),
+                QueryConsolidation::none(),
+            )
+            .await
+            .unwrap();
+        replies.next().timeout(TIMEOUT).await.unwrap().unwrap();
+        replies.cancel();
+        let cancelled = cancelled_receiver.recv_async().timeout(TIMEOUT).await;
+        assert_eq!(cancelled.unwrap().unwrap(), "cancel");
+        // The receiver is closed
+        replies.count().timeout(TIMEOUT).await.unwrap();
+
+        // Cancel on drop
+        let mut replies = session
+            .query(
+                &"/test/cancel".into(),
+                "drop",
+                QueryTarget::default(),
+                QueryConsolidation::none(),
+            )
+            .await
+            .unwrap();
+        replies.next().timeout(TIMEOUT).await.unwrap().unwrap();
+        drop(replies);
+        let cancelled = cancelled_receiver.recv_async().timeout(TIMEOUT).await;
+        assert_eq!(cancelled.unwrap().unwrap(), "drop");
+
+        queryable.undeclare().await.unwrap();
+        handle.cancel().await;
+        session.close().await.unwrap();
+    });
+}
+
+#[test]
+fn session_query_error() {
+    task::block_on(async {
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        let session = open(config).await.unwrap();
+
+        let mut queryable = session
+            .declare_queryable(&"/test/error".into(), EVAL)
+            .await
+            .unwrap();
+        let receiver = queryable.receiver().clone();
+        let handle = task::spawn(async move {
+            let mut receiver = receiver;
+            while let Some(query) = receiver.next().await {
+                query.reply_error(ReplyError {
+                    res_name: query.res_name.clone(),
+                    payload: b"eval failed".to_vec().into(),
+                    data_info: None,
+                });
+            }
+        });
+
+        // The error is delivered, whatever the consolidation
+        for consolidation in [QueryConsolidation::none(), QueryConsolidation::default()] {
+            let replies = session
+                .query(
+                    &"/test/error".into(),
+                    "",
+                    QueryTarget::default(),
+                    consolidation,
+                )
+                .await
+                .unwrap();
+            let replies: Vec<Reply> = replies.collect().timeout(TIMEOUT).await.unwrap();
+            assert_eq!(replies.len(), 1);
+            let error = replies[0].data.as_ref().unwrap_err();
+            assert_eq!(error.res_name, "/test/error");
+            assert_eq!(error.to_string(), "/test/error: eval failed");
+        }
+
+        queryable.undeclare().await.unwrap();
+        handle.cancel().await;
+        session.close().await.unwrap();
+    });
+}
+
+#[cfg(feature = "transport_tcp")]
+#[test]
+fn session_liveliness() {
+    task::block_on(async {
+        let locator = "tcp/127.0.0.1:17448".to_string();
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_LISTENER_KEY, locator.clone());
+        let session1 = open(config).await.unwrap();
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_PEER_KEY, locator);
+        let session2 = open(config).await.unwrap();
+        task::sleep(Duration::from_millis(500)).await;
+
+        let mut subscriber = session2
+            .declare_liveliness_subscriber(&"/test/liveliness/*".into())
+            .await
+            .unwrap();
+        let is_delete = |sample: &Sample| {
+            sample.data_info.as_ref().and_then(|info| info.kind) == Some(data_kind::DELETE)
+        };
+
+        // The token appears
+        let token = session1
+            .declare_liveliness_token(&"/test/liveliness/1".into())
+            .await
+            .unwrap();
+        let sample = subscriber.receiver().next().timeout(TIMEOUT).await;
+        let sample = sample.unwrap().unwrap();
+        assert_eq!(sample.res_name, "/@/liveliness/test/liveliness/1");
+        assert!(!is_delete(&sample));
+
+        let replies = session2
+            .query_liveliness(&"/test/liveliness/*".into())
+            .await
+            .unwrap();
+        let replies: Vec<Reply> = replies.collect().timeout(TIMEOUT).await.unwrap();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(
+            replies[0].data.as_ref().unwrap().res_name,
+            "/@/liveliness/test/liveliness/1"
+        );
+
+        // The token disappears when undeclared
+        token.undeclare().await.unwrap();
+        let sample = subscriber.receiver().next().timeout(TIMEOUT).await;
+        assert!(is_delete(&sample.unwrap().unwrap()));
+
+        // The token disappears when its session closes, even if not undeclared
+        let token = session1
+            .declare_liveliness_token(&"/test/liveliness/1".into())
+            .await
+            .unwrap();
+        let sample = subscriber.receiver().next().timeout(TIMEOUT).await;
+        assert!(!is_delete(&sample.unwrap().unwrap()));
+        std::mem::forget(token);
+        session1.close().await.unwrap();
+        let sample = subscriber.receiver().next().timeout(TIMEOUT).await;
+        assert!(is_delete(&sample.unwrap().unwrap()));
+
+        subscriber.undeclare().await.unwrap();
+        session2.close().await.unwrap();
+    });
+}
+
+#[cfg(feature = "transport_tcp")]
+#[test]
+fn session_matching_status() {
+    task::block_on(async {
+        let locator = "tcp/127.0.0.1:17449".to_string();
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_LISTENER_KEY, locator.clone());
+        let session1 = open(config).await.unwrap();
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_PEER_KEY, locator);
+        let session2 = open(config).await.unwrap();
+        task::sleep(Duration::from_millis(500)).await;
+
+        let publisher = session1
+            .declare_publisher(&"/test/matching/1".into())
+            .await
+            .unwrap();
+        let mut listener = publisher.declare_matching_listener().await.unwrap();
+        assert!(!publisher.is_matching().await.unwrap());
+
+        // A remote subscriber appears and disappears
+        let subscriber = session2
+            .declare_subscriber(&"/test/matching/*".into(), &SubInfo::default())
+            .await
+            .unwrap();
+        let status = listener.receiver().next().timeout(TIMEOUT).await;
+        assert!(status.unwrap().unwrap().matching);
+        assert!(publisher.is_matching().await.unwrap());
+
+        subscriber.undeclare().await.unwrap();
+        let status = listener.receiver().next().timeout(TIMEOUT).await;
+        assert!(!status.unwrap().unwrap().matching);
+        assert!(!publisher.is_matching().await.unwrap());
+
+        // A local subscriber matches too
+        let subscriber = session1
+            .declare_subscriber(&"/test/matching/1".into(), &SubInfo::default())
+            .await
+            .unwrap();
+        let status = listener.receiver().next().timeout(TIMEOUT).await;
+        assert!(status.unwrap().unwrap().matching);
+        subscriber.undeclare().await.unwrap();
+        let status = listener.receiver().next().timeout(TIMEOUT).await;
+        assert!(!status.unwrap().unwrap().matching);
+
+        listener.undeclare().await.unwrap();
+        publisher.undeclare().await.unwrap();
+        session1.close().await.unwrap();
+        session2.close().await.unwrap();
+    });
+}
+
+#[cfg(feature = "transport_udp")]
+#[test]
+fn session_multicast() {
+    task::block_on(async {
+        let locator = "udp/224.0.0.225:17450".to_string();
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_LISTENER_KEY, locator.clone());
+        let session1 = open(config).await.unwrap();
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_LISTENER_KEY, locator);
+        let session2 = open(config).await.unwrap();
+
+        let mut subscriber = session2
+            .declare_subscriber(&"/test/multicast".into(), &SubInfo::default())
+            .await
+            .unwrap();
+        // Wait for the peers to join the group and exchange their declarations
+        task::sleep(Duration::from_secs(3)).await;
+
+        for i in 0..3u8 {
+            session1
+                .write(&"/test/multicast".into(), vec![i].into())
+                .await
+                .unwrap();
+            let sample = subscriber.receiver().next().timeout(TIMEOUT).await;
+            assert_eq!(sample.unwrap().unwrap().payload.to_vec(), vec![i]);
+        }
+        // Each sample is sent once on the group
+        let sample = subscriber
+            .receiver()
+            .next()
+            .timeout(Duration::from_millis(500))
+            .await;
+        assert!(sample.is_err());
+
+        subscriber.undeclare().await.unwrap();
+        session1.close().await.unwrap();
+        session2.close().await.unwrap();
+    });
+}
+
+#[cfg(feature = "transport_udp")]
+#[test]
+fn session_multicast_late_join() {
+    task::block_on(async {
+        let locator = "udp/224.0.0.226:17452".to_string();
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_LISTENER_KEY, locator.clone());
+        config.insert(ZN_JOIN_INTERVAL_KEY, "200".to_string());
+        let session1 = open(config).await.unwrap();
+
+        // The subscription is declared before the publishing peer joins the group
+        let mut subscriber = session1
+            .declare_subscriber(&"/test/late".into(), &SubInfo::default())
+            .await
+            .unwrap();
+        task::sleep(Duration::from_secs(1)).await;
+
+        let mut config = config::peer();
+        config.insert(ZN_MULTICAST_SCOUTING_KEY, ZN_FALSE.to_string());
+        config.insert(ZN_LISTENER_KEY, locator);
+        config.insert(ZN_JOIN_INTERVAL_KEY, "200".to_string());
+        let session2 = open(config).await.unwrap();
+        // Wait for the peers to join the group and for the subscription to be declared again
+        task::sleep(Duration::from_secs(2)).await;
+
+        session2
+            .write(&"/test/late".into(), vec![1].into())
+            .await
+            .unwrap();
+        let sample = subscriber.receiver().next().timeout(TIMEOUT).await;
+        assert_eq!(sample.unwrap().unwrap().payload.to_vec(), vec![1]);
+
+        subscriber.undeclare().await.unwrap();
+        session1.close().await.unwrap();
+        session2.close().await.unwrap();
+    });
+}
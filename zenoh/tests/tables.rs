@@ -446,7 +446,19 @@ impl Primitives for ClientPrimitives {
         _payload: ZBuf,
     ) {
     }
-    fn send_reply_final(&self, _qid: ZInt) {}
+    fn send_reply_error(
+        &self,
+        _qid: ZInt,
+        _replier_kind: ZInt,
+        _replier_id: PeerId,
+        _reskey: ResKey,
+        _info: Option<DataInfo>,
+        _payload: ZBuf,
+    ) {
+    }
+    fn send_reply_final(&self, _qid: ZInt, _is_partial: bool) {}
+
+    fn send_query_cancel(&self, _qid: ZInt) {}
 
     fn send_pull(
         &self,
@@ -145,7 +145,10 @@ impl TransportPeerEventHandler for SCClient {
 }
 
 async fn open_transport(
-    endpoints: &[EndPoint],
+    listeners: &[EndPoint],
+    connectors: &[EndPoint],
+    batch_size: Option<u16>,
+    is_compression: bool,
 ) -> (
     TransportManager,
     Arc<SHRouter>,
@@ -159,28 +162,36 @@ async fn open_transport(
     // Create the router transport manager
     let router_handler = Arc::new(SHRouter::default());
     let unicast = TransportManagerConfigUnicast::builder()
-        .max_links(endpoints.len())
+        .max_links(listeners.len())
         .build();
-    let config = TransportManagerConfig::builder()
+    let mut config = TransportManagerConfig::builder()
         .pid(router_id)
         .whatami(whatami::ROUTER)
-        .unicast(unicast)
-        .build(router_handler.clone());
+        .compression(is_compression)
+        .unicast(unicast);
+    if let Some(batch_size) = batch_size {
+        config = config.batch_size(batch_size);
+    }
+    let config = config.build(router_handler.clone());
     let router_manager = TransportManager::new(config);
 
     // Create the client transport manager
     let unicast = TransportManagerConfigUnicast::builder()
-        .max_links(endpoints.len())
+        .max_links(connectors.len())
         .build();
-    let config = TransportManagerConfig::builder()
+    let mut config = TransportManagerConfig::builder()
         .whatami(whatami::CLIENT)
         .pid(client_id)
-        .unicast(unicast)
-        .build(Arc::new(SHClient::default()));
+        .compression(is_compression)
+        .unicast(unicast);
+    if let Some(batch_size) = batch_size {
+        config = config.batch_size(batch_size);
+    }
+    let config = config.build(Arc::new(SHClient::default()));
     let client_manager = TransportManager::new(config);
 
     // Create the listener on the router
-    for e in endpoints.iter() {
+    for e in listeners.iter() {
         println!("Add endpoint: {}", e);
         let _ = router_manager
             .add_listener(e.clone())
@@ -192,7 +203,7 @@ async fn open_transport(
 
     // Create an empty transport with the client
     // Open transport -> This should be accepted
-    for e in endpoints.iter() {
+    for e in connectors.iter() {
         println!("Opening transport with {}", e);
         let _ = client_manager
             .open_transport(e.clone())
@@ -203,6 +214,7 @@ async fn open_transport(
     }
 
     let client_transport = client_manager.get_transport(&router_id).unwrap();
+    assert_eq!(client_transport.is_compression().unwrap(), is_compression);
 
     // Return the handlers
     (
@@ -254,6 +266,7 @@ async fn single_run(
     client_transport: TransportUnicast,
     channel: Channel,
     msg_size: usize,
+    msg_count: usize,
 ) {
     // Create the message to send
     let key = ResKey::RName("/test".to_string());
@@ -275,16 +288,16 @@ async fn single_run(
 
     println!(
         "Sending {} messages... {:?} {}",
-        MSG_COUNT, channel, msg_size
+        msg_count, channel, msg_size
     );
-    for _ in 0..MSG_COUNT {
+    for _ in 0..msg_count {
         client_transport.schedule(message.clone()).unwrap();
     }
 
     match channel.reliability {
         Reliability::Reliable => {
             let count = async {
-                while router_handler.get_count() != MSG_COUNT {
+                while router_handler.get_count() != msg_count {
                     task::sleep(SLEEP_COUNT).await;
                 }
             };
@@ -304,13 +317,46 @@ async fn single_run(
     task::sleep(SLEEP).await;
 }
 
-async fn run(endpoints: &[EndPoint], channel: &[Channel], msg_size: &[usize]) {
+async fn run(
+    endpoints: &[EndPoint],
+    channel: &[Channel],
+    msg_size: &[usize],
+    is_compression: bool,
+) {
+    run_with_connectors(
+        endpoints,
+        endpoints,
+        channel,
+        msg_size,
+        MSG_COUNT,
+        None,
+        is_compression,
+    )
+    .await;
+}
+
+async fn run_with_connectors(
+    listeners: &[EndPoint],
+    connectors: &[EndPoint],
+    channel: &[Channel],
+    msg_size: &[usize],
+    msg_count: usize,
+    batch_size: Option<u16>,
+    is_compression: bool,
+) {
     for ch in channel.iter() {
         for ms in msg_size.iter() {
             #[allow(unused_variables)] // Used when stats feature is enabled
             let (router_manager, router_handler, client_manager, client_transport) =
-                open_transport(endpoints).await;
-            single_run(router_handler.clone(), client_transport.clone(), *ch, *ms).await;
+                open_transport(listeners, connectors, batch_size, is_compression).await;
+            single_run(
+                router_handler.clone(),
+                client_transport.clone(),
+                *ch,
+                *ms,
+                msg_count,
+            )
+            .await;
 
             #[cfg(feature = "stats")]
             {
@@ -324,7 +370,7 @@ async fn run(endpoints: &[EndPoint], channel: &[Channel], msg_size: &[usize]) {
                 println!("\tRouter: {:?}", r_stats);
             }
 
-            close_transport(router_manager, client_transport, endpoints).await;
+            close_transport(router_manager, client_transport, listeners).await;
         }
     }
 }
@@ -361,7 +407,7 @@ fn transport_unicast_tcp_only() {
         },
     ];
     // Run
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
 }
 
 #[cfg(feature = "transport_udp")]
@@ -388,7 +434,307 @@ fn transport_unicast_udp_only() {
         },
     ];
     // Run
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG, false));
+}
+
+#[cfg(feature = "transport_udp")]
+#[test]
+fn transport_unicast_udp_reliable() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Define the locator
+    let endpoints: Vec<EndPoint> = vec!["udp/127.0.0.1:10451".parse().unwrap()];
+    // Define the reliability and congestion control
+    let channel = [
+        Channel {
+            priority: Priority::default(),
+            reliability: Reliability::Reliable,
+        },
+        Channel {
+            priority: Priority::RealTime,
+            reliability: Reliability::Reliable,
+        },
+    ];
+    // Run
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
+}
+
+// Relay the datagrams sent to `front` to `router`, dropping one out of LOSS_PERIOD of the datagrams
+// carrying data. The small datagrams (e.g. the ones opening or closing the transport, or the AckNacks)
+// are never dropped.
+#[cfg(feature = "transport_udp")]
+fn lossy_udp_relay(front: &str, router: &str) {
+    use std::collections::HashMap;
+    use std::net::{SocketAddr, UdpSocket};
+
+    const LOSS_PERIOD: usize = 5;
+    const LOSS_MIN_SIZE: usize = 512;
+    let is_lost = |count: &mut usize, len: usize| {
+        if len < LOSS_MIN_SIZE {
+            return false;
+        }
+        *count += 1;
+        if *count == LOSS_PERIOD {
+            *count = 0;
+            return true;
+        }
+        false
+    };
+    let router_addr: SocketAddr = router.parse().unwrap();
+    let front = UdpSocket::bind(front).unwrap();
+    std::thread::spawn(move || {
+        // A new client transport uses a new socket: relay it with a new socket as well
+        let mut clients: HashMap<SocketAddr, (UdpSocket, usize)> = HashMap::new();
+        let mut buffer = [0u8; 65_535];
+        while let Ok((n, client_addr)) = front.recv_from(&mut buffer) {
+            let (back, count) = clients.entry(client_addr).or_insert_with(|| {
+                let back = UdpSocket::bind("127.0.0.1:0").unwrap();
+                back.connect(router_addr).unwrap();
+                let c_back = back.try_clone().unwrap();
+                let c_front = front.try_clone().unwrap();
+                std::thread::spawn(move || {
+                    let mut count = 0;
+                    let mut buffer = [0u8; 65_535];
+                    while let Ok(n) = c_back.recv(&mut buffer) {
+                        if !is_lost(&mut count, n) {
+                            let _ = c_front.send_to(&buffer[..n], client_addr);
+                        }
+                    }
+                });
+                (back, 0)
+            });
+            if !is_lost(count, n) {
+                let _ = back.send(&buffer[..n]);
+            }
+        }
+    });
+}
+
+#[cfg(feature = "transport_udp")]
+#[test]
+fn transport_unicast_udp_reliable_lossy() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    lossy_udp_relay("127.0.0.1:10453", "127.0.0.1:10452");
+
+    // Define the locators: the router listens behind the relay
+    let listeners: Vec<EndPoint> = vec!["udp/127.0.0.1:10452".parse().unwrap()];
+    let connectors: Vec<EndPoint> = vec!["udp/127.0.0.1:10453".parse().unwrap()];
+    // Define the reliability and congestion control
+    let channel = [Channel {
+        priority: Priority::default(),
+        reliability: Reliability::Reliable,
+    }];
+    // Run
+    task::block_on(run_with_connectors(
+        &listeners,
+        &connectors,
+        &channel,
+        &MSG_SIZE_ALL,
+        MSG_COUNT,
+        None,
+        false,
+    ));
+}
+
+#[cfg(feature = "transport_udp")]
+#[test]
+fn transport_unicast_udp_reliable_lossy_backlog() {
+    use zenoh::net::transport::defaults::ZN_RELIABILITY_BACKLOG_SIZE;
+
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    lossy_udp_relay("127.0.0.1:10458", "127.0.0.1:10457");
+
+    // Define the locators: the router listens behind the relay
+    let listeners: Vec<EndPoint> = vec!["udp/127.0.0.1:10457".parse().unwrap()];
+    let connectors: Vec<EndPoint> = vec!["udp/127.0.0.1:10458".parse().unwrap()];
+    // Define the reliability and congestion control
+    let channel = [Channel {
+        priority: Priority::default(),
+        reliability: Reliability::Reliable,
+    }];
+    // With small batches, the blocking messages are fragmented in enough frames
+    // to overflow the reliability backlog: none of them is dropped
+    const BATCH_SIZE: u16 = 1_024;
+    const MSG_SIZE: usize = 131_072;
+    const MSG_COUNT_BACKLOG: usize = 80;
+    let frames_per_msg = MSG_SIZE / BATCH_SIZE as usize;
+    assert!(MSG_COUNT_BACKLOG * frames_per_msg > *ZN_RELIABILITY_BACKLOG_SIZE);
+    // Run
+    task::block_on(run_with_connectors(
+        &listeners,
+        &connectors,
+        &channel,
+        &[MSG_SIZE],
+        MSG_COUNT_BACKLOG,
+        Some(BATCH_SIZE),
+        false,
+    ));
+}
+
+#[cfg(feature = "transport_tcp")]
+#[test]
+fn transport_unicast_tcp_compression() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Define the locators
+    let endpoints: Vec<EndPoint> = vec!["tcp/127.0.0.1:10454".parse().unwrap()];
+    // Define the reliability and congestion control
+    let channel = [
+        Channel {
+            priority: Priority::default(),
+            reliability: Reliability::Reliable,
+        },
+        Channel {
+            priority: Priority::RealTime,
+            reliability: Reliability::BestEffort,
+        },
+    ];
+    // Run
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, true));
+}
+
+#[cfg(feature = "transport_udp")]
+#[test]
+fn transport_unicast_udp_compression() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Define the locator
+    let endpoints: Vec<EndPoint> = vec!["udp/127.0.0.1:10455".parse().unwrap()];
+    // Define the reliability and congestion control
+    let channel = [Channel {
+        priority: Priority::default(),
+        reliability: Reliability::BestEffort,
+    }];
+    // Run
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG, true));
+}
+
+#[cfg(feature = "transport_ws")]
+#[test]
+fn transport_unicast_ws_only() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Define the locators
+    let endpoints: Vec<EndPoint> = vec![
+        "ws/127.0.0.1:10456".parse().unwrap(),
+        "ws/[::1]:10456".parse().unwrap(),
+    ];
+    // Define the reliability and congestion control
+    let channel = [
+        Channel {
+            priority: Priority::default(),
+            reliability: Reliability::Reliable,
+        },
+        Channel {
+            priority: Priority::default(),
+            reliability: Reliability::BestEffort,
+        },
+        Channel {
+            priority: Priority::RealTime,
+            reliability: Reliability::Reliable,
+        },
+        Channel {
+            priority: Priority::RealTime,
+            reliability: Reliability::BestEffort,
+        },
+    ];
+    // Run
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
+}
+
+#[cfg(all(feature = "transport_serial", target_os = "linux"))]
+#[test]
+fn transport_unicast_serial_only() {
+    use nix::pty::openpty;
+    use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+    use nix::unistd::ttyname;
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::os::unix::io::FromRawFd;
+
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Create two pseudo-terminal pairs and relay the bytes between their masters,
+    // so that the two slaves behave like the two ends of a serial line
+    let mut ptys = vec![];
+    for _ in 0..2 {
+        let pty = openpty(None, None).unwrap();
+        let mut tio = tcgetattr(pty.slave).unwrap();
+        cfmakeraw(&mut tio);
+        tcsetattr(pty.slave, SetArg::TCSANOW, &tio).unwrap();
+        let path = ttyname(pty.slave).unwrap();
+        // Keep the slaves open for the whole test to not hang up the masters
+        let master = unsafe { File::from_raw_fd(pty.master) };
+        let slave = unsafe { File::from_raw_fd(pty.slave) };
+        ptys.push((path, master, slave));
+    }
+    let relay = |mut from: File, mut to: File| {
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 1_024];
+            while let Ok(n) = from.read(&mut buffer) {
+                if n == 0 || to.write_all(&buffer[..n]).is_err() {
+                    break;
+                }
+            }
+        })
+    };
+    relay(
+        ptys[0].1.try_clone().unwrap(),
+        ptys[1].1.try_clone().unwrap(),
+    );
+    relay(
+        ptys[1].1.try_clone().unwrap(),
+        ptys[0].1.try_clone().unwrap(),
+    );
+
+    // Send what a serial line can carry in a reasonable time, still fragmenting the large messages
+    const MSG_COUNT_SERIAL: usize = 100;
+    const MSG_SIZE_SERIAL: [usize; 2] = [1_024, 8_192];
+
+    // Define the locators: the router listens on the first slave and the client opens the second one
+    let listener: EndPoint = format!("serial/{}#baudrate=115200", ptys[0].0.display())
+        .parse()
+        .unwrap();
+    let connector: EndPoint = format!("serial/{}#baudrate=115200", ptys[1].0.display())
+        .parse()
+        .unwrap();
+    // Define the reliability and congestion control
+    let channel = [
+        Channel {
+            priority: Priority::default(),
+            reliability: Reliability::Reliable,
+        },
+        Channel {
+            priority: Priority::RealTime,
+            reliability: Reliability::BestEffort,
+        },
+    ];
+    // Run
+    task::block_on(run_with_connectors(
+        &[listener],
+        &[connector],
+        &channel,
+        &MSG_SIZE_SERIAL,
+        MSG_COUNT_SERIAL,
+        None,
+        false,
+    ));
 }
 
 #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
@@ -415,11 +761,40 @@ fn transport_unicast_unix_only() {
         },
     ];
     // Run
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
     let _ = std::fs::remove_file("zenoh-test-unix-socket-5.sock");
     let _ = std::fs::remove_file("zenoh-test-unix-socket-5.sock.lock");
 }
 
+#[cfg(all(feature = "transport_unixsock-seqpacket", target_os = "linux"))]
+#[test]
+fn transport_unicast_unix_seqpacket_only() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-13.sock");
+    // Define the locator
+    let endpoints: Vec<EndPoint> = vec!["unixsock-seqpacket/zenoh-test-unix-socket-13.sock"
+        .parse()
+        .unwrap()];
+    // Define the reliability and congestion control
+    let channel = [
+        Channel {
+            priority: Priority::default(),
+            reliability: Reliability::Reliable,
+        },
+        Channel {
+            priority: Priority::RealTime,
+            reliability: Reliability::BestEffort,
+        },
+    ];
+    // Run
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-13.sock");
+    let _ = std::fs::remove_file("zenoh-test-unix-socket-13.sock.lock");
+}
+
 #[cfg(all(feature = "transport_tcp", feature = "transport_udp"))]
 #[test]
 fn transport_unicast_tcp_udp() {
@@ -446,7 +821,7 @@ fn transport_unicast_tcp_udp() {
         },
     ];
     // Run
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG, false));
 }
 
 #[cfg(all(
@@ -481,7 +856,7 @@ fn transport_unicast_tcp_unix() {
         },
     ];
     // Run
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
     let _ = std::fs::remove_file("zenoh-test-unix-socket-6.sock");
     let _ = std::fs::remove_file("zenoh-test-unix-socket-6.sock.lock");
 }
@@ -518,7 +893,7 @@ fn transport_unicast_udp_unix() {
         },
     ];
     // Run
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG, false));
     let _ = std::fs::remove_file("zenoh-test-unix-socket-7.sock");
     let _ = std::fs::remove_file("zenoh-test-unix-socket-7.sock.lock");
 }
@@ -558,7 +933,7 @@ fn transport_unicast_tcp_udp_unix() {
         },
     ];
     // Run
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_NOFRAG, false));
     let _ = std::fs::remove_file("zenoh-test-unix-socket-8.sock");
     let _ = std::fs::remove_file("zenoh-test-unix-socket-8.sock.lock");
 }
@@ -675,7 +1050,7 @@ tOzot3pwe+3SJtpk90xAQrABEO0Zh2unrC8i83ySfg==
     ];
     // Run
     let endpoints = vec![endpoint];
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
 }
 
 #[cfg(feature = "transport_quic")]
@@ -789,5 +1164,5 @@ tOzot3pwe+3SJtpk90xAQrABEO0Zh2unrC8i83ySfg==
     ];
     // Run
     let endpoints = vec![endpoint];
-    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL));
+    task::block_on(run(&endpoints, &channel, &MSG_SIZE_ALL, false));
 }